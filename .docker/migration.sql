@@ -1,6 +1,6 @@
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 
-CREATE TABLE todos (
+CREATE TABLE IF NOT EXISTS todos (
   id uuid DEFAULT uuid_generate_v4(),
   name VARCHAR NOT NULL,
   description VARCHAR NOT NULL,
@@ -8,4 +8,50 @@ CREATE TABLE todos (
   updated_at timestamptz DEFAULT NOW() NOT NULL,
   deleted_at timestamptz,
   CONSTRAINT todos_pkey PRIMARY KEY(id)
-);
\ No newline at end of file
+);
+
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS owner_id VARCHAR;
+
+ALTER TABLE todos ADD COLUMN IF NOT EXISTS completed_at timestamptz;
+
+CREATE TABLE IF NOT EXISTS api_keys (
+  id uuid DEFAULT uuid_generate_v4(),
+  name VARCHAR NOT NULL,
+  key_hash VARCHAR NOT NULL,
+  owner_id VARCHAR NOT NULL,
+  scopes VARCHAR[] NOT NULL DEFAULT '{}',
+  expires_at timestamptz,
+  last_used_at timestamptz,
+  created_at timestamptz DEFAULT NOW() NOT NULL,
+  revoked_at timestamptz,
+  CONSTRAINT api_keys_pkey PRIMARY KEY(id),
+  CONSTRAINT api_keys_key_hash_key UNIQUE(key_hash)
+);
+
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS created_by VARCHAR;
+
+CREATE TABLE IF NOT EXISTS processed_messages (
+  consumer VARCHAR NOT NULL,
+  source VARCHAR NOT NULL,
+  message_id VARCHAR NOT NULL,
+  processed_at timestamptz DEFAULT NOW() NOT NULL,
+  expires_at timestamptz NOT NULL,
+  CONSTRAINT processed_messages_pkey PRIMARY KEY(consumer, source, message_id)
+);
+
+CREATE INDEX IF NOT EXISTS processed_messages_expires_at_idx ON processed_messages (expires_at);
+
+CREATE TABLE IF NOT EXISTS todo_stats (
+  owner_id VARCHAR NOT NULL,
+  day date NOT NULL,
+  created INT DEFAULT 0 NOT NULL,
+  deleted INT DEFAULT 0 NOT NULL,
+  completed INT DEFAULT 0 NOT NULL,
+  CONSTRAINT todo_stats_pkey PRIMARY KEY(owner_id, day)
+);
+
+CREATE TABLE IF NOT EXISTS todo_stats_changes (
+  todo_id uuid NOT NULL,
+  change VARCHAR NOT NULL,
+  CONSTRAINT todo_stats_changes_pkey PRIMARY KEY(todo_id, change)
+);
@@ -0,0 +1,41 @@
+use crate::retry::queue_var;
+
+const PREFETCH_ENV_KEY: &str = "CONSUMER_PREFETCH";
+const PARALLELISM_ENV_KEY: &str = "CONSUMER_PARALLELISM";
+
+/// How many messages of a queue are fetched ahead and handled at the same time.
+///
+/// The broker sends at most `prefetch` unacked messages to the consumer, of which `parallelism`
+/// run their handler concurrently, so a `parallelism` above `prefetch` is never reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConcurrency {
+    pub prefetch: u16,
+    pub parallelism: usize,
+}
+
+impl QueueConcurrency {
+    /// Reads `CONSUMER_PREFETCH` and `CONSUMER_PARALLELISM`, each one overridable for the queue
+    /// by prefixing it with the queue name, e.g. `SIMPLE_QUEUE_CONSUMER_PREFETCH`.
+    pub fn from_env(queue: &str) -> QueueConcurrency {
+        let default = QueueConcurrency::default();
+
+        QueueConcurrency {
+            prefetch: queue_var(queue, PREFETCH_ENV_KEY)
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(default.prefetch),
+            parallelism: queue_var(queue, PARALLELISM_ENV_KEY)
+                .and_then(|p| p.parse().ok())
+                .filter(|p| *p > 0)
+                .unwrap_or(default.parallelism),
+        }
+    }
+}
+
+impl Default for QueueConcurrency {
+    fn default() -> Self {
+        QueueConcurrency {
+            prefetch: 10,
+            parallelism: 1,
+        }
+    }
+}
@@ -1,3 +1,5 @@
 mod simple;
+mod todo_stats;
 
 pub use simple::SimpleConsumer;
+pub use todo_stats::TodoStatsProjection;
@@ -1,3 +1,4 @@
+use crate::handler::EventAttributes;
 use amqp::{dispatcher::ConsumerHandler, errors::AmqpError};
 use async_trait::async_trait;
 use opentelemetry::{
@@ -45,7 +46,10 @@ impl ConsumerHandler for SimpleConsumer {
             .tracer
             .start_with_context("simple_consumer_handler", ctx);
 
-        let received = match TodoCreatedMessage::try_from(data) {
+        let received =
+            EventAttributes::of(ctx).and_then(|event| event.data_as::<TodoCreatedMessage>(data));
+
+        let received = match received {
             Err(err) => {
                 span.record_error(&err);
                 span.set_status(Status::Error {
@@ -0,0 +1,156 @@
+use crate::handler::{EventAttributes, HandlerError, TransactionalHandler};
+use amqp::errors::AmqpError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Transaction;
+use infra::repositories::TodoStatsRepositoryImpl;
+use opentelemetry::{
+    global,
+    metrics::{Histogram, Unit},
+    Context, KeyValue,
+};
+use shared::{
+    events::{TODO_COMPLETED_EVENT, TODO_CREATED_EVENT, TODO_DELETED_EVENT},
+    models::{
+        todo::{TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage},
+        todo_stats::{TodoChange, TodoStats},
+    },
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+const PROJECTION: &str = "todo_stats";
+
+/// Projects the created, deleted and completed events into `todo_stats`, counting each ToDo on
+/// the UTC day the change happened. ToDo's without an owner are not counted, and each change of
+/// a ToDo is counted once even when a rebuild already counted it.
+///
+/// The time between the event and its projection is recorded in `consumers.projection.lag`.
+pub struct TodoStatsProjection {
+    repository: Arc<TodoStatsRepositoryImpl>,
+    lag: Histogram<f64>,
+}
+
+impl TodoStatsProjection {
+    pub fn new(repository: Arc<TodoStatsRepositoryImpl>) -> Arc<TodoStatsProjection> {
+        let lag = global::meter("consumers-handler-meter")
+            .f64_histogram("consumers.projection.lag")
+            .with_description("Time Between an Event and its Projection")
+            .with_unit(Unit::new("ms"))
+            .init();
+
+        Arc::new(TodoStatsProjection { repository, lag })
+    }
+
+    /// The ToDo, the change and the counts to add for an event, none when it is not counted.
+    fn delta(
+        &self,
+        event: &EventAttributes,
+        data: &[u8],
+    ) -> Result<Option<(String, TodoChange, TodoStats)>, HandlerError> {
+        let (todo_id, owner_id, at, change) = match event.ty.as_str() {
+            TODO_CREATED_EVENT => {
+                let message = event.data_as::<TodoCreatedMessage>(data)?;
+                (
+                    message.id,
+                    message.owner_id,
+                    message.created_at,
+                    TodoChange::Created,
+                )
+            }
+            TODO_DELETED_EVENT => {
+                let message = event.data_as::<TodoDeletedMessage>(data)?;
+                (
+                    message.id,
+                    message.owner_id,
+                    message.deleted_at,
+                    TodoChange::Deleted,
+                )
+            }
+            TODO_COMPLETED_EVENT => {
+                let message = event.data_as::<TodoCompletedMessage>(data)?;
+                (
+                    message.id,
+                    message.owner_id,
+                    message.completed_at,
+                    TodoChange::Completed,
+                )
+            }
+            _ => return Ok(None),
+        };
+
+        let owner_id = match owner_id {
+            None => return Ok(None),
+            Some(o) => o,
+        };
+
+        let day = parse_time(&at)?.format("%Y-%m-%d").to_string();
+
+        Ok(Some((todo_id, change, change.delta(owner_id, day))))
+    }
+}
+
+#[async_trait]
+impl TransactionalHandler for TodoStatsProjection {
+    async fn exec(
+        &self,
+        ctx: &Context,
+        tx: &Transaction<'_>,
+        data: &[u8],
+    ) -> Result<(), HandlerError> {
+        let event = EventAttributes::of(ctx)?;
+
+        let (todo_id, change, delta) = match self.delta(&event, data)? {
+            None => {
+                info!(
+                    projection = PROJECTION,
+                    event_type = event.ty.as_str(),
+                    "event not projected"
+                );
+                return Ok(());
+            }
+            Some(d) => d,
+        };
+
+        let recorded = self
+            .repository
+            .record(ctx, tx, &todo_id, change, &delta)
+            .await
+            .map_err(HandlerError::Database)?;
+        if !recorded {
+            info!(
+                projection = PROJECTION,
+                todo_id = todo_id.as_str(),
+                change = change.as_str(),
+                "change already projected"
+            );
+            return Ok(());
+        }
+
+        if let Some(time) = event.time.as_deref().and_then(|t| parse_time(t).ok()) {
+            let lag = Utc::now().signed_duration_since(time).num_milliseconds();
+            self.lag.record(
+                ctx,
+                lag.max(0) as f64,
+                &[
+                    KeyValue::new("projection", PROJECTION),
+                    KeyValue::new("event_type", event.ty),
+                ],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>, HandlerError> {
+    match DateTime::parse_from_rfc3339(time) {
+        Err(err) => {
+            error!(error = err.to_string(), time = time, "invalid event time");
+            Err(HandlerError::Message(
+                AmqpError::AckMessageDeserializationError(err.to_string()),
+            ))
+        }
+        Ok(t) => Ok(t.with_timezone(&Utc)),
+    }
+}
@@ -0,0 +1,466 @@
+use crate::{
+    concurrency::QueueConcurrency,
+    handler::{ErrorClass, EventAttributes, EventHandler, HandlerError, MessageId},
+    retry::{self, RetryPolicy},
+    shutdown::Shutdown,
+};
+use futures_util::{future::join_all, stream::FuturesUnordered, StreamExt};
+use lapin::{
+    message::Delivery,
+    options::{
+        BasicAckOptions, BasicCancelOptions, BasicConsumeOptions, BasicNackOptions,
+        BasicPublishOptions, BasicQosOptions,
+    },
+    types::{AMQPValue, DeliveryTag, FieldTable, LongString, ShortString},
+    BasicProperties, Channel, Connection,
+};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    trace::{SpanKind, Status, TraceContextExt, Tracer},
+    KeyValue,
+};
+use shared::{
+    amqp::{decode_event, dlq_name, event_context, EnvelopeError},
+    codecs::codec_for,
+    events::Event,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::Arc,
+};
+use tracing::{error, info, warn};
+
+/// Header carrying why the dispatcher dead-lettered a message itself.
+pub const DLQ_REASON_HEADER: &str = "x-dlq-reason";
+
+struct Registration {
+    version: u32,
+    handler: Arc<dyn EventHandler>,
+}
+
+type Handlers = HashMap<String, Registration>;
+
+#[derive(Debug)]
+pub enum DispatchError {
+    /// The message can never be handled, it goes straight to the DLQ with the reason.
+    Rejected(EnvelopeError),
+    /// The handler failed, the message is retried or dead-lettered.
+    Failed(HandlerError),
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchError::Rejected(err) => write!(f, "message rejected: {}", err),
+            DispatchError::Failed(err) => write!(f, "handler failed: {}", err),
+        }
+    }
+}
+
+/// Routes CloudEvents to the handler registered for their queue and `type`, upcasting the data
+/// to the schema version the handler was built with. Handlers get the data encoded with the codec
+/// of its `datacontenttype`, which `EventAttributes::data_as` reads back.
+///
+/// Envelopes that are not CloudEvents, whose type has no handler on the queue, or whose version
+/// can not be upcasted, are published to the queue DLQ with an `x-dlq-reason` header and acked,
+/// so they are never retried. So are messages whose handler failed with a permanent error, while
+/// transient errors are retried following the queue `RetryPolicy` until its attempts run out.
+///
+/// Every queue is consumed on its own channel, fetching and handling messages as set by its
+/// `QueueConcurrency`.
+pub struct EventDispatcher {
+    tracer: BoxedTracer,
+    queues: HashMap<String, Handlers>,
+    retry_policies: HashMap<String, RetryPolicy>,
+    concurrency: HashMap<String, QueueConcurrency>,
+}
+
+impl EventDispatcher {
+    pub fn new() -> EventDispatcher {
+        EventDispatcher {
+            tracer: global::tracer("consumers-dispatcher"),
+            queues: HashMap::new(),
+            retry_policies: HashMap::new(),
+            concurrency: HashMap::new(),
+        }
+    }
+
+    /// Prefetch and parallelism of `queue`, `QueueConcurrency::default()` when not set.
+    pub fn concurrency(mut self, queue: &str, concurrency: QueueConcurrency) -> Self {
+        self.concurrency.insert(queue.to_owned(), concurrency);
+        self
+    }
+
+    /// Retries the transient failures of `queue`, whose delay queues must be declared through
+    /// `RetryTopology::retry_policy`. Without a policy they are dead-lettered right away.
+    pub fn retry_policy(mut self, queue: &str, policy: RetryPolicy) -> Self {
+        self.retry_policies.insert(queue.to_owned(), policy);
+        self
+    }
+
+    pub fn register<E: Event>(mut self, queue: &str, handler: Arc<dyn EventHandler>) -> Self {
+        self.queues.entry(queue.to_owned()).or_default().insert(
+            E::TYPE.to_owned(),
+            Registration {
+                version: E::VERSION,
+                handler,
+            },
+        );
+        self
+    }
+
+    /// Decodes the envelope and runs the handler inside a Consumer span continuing the
+    /// publisher's trace.
+    pub async fn dispatch(
+        &self,
+        queue: &str,
+        body: &[u8],
+        properties: &BasicProperties,
+    ) -> Result<(), DispatchError> {
+        let event = decode_event(body, properties).map_err(DispatchError::Rejected)?;
+
+        let registration = self
+            .queues
+            .get(queue)
+            .and_then(|handlers| handlers.get(&event.ty))
+            .ok_or_else(|| DispatchError::Rejected(EnvelopeError::UnknownType(event.ty.clone())))?;
+
+        let event = event
+            .upcast(registration.version)
+            .map_err(DispatchError::Rejected)?;
+        // handlers get the upcasted data in the codec the message traveled with
+        let datacontenttype = event.datacontenttype.clone();
+        let codec = codec_for(datacontenttype.as_deref()).ok_or_else(|| {
+            DispatchError::Rejected(EnvelopeError::UnsupportedContentType(
+                datacontenttype.clone().unwrap_or_default(),
+            ))
+        })?;
+        let data = codec
+            .encode_data(&event.ty, &event.data)
+            .map_err(|err| DispatchError::Rejected(EnvelopeError::InvalidData(err.to_string())))?;
+
+        let parent = event_context(properties, &event);
+        let span = self
+            .tracer
+            .span_builder(format!("{} process", queue))
+            .with_kind(SpanKind::Consumer)
+            .with_attributes(vec![
+                KeyValue::new("messaging.system", "rabbitmq"),
+                KeyValue::new("messaging.source.name", queue.to_owned()),
+                KeyValue::new("messaging.message.id", event.id.clone()),
+                KeyValue::new("cloudevents.event_type", event.ty.clone()),
+                KeyValue::new("cloudevents.event_source", event.source.clone()),
+            ])
+            .start_with_context(&self.tracer, &parent);
+        let ctx = parent
+            .with_span(span)
+            .with_value(MessageId {
+                source: event.source.clone(),
+                id: event.id.clone(),
+            })
+            .with_value(EventAttributes {
+                ty: event.ty.clone(),
+                time: event.time.clone(),
+                datacontenttype,
+            });
+
+        let result = match registration.handler.exec(&ctx, &data).await {
+            Err(err) => {
+                ctx.span().set_status(Status::Error {
+                    description: Cow::from("error to handle message"),
+                });
+                Err(DispatchError::Failed(err))
+            }
+            Ok(_) => Ok(()),
+        };
+
+        ctx.span().end();
+        result
+    }
+
+    /// Consumes every registered queue until the shutdown is triggered or their consumers are
+    /// cancelled.
+    ///
+    /// On shutdown each queue stops fetching and its messages in flight get the drain timeout
+    /// to finish. Whatever is left, in flight or prefetched, is nacked back to the queue.
+    pub async fn consume_blocking(
+        &self,
+        connection: Arc<Connection>,
+        shutdown: &Shutdown,
+    ) -> Vec<Result<(), lapin::Error>> {
+        join_all(
+            self.queues
+                .keys()
+                .map(|queue| self.consume(&connection, queue, shutdown)),
+        )
+        .await
+    }
+
+    async fn consume(
+        &self,
+        connection: &Connection,
+        queue: &str,
+        shutdown: &Shutdown,
+    ) -> Result<(), lapin::Error> {
+        let concurrency = self.concurrency.get(queue).copied().unwrap_or_default();
+
+        let channel = connection.create_channel().await?;
+        channel
+            .basic_qos(concurrency.prefetch, BasicQosOptions::default())
+            .await?;
+
+        let consumer_tag = format!("{}-consumer", queue);
+        let mut consumer = channel
+            .basic_consume(
+                queue,
+                &consumer_tag,
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+
+        let mut in_flight = FuturesUnordered::new();
+        let mut unsettled: HashSet<DeliveryTag> = HashSet::new();
+        let stopped = shutdown.wait();
+        tokio::pin!(stopped);
+
+        loop {
+            tokio::select! {
+                _ = &mut stopped => break,
+                Some(tag) = in_flight.next() => {
+                    unsettled.remove(&tag);
+                }
+                delivery = consumer.next(), if in_flight.len() < concurrency.parallelism => {
+                    match delivery {
+                        None => break,
+                        Some(Err(err)) => {
+                            error!(error = err.to_string(), "error to receive message");
+                        }
+                        Some(Ok(delivery)) => {
+                            unsettled.insert(delivery.delivery_tag);
+                            in_flight.push(self.handle(&channel, queue, delivery));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !shutdown.is_draining() {
+            // the broker cancelled the consumer, the messages in flight can still be settled
+            while in_flight.next().await.is_some() {}
+            return Ok(());
+        }
+
+        info!(
+            queue = queue,
+            in_flight = in_flight.len(),
+            "draining consumer"
+        );
+
+        if let Err(err) = channel
+            .basic_cancel(&consumer_tag, BasicCancelOptions::default())
+            .await
+        {
+            error!(error = err.to_string(), "error to cancel consumer");
+        }
+
+        let drained = tokio::time::timeout(shutdown.drain_timeout(), async {
+            while let Some(tag) = in_flight.next().await {
+                unsettled.remove(&tag);
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            warn!(
+                queue = queue,
+                unfinished = unsettled.len(),
+                "drain timed out, nacking unfinished messages"
+            );
+        }
+
+        // the unfinished handlers are dropped before their messages are nacked, so none of them
+        // settles a message afterwards
+        drop(in_flight);
+
+        // delivery tag 0 with `multiple` nacks every message delivered to the channel and not
+        // settled yet, the unfinished ones and the prefetched ones the handlers never got
+        channel
+            .basic_nack(
+                0,
+                BasicNackOptions {
+                    multiple: true,
+                    requeue: true,
+                },
+            )
+            .await?;
+        channel.close(200, "consumer drained").await
+    }
+
+    /// Dispatches the delivery and settles it, returning its tag.
+    async fn handle(&self, channel: &Channel, queue: &str, delivery: Delivery) -> DeliveryTag {
+        let result = match self
+            .dispatch(queue, &delivery.data, &delivery.properties)
+            .await
+        {
+            Ok(_) => delivery.ack(BasicAckOptions::default()).await,
+            Err(DispatchError::Rejected(reason)) => {
+                warn!(
+                    queue = queue,
+                    reason = reason.to_string(),
+                    "rejecting message"
+                );
+                dead_letter(channel, queue, &delivery, &reason.to_string()).await
+            }
+            Err(DispatchError::Failed(err)) => {
+                error!(
+                    queue = queue,
+                    error = err.to_string(),
+                    "error to handle message"
+                );
+                self.retry(channel, queue, &delivery, &err).await
+            }
+        };
+
+        if let Err(err) = result {
+            error!(error = err.to_string(), "error to settle message");
+        }
+
+        delivery.delivery_tag
+    }
+
+    /// Sends a transient failure to the delay queue of its next attempt, anything else to the
+    /// DLQ.
+    async fn retry(
+        &self,
+        channel: &Channel,
+        queue: &str,
+        delivery: &Delivery,
+        err: &HandlerError,
+    ) -> Result<(), lapin::Error> {
+        let reason = match self.next_step(queue, &delivery.properties, err) {
+            NextStep::Delay(policy, attempt) => {
+                match delay(channel, queue, delivery, policy, attempt).await {
+                    Ok(_) => return delivery.ack(BasicAckOptions::default()).await,
+                    Err(publish_err) => {
+                        error!(
+                            error = publish_err.to_string(),
+                            "error to send message to the delay queue"
+                        );
+                        format!("error to retry: {}", err)
+                    }
+                }
+            }
+            NextStep::DeadLetter(reason) => reason,
+        };
+
+        dead_letter(channel, queue, delivery, &reason).await
+    }
+
+    /// Where a delivery of `queue` whose handler failed with `err` goes next.
+    pub(crate) fn next_step(
+        &self,
+        queue: &str,
+        properties: &BasicProperties,
+        err: &HandlerError,
+    ) -> NextStep<'_> {
+        let attempt = retry::attempts(properties) + 1;
+
+        match (err.class(), self.retry_policies.get(queue)) {
+            (ErrorClass::Transient, Some(policy)) if attempt <= policy.max_attempts => {
+                NextStep::Delay(policy, attempt)
+            }
+            (ErrorClass::Transient, Some(_)) => {
+                NextStep::DeadLetter(format!("retries exhausted: {}", err))
+            }
+            _ => NextStep::DeadLetter(err.to_string()),
+        }
+    }
+}
+
+/// What the dispatcher does with a delivery whose handler failed.
+#[derive(Debug, PartialEq)]
+pub(crate) enum NextStep<'a> {
+    /// Sent to the delay queue of the attempt.
+    Delay(&'a RetryPolicy, u32),
+    /// Sent to the DLQ with the reason.
+    DeadLetter(String),
+}
+
+impl Default for EventDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn delay(
+    channel: &Channel,
+    queue: &str,
+    delivery: &Delivery,
+    policy: &RetryPolicy,
+    attempt: u32,
+) -> Result<(), lapin::Error> {
+    channel
+        .basic_publish(
+            "",
+            &RetryPolicy::delay_queue(queue, attempt),
+            BasicPublishOptions::default(),
+            &delivery.data,
+            policy.delayed(&delivery.properties, attempt),
+        )
+        .await?
+        .await?;
+
+    Ok(())
+}
+
+/// Publishes the delivery to the queue DLQ with the reason and acks it, or nacks it without
+/// requeue when that fails so the broker dead-letters it into the DLQ set by `with_dlq`.
+async fn dead_letter(
+    channel: &Channel,
+    queue: &str,
+    delivery: &Delivery,
+    reason: &str,
+) -> Result<(), lapin::Error> {
+    match publish_dead_letter(channel, queue, &delivery.data, &delivery.properties, reason).await {
+        Ok(_) => delivery.ack(BasicAckOptions::default()).await,
+        Err(err) => {
+            error!(error = err.to_string(), "error to dead-letter message");
+            delivery
+                .nack(BasicNackOptions {
+                    requeue: false,
+                    ..Default::default()
+                })
+                .await
+        }
+    }
+}
+
+async fn publish_dead_letter(
+    channel: &Channel,
+    queue: &str,
+    body: &[u8],
+    properties: &BasicProperties,
+    reason: &str,
+) -> Result<(), lapin::Error> {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    headers.insert(
+        ShortString::from(DLQ_REASON_HEADER),
+        AMQPValue::LongString(LongString::from(reason)),
+    );
+
+    channel
+        .basic_publish(
+            "",
+            &dlq_name(queue),
+            BasicPublishOptions::default(),
+            body,
+            properties.clone().with_headers(headers),
+        )
+        .await?
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use amqp::{dispatcher::ConsumerHandler, errors::AmqpError};
+use async_trait::async_trait;
+use deadpool_postgres::Transaction;
+use opentelemetry::Context;
+use shared::{codecs::codec_for, events::Event};
+use std::fmt;
+use tracing::error;
+
+/// Why a handler could not process a message.
+#[derive(Debug)]
+pub enum HandlerError {
+    Message(AmqpError),
+    Database(String),
+    MissingMessageId,
+}
+
+/// Whether retrying a failed message can ever succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The message itself is wrong, it goes straight to the DLQ.
+    Permanent,
+    /// Something the message depends on failed, it is retried following the queue policy.
+    Transient,
+}
+
+impl HandlerError {
+    /// Only database failures are retried. An `AmqpError` from a `ConsumerHandler` can not tell
+    /// a wrong message from a failing dependency, so retrying it would only delay the DLQ.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            HandlerError::Message(_) => ErrorClass::Permanent,
+            HandlerError::Database(_) => ErrorClass::Transient,
+            HandlerError::MissingMessageId => ErrorClass::Permanent,
+        }
+    }
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerError::Message(err) => write!(f, "{}", err),
+            HandlerError::Database(err) => write!(f, "database error: {}", err),
+            HandlerError::MissingMessageId => write!(f, "message without id in the context"),
+        }
+    }
+}
+
+impl From<AmqpError> for HandlerError {
+    fn from(value: AmqpError) -> Self {
+        HandlerError::Message(value)
+    }
+}
+
+/// Identity of the message being handled, set by the `EventDispatcher` in the handler context.
+#[derive(Debug, Clone)]
+pub struct MessageId {
+    pub source: String,
+    pub id: String,
+}
+
+/// CloudEvents `type`, `time` and `datacontenttype` of the message being handled, set by the
+/// `EventDispatcher` in the handler context.
+#[derive(Debug, Clone)]
+pub struct EventAttributes {
+    pub ty: String,
+    pub time: Option<String>,
+    /// Codec of the data handed to the handler, the one the message traveled with. JSON when
+    /// none.
+    pub datacontenttype: Option<String>,
+}
+
+impl EventAttributes {
+    /// Reads the data handed to the handler with the codec of the event.
+    pub fn data_as<E: Event>(&self, data: &[u8]) -> Result<E, AmqpError> {
+        let decoded = match codec_for(self.datacontenttype.as_deref()) {
+            None => Err(format!(
+                "unsupported datacontenttype: {}",
+                self.datacontenttype.as_deref().unwrap_or_default()
+            )),
+            Some(codec) => codec.decode_as::<E>(data).map_err(|err| err.to_string()),
+        };
+
+        match decoded {
+            Err(err) => {
+                error!(error = err, event_type = self.ty.as_str(), "parsing error");
+                Err(AmqpError::AckMessageDeserializationError(err))
+            }
+            Ok(v) => Ok(v),
+        }
+    }
+
+    /// The attributes set by the dispatcher in `ctx`.
+    pub fn of(ctx: &Context) -> Result<EventAttributes, AmqpError> {
+        match ctx.get::<EventAttributes>() {
+            None => {
+                error!("message without event attributes");
+                Err(AmqpError::AckMessageDeserializationError(
+                    "message without event attributes".to_owned(),
+                ))
+            }
+            Some(e) => Ok(e.clone()),
+        }
+    }
+}
+
+/// A handler run by the `EventDispatcher`. Every `ConsumerHandler` is one.
+#[async_trait]
+pub trait EventHandler: Send + Sync {
+    async fn exec(&self, ctx: &Context, data: &[u8]) -> Result<(), HandlerError>;
+}
+
+#[async_trait]
+impl<T: ConsumerHandler + Send + Sync> EventHandler for T {
+    async fn exec(&self, ctx: &Context, data: &[u8]) -> Result<(), HandlerError> {
+        ConsumerHandler::exec(self, ctx, data)
+            .await
+            .map_err(HandlerError::Message)
+    }
+}
+
+/// A handler whose database work runs in a transaction owned by the caller, committed only if
+/// the handler succeeds. Every `ConsumerHandler` is one that does not touch the transaction.
+#[async_trait]
+pub trait TransactionalHandler: Send + Sync {
+    async fn exec(
+        &self,
+        ctx: &Context,
+        tx: &Transaction<'_>,
+        data: &[u8],
+    ) -> Result<(), HandlerError>;
+}
+
+#[async_trait]
+impl<T: ConsumerHandler + Send + Sync> TransactionalHandler for T {
+    async fn exec(
+        &self,
+        ctx: &Context,
+        _tx: &Transaction<'_>,
+        data: &[u8],
+    ) -> Result<(), HandlerError> {
+        ConsumerHandler::exec(self, ctx, data)
+            .await
+            .map_err(HandlerError::Message)
+    }
+}
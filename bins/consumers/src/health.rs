@@ -0,0 +1,134 @@
+use crate::shutdown::Shutdown;
+use actix_web::{
+    dev::ServerHandle, get, http::StatusCode, web::Data, App, HttpResponse, HttpServer, Responder,
+};
+use configs::HealthReadinessConfigs;
+use deadpool_postgres::Pool;
+use health_readiness::{HealthReadinessService, HealthReadinessServiceImpl};
+use lapin::Connection;
+use serde::Serialize;
+use std::{io, sync::Arc};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unhealthy,
+    Draining,
+}
+
+impl HealthStatus {
+    /// Status of the instance, `Draining` from the moment the shutdown is triggered whatever its
+    /// dependencies report.
+    pub fn of(draining: bool, healthy: bool) -> HealthStatus {
+        match (draining, healthy) {
+            (true, _) => HealthStatus::Draining,
+            (_, false) => HealthStatus::Unhealthy,
+            _ => HealthStatus::Ok,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            HealthStatus::Ok => StatusCode::OK,
+            _ => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Clone)]
+struct HealthState {
+    shutdown: Shutdown,
+    checker: Arc<dyn HealthReadinessService>,
+}
+
+/// Readiness endpoint of the consumers, `GET /health` on the health readiness port.
+///
+/// Runs the same `HealthReadinessServiceImpl` checks as the `HealthReadinessServer` of the
+/// other binaries, answering 200 `ok` when they pass and 503 otherwise, with `draining` from the
+/// moment the shutdown is triggered so no new work is routed to the instance.
+pub struct HealthServer {
+    enabled: bool,
+    port: u16,
+    shutdown: Shutdown,
+    checker: HealthReadinessServiceImpl,
+}
+
+impl HealthServer {
+    pub fn new(cfg: &HealthReadinessConfigs, shutdown: Shutdown) -> HealthServer {
+        HealthServer {
+            enabled: cfg.enable,
+            port: cfg.port as u16,
+            shutdown,
+            checker: HealthReadinessServiceImpl::default(),
+        }
+    }
+
+    pub fn rabbitmq(mut self, conn: Arc<Connection>) -> Self {
+        self.checker = self.checker.amqp(conn);
+        self
+    }
+
+    pub fn postgres(mut self, pool: Arc<Pool>) -> Self {
+        self.checker = self.checker.postgres(pool);
+        self
+    }
+
+    /// Serves the endpoint in the background until the returned handle stops it, `None` when
+    /// it is disabled. Signals are left to `Shutdown`, so the endpoint keeps answering while the
+    /// consumers drain.
+    pub fn start(self) -> io::Result<Option<ServerHandle>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let state = HealthState {
+            shutdown: self.shutdown,
+            checker: Arc::new(self.checker),
+        };
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(Data::new(state.clone()))
+                .service(health)
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("0.0.0.0", self.port))?
+        .run();
+
+        let handle = server.handle();
+        tokio::spawn(server);
+        info!(port = self.port, "health readiness listening");
+
+        Ok(Some(handle))
+    }
+}
+
+#[get("/health")]
+async fn health(state: Data<HealthState>) -> impl Responder {
+    let draining = state.shutdown.is_draining();
+
+    // a draining instance is unready whatever its dependencies say
+    let error = match draining {
+        true => None,
+        false => match state.checker.validate().await {
+            Err(err) => {
+                warn!(error = err.to_string(), "health check failed");
+                Some(err.to_string())
+            }
+            Ok(_) => None,
+        },
+    };
+
+    let status = HealthStatus::of(draining, error.is_none());
+
+    HttpResponse::build(status.status_code()).json(HealthResponse { status, error })
+}
@@ -0,0 +1,166 @@
+use crate::{
+    handler::{EventHandler, HandlerError, MessageId, TransactionalHandler},
+    shutdown::Shutdown,
+};
+use async_trait::async_trait;
+use deadpool_postgres::Pool;
+use infra::repositories::ProcessedMessageRepository;
+use opentelemetry::{global, metrics::Counter, Context, KeyValue};
+use std::{env, sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+const IDEMPOTENCY_TTL_ENV_KEY: &str = "IDEMPOTENCY_TTL_SECS";
+const IDEMPOTENCY_PURGE_INTERVAL_ENV_KEY: &str = "IDEMPOTENCY_PURGE_INTERVAL_SECS";
+
+/// How long processed message ids are remembered, and how often expired ones are purged.
+pub struct IdempotencyConfigs {
+    pub ttl: Duration,
+    pub purge_interval: Duration,
+}
+
+impl IdempotencyConfigs {
+    pub fn from_env() -> IdempotencyConfigs {
+        let secs = |key: &str, default: u64| {
+            Duration::from_secs(
+                env::var(key)
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default),
+            )
+        };
+
+        IdempotencyConfigs {
+            ttl: secs(IDEMPOTENCY_TTL_ENV_KEY, 86400),
+            purge_interval: secs(IDEMPOTENCY_PURGE_INTERVAL_ENV_KEY, 3600),
+        }
+    }
+}
+
+/// Runs `inner` at most once per message within the TTL.
+///
+/// The message id is recorded in `processed_messages` in the same transaction as the handler's
+/// own database work, so a redelivery after a failure is processed again while a redelivery
+/// after a commit is skipped and counted in `consumers.messages.duplicates`.
+pub struct Idempotent<H> {
+    consumer: &'static str,
+    pool: Arc<Pool>,
+    store: Arc<ProcessedMessageRepository>,
+    inner: Arc<H>,
+    ttl: Duration,
+    duplicates: Counter<u64>,
+}
+
+impl<H: TransactionalHandler> Idempotent<H> {
+    pub fn new(
+        consumer: &'static str,
+        pool: Arc<Pool>,
+        store: Arc<ProcessedMessageRepository>,
+        inner: Arc<H>,
+        cfg: &IdempotencyConfigs,
+    ) -> Arc<Idempotent<H>> {
+        let duplicates = global::meter("consumers-handler-meter")
+            .u64_counter("consumers.messages.duplicates")
+            .with_description("Consumer Messages Skipped as Duplicates")
+            .init();
+
+        Arc::new(Idempotent {
+            consumer,
+            pool,
+            store,
+            inner,
+            ttl: cfg.ttl,
+            duplicates,
+        })
+    }
+}
+
+#[async_trait]
+impl<H: TransactionalHandler> EventHandler for Idempotent<H> {
+    async fn exec(&self, ctx: &Context, data: &[u8]) -> Result<(), HandlerError> {
+        let message = match ctx.get::<MessageId>() {
+            None => {
+                error!(consumer = self.consumer, "message without id");
+                Err(HandlerError::MissingMessageId)
+            }
+            Some(m) => Ok(m.clone()),
+        }?;
+
+        let mut conn = match self.pool.get().await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get connection from poll");
+                Err(HandlerError::Database(err.to_string()))
+            }
+            Ok(c) => Ok(c),
+        }?;
+
+        let tx = match conn.transaction().await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to begin transaction");
+                Err(HandlerError::Database(err.to_string()))
+            }
+            Ok(tx) => Ok(tx),
+        }?;
+
+        let first_delivery = self
+            .store
+            .mark(
+                ctx,
+                &tx,
+                self.consumer,
+                &message.source,
+                &message.id,
+                self.ttl,
+            )
+            .await
+            .map_err(HandlerError::Database)?;
+
+        if !first_delivery {
+            // dropping the transaction rolls it back
+            self.duplicates
+                .add(ctx, 1, &[KeyValue::new("consumer", self.consumer)]);
+            info!(
+                consumer = self.consumer,
+                message_id = message.id.as_str(),
+                "skipping duplicated message"
+            );
+            return Ok(());
+        }
+
+        TransactionalHandler::exec(self.inner.as_ref(), ctx, &tx, data).await?;
+
+        match tx.commit().await {
+            Err(err) => {
+                error!(
+                    error = err.to_string(),
+                    "error to commit message processing"
+                );
+                Err(HandlerError::Database(err.to_string()))
+            }
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+/// Purges expired message ids every `period` until the shutdown is triggered.
+pub async fn purge_expired(
+    store: Arc<ProcessedMessageRepository>,
+    period: Duration,
+    shutdown: Shutdown,
+) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = shutdown.wait() => {
+                info!("stopping the processed messages purge");
+                return;
+            }
+            _ = interval.tick() => {}
+        }
+
+        if let Err(err) = store.purge_expired(&Context::new()).await {
+            warn!(error = err, "error to purge processed messages");
+        }
+    }
+}
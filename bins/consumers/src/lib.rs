@@ -0,0 +1,22 @@
+mod concurrency;
+mod consumers;
+mod dispatcher;
+mod handler;
+mod health;
+mod idempotent;
+mod retry;
+mod shutdown;
+
+#[cfg(test)]
+mod tests;
+
+pub use concurrency::QueueConcurrency;
+pub use consumers::{SimpleConsumer, TodoStatsProjection};
+pub use dispatcher::{DispatchError, EventDispatcher, DLQ_REASON_HEADER};
+pub use handler::{
+    ErrorClass, EventAttributes, EventHandler, HandlerError, MessageId, TransactionalHandler,
+};
+pub use health::{HealthServer, HealthStatus};
+pub use idempotent::{purge_expired, IdempotencyConfigs, Idempotent};
+pub use retry::{RetryPolicy, RetryTopology, RETRY_ATTEMPT_HEADER};
+pub use shutdown::Shutdown;
@@ -1,62 +1,165 @@
-mod consumers;
-
 use amqp::{
     channel,
-    dispatcher::{AmqpDispatcher, Dispatcher},
     exchange::ExchangeDefinition,
     queue::{QueueBinding, QueueDefinition},
-    topology::{AmqpTopology, Topology},
+    topology::Topology,
 };
 use configs::{Configs, Empty};
 use configs_builder::ConfigBuilder;
-use consumers::SimpleConsumer;
-use health_readiness::HealthReadinessServer;
-use lapin::{Channel, Connection};
-use opentelemetry::{global, Context};
+use consumers::{
+    purge_expired, EventDispatcher, HealthServer, IdempotencyConfigs, Idempotent, QueueConcurrency,
+    RetryPolicy, RetryTopology, Shutdown, SimpleConsumer, TodoStatsProjection,
+};
+use deadpool_postgres::Pool;
+use infra::{
+    health::HealthMeter,
+    repositories::{ProcessedMessageRepository, SlowQueryConfigs, TodoStatsRepositoryImpl},
+    telemetry::{self, TelemetryExporter},
+};
+use lapin::Connection;
+use opentelemetry::Context;
 use shared::{
-    amqp::{EXCHANGE, ROUTING_KEY},
-    models::todo::TodoCreatedMessage,
+    amqp::{
+        EXCHANGE, TODO_COMPLETED_ROUTING_KEY, TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY,
+    },
+    models::todo::{TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage},
 };
 use sql_pool::postgres::conn_pool;
-use std::{error::Error, sync::Arc};
-use tracing::error;
+use std::{env, error::Error, sync::Arc};
+use tracing::{error, info};
 
 pub const QUEUE: &str = "simple-queue";
+pub const TODO_STATS_QUEUE: &str = "todo-stats-queue";
+
+/// `consumers rebuild-todo-stats` recomputes the `todo_stats` projection and exits.
+const REBUILD_TODO_STATS_COMMAND: &str = "rebuild-todo-stats";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let cfg = default_setup().await?;
 
-    let (conn, channel, queue) = amqp_setup(&cfg).await?;
+    let result = run(&cfg).await;
+    if let Err(err) = &result {
+        error!(error = err.to_string(), "consumers stopped with an error");
+    }
+
+    telemetry::shutdown();
+    result
+}
+
+async fn run(cfg: &Configs<Empty>) -> Result<(), Box<dyn Error>> {
     let db_conn = Arc::new(conn_pool(&cfg.postgres)?);
+    let slow_query = SlowQueryConfigs::from_env();
+    if env::args().nth(1).as_deref() == Some(REBUILD_TODO_STATS_COMMAND) {
+        return rebuild_todo_stats(cfg, db_conn, &slow_query).await;
+    }
+
+    // listening before connecting, so a SIGTERM at any point still returns through main and
+    // flushes the telemetry
+    let shutdown = Shutdown::from_env();
+    tokio::spawn(shutdown.clone().on_signal());
+
+    let retry = RetryPolicy::from_env(QUEUE);
+    let todo_stats_retry = RetryPolicy::from_env(TODO_STATS_QUEUE);
+    let conn = tokio::select! {
+        conn = amqp_setup(
+            cfg,
+            &[(QUEUE, &retry), (TODO_STATS_QUEUE, &todo_stats_retry)],
+        ) => conn?,
+        _ = shutdown.wait() => {
+            info!("consumers stopped before connecting");
+            return Ok(());
+        }
+    };
 
-    let handler = SimpleConsumer::new();
+    let idempotency = IdempotencyConfigs::from_env();
+    let processed_messages =
+        ProcessedMessageRepository::new(db_conn.clone(), &cfg.postgres, &slow_query);
+    let purge = tokio::spawn(purge_expired(
+        processed_messages.clone(),
+        idempotency.purge_interval,
+        shutdown.clone(),
+    ));
+
+    let handler = Idempotent::new(
+        "simple-consumer",
+        db_conn.clone(),
+        processed_messages.clone(),
+        SimpleConsumer::new(),
+        &idempotency,
+    );
+
+    let todo_stats = Idempotent::new(
+        "todo-stats-projection",
+        db_conn.clone(),
+        processed_messages,
+        TodoStatsProjection::new(TodoStatsRepositoryImpl::new(
+            db_conn.clone(),
+            &cfg.postgres,
+            &slow_query,
+        )),
+        &idempotency,
+    );
+
+    let dispatcher = EventDispatcher::new()
+        .register::<TodoCreatedMessage>(QUEUE, handler)
+        .register::<TodoCreatedMessage>(TODO_STATS_QUEUE, todo_stats.clone())
+        .register::<TodoDeletedMessage>(TODO_STATS_QUEUE, todo_stats.clone())
+        .register::<TodoCompletedMessage>(TODO_STATS_QUEUE, todo_stats)
+        .retry_policy(QUEUE, retry)
+        .retry_policy(TODO_STATS_QUEUE, todo_stats_retry)
+        .concurrency(QUEUE, QueueConcurrency::from_env(QUEUE))
+        .concurrency(
+            TODO_STATS_QUEUE,
+            QueueConcurrency::from_env(TODO_STATS_QUEUE),
+        );
+
+    HealthMeter::new("consumers-meter", "consumers")
+        .rabbitmq(conn.clone())
+        .postgres(db_conn.clone())
+        .install()?;
+
+    let health = HealthServer::new(&cfg.health_readiness, shutdown.clone())
+        .rabbitmq(conn.clone())
+        .postgres(db_conn.clone())
+        .start()?;
+
+    let errors: Vec<lapin::Error> = dispatcher
+        .consume_blocking(conn.clone(), &shutdown)
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+
+    for err in &errors {
+        error!(error = err.to_string(), "error to consume queue");
+    }
 
-    let dispatcher =
-        AmqpDispatcher::new(channel).register(&queue, &TodoCreatedMessage::default(), handler);
+    if let Some(health) = health {
+        health.stop(true).await;
+    }
 
-    let health_readiness = HealthReadinessServer::new(&cfg.health_readiness)
-        .rabbitmq(conn)
-        .postgres(db_conn.clone());
+    if let Err(err) = purge.await {
+        error!(
+            error = err.to_string(),
+            "error to stop the processed messages purge"
+        );
+    }
 
-    declare_health_meter()?;
+    if let Err(err) = conn.close(200, "consumers stopped").await {
+        error!(
+            error = err.to_string(),
+            "error to close the amqp connection"
+        );
+    }
 
-    match tokio::join!(health_readiness.run(), dispatcher.consume_blocking()) {
-        (Err(e), _) => {
-            error!(error = e.to_string(), "error");
-            panic!("{:?}", e)
-        }
-        (Ok(_), errors) => {
-            for err in errors {
-                if err.is_err() {
-                    error!("error");
-                    panic!("{:?}", err)
-                }
-            }
+    match errors.into_iter().next() {
+        Some(err) => Err(err.into()),
+        None => {
+            info!("consumers stopped");
+            Ok(())
         }
     }
-
-    Ok(())
 }
 
 async fn default_setup() -> Result<Configs<Empty>, Box<dyn Error>> {
@@ -68,52 +171,69 @@ async fn default_setup() -> Result<Configs<Empty>, Box<dyn Error>> {
         .build::<Empty>()
         .await?;
 
-    traces::otlp::setup(&configs)?;
-    metrics::otlp::setup(&configs)?;
+    let exporter = TelemetryExporter::from_env()?;
+    if exporter == TelemetryExporter::Otlp {
+        traces::otlp::setup(&configs)?;
+    }
+    telemetry::setup(exporter, &configs.app.name, telemetry::default_selector())?;
 
     Ok(configs)
 }
 
+async fn rebuild_todo_stats(
+    cfg: &Configs<Empty>,
+    db_conn: Arc<Pool>,
+    slow_query: &SlowQueryConfigs,
+) -> Result<(), Box<dyn Error>> {
+    let days = TodoStatsRepositoryImpl::new(db_conn, &cfg.postgres, slow_query)
+        .rebuild(&Context::new())
+        .await?;
+
+    info!(days = days, "todo stats rebuilt");
+
+    Ok(())
+}
+
 async fn amqp_setup(
     cfg: &Configs<Empty>,
-) -> Result<(Arc<Connection>, Arc<Channel>, QueueDefinition), Box<dyn Error>> {
+    retry_policies: &[(&str, &RetryPolicy)],
+) -> Result<Arc<Connection>, Box<dyn Error>> {
     let (conn, channel) = channel::new_amqp_channel(cfg).await?;
 
-    let queue = QueueDefinition::new(QUEUE)
-        .durable()
-        .with_dlq()
-        .with_retry(18000, 3);
+    let queue = QueueDefinition::new(QUEUE).durable().with_dlq();
+    let todo_stats_queue = QueueDefinition::new(TODO_STATS_QUEUE).durable().with_dlq();
 
-    AmqpTopology::new(channel.clone())
-        .exchange(&ExchangeDefinition::new(EXCHANGE).direct().durable())
+    retry_policies
+        .iter()
+        .fold(
+            RetryTopology::new(channel.clone()),
+            |topology, (queue, policy)| topology.retry_policy(queue, policy),
+        )
+        .exchange(&ExchangeDefinition::new(EXCHANGE).topic().durable())
         .queue(&queue)
         .queue_binding(
             &QueueBinding::new(QUEUE)
                 .exchange(EXCHANGE)
-                .routing_key(ROUTING_KEY),
+                .routing_key(TODO_CREATED_ROUTING_KEY),
+        )
+        .queue(&todo_stats_queue)
+        .queue_binding(
+            &QueueBinding::new(TODO_STATS_QUEUE)
+                .exchange(EXCHANGE)
+                .routing_key(TODO_CREATED_ROUTING_KEY),
+        )
+        .queue_binding(
+            &QueueBinding::new(TODO_STATS_QUEUE)
+                .exchange(EXCHANGE)
+                .routing_key(TODO_DELETED_ROUTING_KEY),
+        )
+        .queue_binding(
+            &QueueBinding::new(TODO_STATS_QUEUE)
+                .exchange(EXCHANGE)
+                .routing_key(TODO_COMPLETED_ROUTING_KEY),
         )
         .install()
         .await?;
 
-    Ok((conn, channel, queue))
-}
-
-fn declare_health_meter() -> Result<(), Box<dyn Error>> {
-    let meter = global::meter("consumers-meter");
-    let health_counter = meter
-        .i64_observable_up_down_counter("consumers.health")
-        .with_description("AMQP Consumer Health")
-        .init();
-    let callback = move |ctx: &Context| {
-        health_counter.observe(ctx, 1, &[]);
-    };
-    match meter.register_callback(callback) {
-        Err(err) => {
-            error!(error = err.to_string(), "error to register health counter");
-            Err(err)
-        }
-        _ => Ok(()),
-    }?;
-
-    Ok(())
+    Ok(conn)
 }
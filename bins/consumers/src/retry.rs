@@ -0,0 +1,224 @@
+use amqp::{
+    errors::AmqpError,
+    exchange::ExchangeDefinition,
+    queue::{QueueBinding, QueueDefinition},
+    topology::{AmqpTopology, Topology},
+};
+use async_trait::async_trait;
+use lapin::{
+    options::QueueDeclareOptions,
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties, Channel,
+};
+use rand::Rng;
+use std::{env, sync::Arc, time::Duration};
+use tracing::error;
+
+/// Header counting how many times a message was sent to a delay queue.
+pub const RETRY_ATTEMPT_HEADER: &str = "x-retry-attempt";
+
+const MAX_ATTEMPTS_ENV_KEY: &str = "RETRY_MAX_ATTEMPTS";
+const BASE_DELAY_ENV_KEY: &str = "RETRY_BASE_DELAY_MS";
+const MAX_DELAY_ENV_KEY: &str = "RETRY_MAX_DELAY_MS";
+const JITTER_ENV_KEY: &str = "RETRY_JITTER";
+
+/// How transient failures of a queue are retried.
+///
+/// Attempt `n` waits `base_delay * 2^(n-1)`, capped at `max_delay` and spread by `jitter` (0.2
+/// means ±20%), in its own delay queue `<queue>-retry-<n>` that dead-letters back to the queue.
+/// Keeping one queue per attempt means messages in a queue expire at about the same time, so a
+/// long delay never holds back a shorter one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Reads `RETRY_MAX_ATTEMPTS`, `RETRY_BASE_DELAY_MS`, `RETRY_MAX_DELAY_MS` and
+    /// `RETRY_JITTER`, each one overridable for the queue by prefixing it with the queue name,
+    /// e.g. `SIMPLE_QUEUE_RETRY_MAX_ATTEMPTS` for `simple-queue`.
+    pub fn from_env(queue: &str) -> RetryPolicy {
+        let var = |key: &str| queue_var(queue, key);
+
+        RetryPolicy {
+            max_attempts: var(MAX_ATTEMPTS_ENV_KEY)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            base_delay: Duration::from_millis(
+                var(BASE_DELAY_ENV_KEY)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(1000),
+            ),
+            max_delay: Duration::from_millis(
+                var(MAX_DELAY_ENV_KEY)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(60000),
+            ),
+            jitter: var(JITTER_ENV_KEY)
+                .and_then(|v| v.parse::<f64>().ok())
+                .map(|j| j.clamp(0.0, 1.0))
+                .unwrap_or(0.2),
+        }
+    }
+
+    /// Delay before attempt `attempt`, starting at 1, without jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+        self.base_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Delay before attempt `attempt` spread by the jitter.
+    pub fn jittered_delay(&self, attempt: u32) -> Duration {
+        let spread = match self.jitter > 0.0 {
+            true => rand::thread_rng().gen_range(-self.jitter..=self.jitter),
+            false => 0.0,
+        };
+
+        self.delay(attempt).mul_f64(1.0 + spread)
+    }
+
+    /// Queue holding the messages waiting for attempt `attempt` of `queue`.
+    pub fn delay_queue(queue: &str, attempt: u32) -> String {
+        format!("{}-retry-{}", queue, attempt)
+    }
+
+    /// Properties of a message sent to the delay queue of `attempt`.
+    pub fn delayed(&self, properties: &BasicProperties, attempt: u32) -> BasicProperties {
+        let mut headers = properties.headers().clone().unwrap_or_default();
+        headers.insert(
+            ShortString::from(RETRY_ATTEMPT_HEADER),
+            AMQPValue::LongLongInt(attempt as i64),
+        );
+
+        properties
+            .clone()
+            .with_headers(headers)
+            .with_expiration(ShortString::from(
+                self.jittered_delay(attempt).as_millis().to_string(),
+            ))
+    }
+}
+
+/// Reads `key` prefixed with the queue name, e.g. `SIMPLE_QUEUE_<key>` for `simple-queue`,
+/// falling back to `key` itself.
+pub(crate) fn queue_var(queue: &str, key: &str) -> Option<String> {
+    let prefix = queue.to_uppercase().replace(['-', '.'], "_");
+
+    env::var(format!("{}_{}", prefix, key))
+        .or_else(|_| env::var(key))
+        .ok()
+}
+
+/// Attempts already made for a delivery, from the `x-retry-attempt` header.
+pub fn attempts(properties: &BasicProperties) -> u32 {
+    match properties
+        .headers()
+        .as_ref()
+        .and_then(|h| h.inner().get(RETRY_ATTEMPT_HEADER))
+    {
+        Some(AMQPValue::LongLongInt(attempt)) => *attempt as u32,
+        Some(AMQPValue::LongInt(attempt)) => *attempt as u32,
+        _ => 0,
+    }
+}
+
+/// `AmqpTopology` extended with the delay queues of each retry policy, so a single `install`
+/// declares the queues and the delay queues feeding them back.
+///
+/// `QueueDefinition` can only dead-letter a queue into the `-dlq` or `-retry` queue named after
+/// it, while every delay queue has its own TTL and dead-letters into the queue it delays, so they
+/// are declared here once the wrapped topology is installed.
+pub struct RetryTopology<'tp> {
+    topology: AmqpTopology<'tp>,
+    channel: Arc<Channel>,
+    queues: Vec<(String, RetryPolicy)>,
+}
+
+impl<'tp> RetryTopology<'tp> {
+    pub fn new(channel: Arc<Channel>) -> RetryTopology<'tp> {
+        RetryTopology {
+            topology: AmqpTopology::new(channel.clone()),
+            channel,
+            queues: vec![],
+        }
+    }
+
+    /// Declares the `max_attempts` delay queues of `queue`.
+    pub fn retry_policy(mut self, queue: &str, policy: &RetryPolicy) -> Self {
+        self.queues.push((queue.to_owned(), policy.clone()));
+        self
+    }
+
+    async fn install_delay_queues(&self) -> Result<(), lapin::Error> {
+        for (queue, policy) in &self.queues {
+            for attempt in 1..=policy.max_attempts {
+                // the per message expiration is jittered, the queue TTL only caps it
+                let ttl = policy.delay(attempt).mul_f64(1.0 + policy.jitter);
+
+                let mut args = FieldTable::default();
+                args.insert(
+                    ShortString::from("x-message-ttl"),
+                    AMQPValue::LongLongInt(ttl.as_millis() as i64),
+                );
+                args.insert(
+                    ShortString::from("x-dead-letter-exchange"),
+                    AMQPValue::LongString(LongString::from("")),
+                );
+                args.insert(
+                    ShortString::from("x-dead-letter-routing-key"),
+                    AMQPValue::LongString(LongString::from(queue.as_str())),
+                );
+
+                self.channel
+                    .queue_declare(
+                        &RetryPolicy::delay_queue(queue, attempt),
+                        QueueDeclareOptions {
+                            durable: true,
+                            ..Default::default()
+                        },
+                        args,
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<'tp> Topology<'tp> for RetryTopology<'tp> {
+    fn exchange(mut self, def: &'tp ExchangeDefinition<'tp>) -> Self {
+        self.topology = self.topology.exchange(def);
+        self
+    }
+
+    fn queue(mut self, def: &'tp QueueDefinition) -> Self {
+        self.topology = self.topology.queue(def);
+        self
+    }
+
+    fn queue_binding(mut self, def: &'tp QueueBinding<'tp>) -> Self {
+        self.topology = self.topology.queue_binding(def);
+        self
+    }
+
+    async fn install(&self) -> Result<(), AmqpError> {
+        self.topology.install().await?;
+
+        match self.install_delay_queues().await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to declare the delay queues");
+                Err(AmqpError::DeclareQueueError(err.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
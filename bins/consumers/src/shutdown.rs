@@ -0,0 +1,80 @@
+use std::{env, sync::Arc, time::Duration};
+use tokio::{
+    signal::{self, unix::SignalKind},
+    sync::watch,
+};
+use tracing::{error, info};
+
+const DRAIN_TIMEOUT_ENV_KEY: &str = "SHUTDOWN_DRAIN_TIMEOUT_MS";
+
+/// Shared shutdown state of the consumers.
+///
+/// Once triggered the consumers stop fetching and get `drain_timeout` to finish the messages in
+/// flight, and the health endpoint reports `draining` until the process exits.
+#[derive(Clone)]
+pub struct Shutdown {
+    drain_timeout: Duration,
+    draining: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new(drain_timeout: Duration) -> Shutdown {
+        let (draining, _) = watch::channel(false);
+
+        Shutdown {
+            drain_timeout,
+            draining: Arc::new(draining),
+        }
+    }
+
+    /// Reads `SHUTDOWN_DRAIN_TIMEOUT_MS`, 30 seconds by default.
+    pub fn from_env() -> Shutdown {
+        Shutdown::new(Duration::from_millis(
+            env::var(DRAIN_TIMEOUT_ENV_KEY)
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(30000),
+        ))
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    pub fn trigger(&self) {
+        self.draining.send_replace(true);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Resolves once the shutdown is triggered.
+    pub async fn wait(&self) {
+        let mut draining = self.draining.subscribe();
+
+        while !*draining.borrow_and_update() {
+            if draining.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Triggers the shutdown on SIGTERM or Ctrl-C.
+    pub async fn on_signal(self) {
+        let mut terminate = match signal::unix::signal(SignalKind::terminate()) {
+            Err(err) => {
+                error!(error = err.to_string(), "error to listen for SIGTERM");
+                return;
+            }
+            Ok(s) => s,
+        };
+
+        tokio::select! {
+            _ = terminate.recv() => info!("SIGTERM received, draining consumers"),
+            _ = signal::ctrl_c() => info!("Ctrl-C received, draining consumers"),
+        }
+
+        self.trigger();
+    }
+}
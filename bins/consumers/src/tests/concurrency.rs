@@ -0,0 +1,27 @@
+use crate::QueueConcurrency;
+use std::env;
+
+#[test]
+fn concurrency_is_overridden_per_queue() {
+    env::set_var("CONCURRENCY_TEST_CONSUMER_PREFETCH", "50");
+    env::set_var("CONCURRENCY_TEST_CONSUMER_PARALLELISM", "8");
+
+    assert_eq!(
+        QueueConcurrency::from_env("concurrency-test"),
+        QueueConcurrency {
+            prefetch: 50,
+            parallelism: 8,
+        }
+    );
+}
+
+#[test]
+fn invalid_concurrency_falls_back_to_the_defaults() {
+    env::set_var("INVALID_CONCURRENCY_TEST_CONSUMER_PREFETCH", "many");
+    env::set_var("INVALID_CONCURRENCY_TEST_CONSUMER_PARALLELISM", "0");
+
+    assert_eq!(
+        QueueConcurrency::from_env("invalid-concurrency-test"),
+        QueueConcurrency::default()
+    );
+}
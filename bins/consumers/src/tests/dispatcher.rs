@@ -0,0 +1,133 @@
+use crate::{
+    dispatcher::NextStep, EventAttributes, EventDispatcher, EventHandler, HandlerError, RetryPolicy,
+};
+use amqp::errors::AmqpError;
+use async_trait::async_trait;
+use lapin::{
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties,
+};
+use opentelemetry::Context;
+use shared::{codecs::CodecKind, events::CloudEvent, models::todo::TodoCreatedMessage};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const QUEUE: &str = "dispatcher-test-queue";
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 2,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(100),
+        jitter: 0.0,
+    }
+}
+
+fn delivered_after(attempts: i64) -> BasicProperties {
+    let mut headers = FieldTable::default();
+    headers.insert(
+        ShortString::from("x-retry-attempt"),
+        AMQPValue::LongLongInt(attempts),
+    );
+
+    BasicProperties::default().with_headers(headers)
+}
+
+/// Keeps every message it gets, read with the codec of its event.
+#[derive(Default)]
+struct ReadingHandler {
+    received: Mutex<Vec<TodoCreatedMessage>>,
+}
+
+#[async_trait]
+impl EventHandler for ReadingHandler {
+    async fn exec(&self, ctx: &Context, data: &[u8]) -> Result<(), HandlerError> {
+        let message = EventAttributes::of(ctx)?.data_as::<TodoCreatedMessage>(data)?;
+        self.received.lock().unwrap().push(message);
+
+        Ok(())
+    }
+}
+
+fn transient() -> HandlerError {
+    HandlerError::Database("connection reset".to_owned())
+}
+
+#[test]
+fn transient_failures_are_delayed_until_the_attempts_run_out() {
+    let policy = policy();
+    let dispatcher = EventDispatcher::new().retry_policy(QUEUE, policy.clone());
+
+    assert_eq!(
+        dispatcher.next_step(QUEUE, &BasicProperties::default(), &transient()),
+        NextStep::Delay(&policy, 1)
+    );
+    assert_eq!(
+        dispatcher.next_step(QUEUE, &delivered_after(1), &transient()),
+        NextStep::Delay(&policy, 2)
+    );
+    assert_eq!(
+        dispatcher.next_step(QUEUE, &delivered_after(2), &transient()),
+        NextStep::DeadLetter("retries exhausted: database error: connection reset".to_owned())
+    );
+}
+
+#[test]
+fn permanent_failures_are_dead_lettered_right_away() {
+    let dispatcher = EventDispatcher::new().retry_policy(QUEUE, policy());
+
+    for err in [
+        HandlerError::Message(AmqpError::AckMessageDeserializationError(
+            "invalid".to_owned(),
+        )),
+        HandlerError::MissingMessageId,
+    ] {
+        let reason = err.to_string();
+
+        assert_eq!(
+            dispatcher.next_step(QUEUE, &BasicProperties::default(), &err),
+            NextStep::DeadLetter(reason)
+        );
+    }
+}
+
+#[test]
+fn failures_of_queues_without_policy_are_dead_lettered() {
+    let dispatcher = EventDispatcher::new().retry_policy("other-queue", policy());
+
+    assert_eq!(
+        dispatcher.next_step(QUEUE, &BasicProperties::default(), &transient()),
+        NextStep::DeadLetter("database error: connection reset".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn handlers_read_the_data_in_the_codec_it_traveled_with() {
+    let handler = Arc::new(ReadingHandler::default());
+    let dispatcher = EventDispatcher::new().register::<TodoCreatedMessage>(QUEUE, handler.clone());
+    let message = TodoCreatedMessage {
+        id: "todo".to_owned(),
+        name: "name".to_owned(),
+        description: "description".to_owned(),
+        created_at: "2023-04-20T12:00:00+00:00".to_owned(),
+        owner_id: Some("owner".to_owned()),
+    };
+
+    for kind in [CodecKind::Json, CodecKind::MessagePack, CodecKind::Protobuf] {
+        let codec = kind.codec();
+        let mut event =
+            CloudEvent::new(message.clone()).map(|data| serde_json::to_value(data).unwrap());
+        event.datacontenttype = Some(codec.content_type().to_owned());
+        let properties =
+            BasicProperties::default().with_content_type(ShortString::from(codec.content_type()));
+
+        dispatcher
+            .dispatch(QUEUE, &codec.encode_event(&event).unwrap(), &properties)
+            .await
+            .unwrap_or_else(|err| panic!("{:?}: {}", kind, err));
+    }
+
+    assert_eq!(*handler.received.lock().unwrap(), vec![message; 3]);
+}
@@ -0,0 +1,27 @@
+use crate::HealthStatus;
+use actix_web::http::StatusCode;
+
+#[test]
+fn draining_wins_over_the_dependency_checks() {
+    assert_eq!(HealthStatus::of(false, true), HealthStatus::Ok);
+    assert_eq!(HealthStatus::of(false, false), HealthStatus::Unhealthy);
+    assert_eq!(HealthStatus::of(true, true), HealthStatus::Draining);
+    assert_eq!(HealthStatus::of(true, false), HealthStatus::Draining);
+}
+
+#[test]
+fn only_ok_is_ready() {
+    assert_eq!(HealthStatus::Ok.status_code(), StatusCode::OK);
+    assert_eq!(
+        HealthStatus::Unhealthy.status_code(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        HealthStatus::Draining.status_code(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        serde_json::to_value(HealthStatus::Draining).unwrap(),
+        "draining"
+    );
+}
@@ -0,0 +1,5 @@
+mod concurrency;
+mod dispatcher;
+mod health;
+mod retry;
+mod shutdown;
@@ -0,0 +1,101 @@
+use crate::{retry::attempts, RetryPolicy, RETRY_ATTEMPT_HEADER};
+use lapin::{
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties,
+};
+use std::{env, time::Duration};
+
+fn policy(jitter: f64) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_millis(1000),
+        max_delay: Duration::from_millis(6000),
+        jitter,
+    }
+}
+
+#[test]
+fn delays_double_up_to_the_max_delay() {
+    let policy = policy(0.0);
+
+    let delays: Vec<u64> = (1..=5)
+        .map(|attempt| policy.delay(attempt).as_millis() as u64)
+        .collect();
+
+    assert_eq!(delays, vec![1000, 2000, 4000, 6000, 6000]);
+    assert_eq!(policy.delay(u32::MAX), Duration::from_millis(6000));
+}
+
+#[test]
+fn jittered_delays_stay_within_the_jitter() {
+    let policy = policy(0.2);
+
+    for attempt in 1..=5 {
+        let delay = policy.delay(attempt);
+        for _ in 0..100 {
+            let jittered = policy.jittered_delay(attempt);
+            assert!(
+                jittered >= delay.mul_f64(0.8),
+                "{:?} < {:?}",
+                jittered,
+                delay
+            );
+            assert!(
+                jittered <= delay.mul_f64(1.2),
+                "{:?} > {:?}",
+                jittered,
+                delay
+            );
+        }
+    }
+
+    assert_eq!(policy(0.0).jittered_delay(2), Duration::from_millis(2000));
+}
+
+#[test]
+fn delayed_messages_carry_the_attempt_and_expiration() {
+    let policy = policy(0.0);
+    let mut headers = FieldTable::default();
+    headers.insert(
+        ShortString::from("cloudEvents:id"),
+        AMQPValue::LongString("id".into()),
+    );
+    let properties = BasicProperties::default().with_headers(headers);
+
+    let delayed = policy.delayed(&properties, 3);
+
+    assert_eq!(attempts(&properties), 0);
+    assert_eq!(attempts(&delayed), 3);
+    assert_eq!(
+        delayed.expiration().as_ref().map(|e| e.as_str()),
+        Some("4000")
+    );
+    assert!(delayed
+        .headers()
+        .as_ref()
+        .unwrap()
+        .inner()
+        .contains_key("cloudEvents:id"));
+    assert!(delayed
+        .headers()
+        .as_ref()
+        .unwrap()
+        .inner()
+        .contains_key(RETRY_ATTEMPT_HEADER));
+    assert_eq!(
+        RetryPolicy::delay_queue("simple-queue", 3),
+        "simple-queue-retry-3"
+    );
+}
+
+#[test]
+fn policies_are_overridden_per_queue() {
+    env::set_var("RETRY_POLICY_TEST_RETRY_MAX_ATTEMPTS", "7");
+    env::set_var("RETRY_POLICY_TEST_RETRY_JITTER", "3");
+
+    let policy = RetryPolicy::from_env("retry-policy-test");
+
+    assert_eq!(policy.max_attempts, 7);
+    assert_eq!(policy.jitter, 1.0);
+    assert_eq!(policy.base_delay, Duration::from_millis(1000));
+}
@@ -0,0 +1,32 @@
+use crate::Shutdown;
+use std::time::Duration;
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(1);
+
+#[tokio::test]
+async fn waiters_resolve_once_triggered() {
+    let shutdown = Shutdown::new(Duration::from_millis(100));
+    let waiter = shutdown.clone();
+
+    let waiting = tokio::spawn(async move { waiter.wait().await });
+    assert!(!shutdown.is_draining());
+    assert!(timeout(Duration::from_millis(50), shutdown.wait())
+        .await
+        .is_err());
+
+    shutdown.trigger();
+
+    timeout(WAIT, waiting).await.unwrap().unwrap();
+    assert!(shutdown.is_draining());
+}
+
+#[tokio::test]
+async fn waiting_after_the_trigger_resolves_right_away() {
+    let shutdown = Shutdown::new(Duration::from_millis(100));
+    shutdown.trigger();
+
+    timeout(WAIT, shutdown.wait()).await.unwrap();
+    assert!(shutdown.is_draining());
+    assert_eq!(shutdown.drain_timeout(), Duration::from_millis(100));
+}
@@ -0,0 +1,161 @@
+//! Runs against the rabbitmq of the local environment:
+//!
+//! `RUST_ENV=local cargo test -p consumers --test drain -- --ignored`
+use amqp::{channel::new_amqp_channel, publisher::Payload};
+use async_trait::async_trait;
+use configs::Empty;
+use configs_builder::ConfigBuilder;
+use consumers::{EventDispatcher, EventHandler, HandlerError, QueueConcurrency, Shutdown};
+use lapin::{
+    options::{BasicGetOptions, BasicPublishOptions, QueueDeclareOptions, QueueDeleteOptions},
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties, Channel,
+};
+use opentelemetry::Context;
+use shared::{
+    amqp::{publish_event, EventPublisher, EventsConfigs, PublishError},
+    models::todo::TodoCreatedMessage,
+};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+/// Publishes straight to the queue named by the routing key.
+struct QueuePublisher {
+    channel: Arc<Channel>,
+}
+
+#[async_trait]
+impl EventPublisher for QueuePublisher {
+    async fn publish(
+        &self,
+        _ctx: &Context,
+        _exchange: &str,
+        routing_key: &str,
+        payload: &Payload,
+        content_type: &str,
+        headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError> {
+        let mut table = FieldTable::default();
+        for (name, value) in headers.unwrap_or_default() {
+            table.insert(ShortString::from(name), value);
+        }
+
+        self.channel
+            .basic_publish(
+                "",
+                routing_key,
+                BasicPublishOptions::default(),
+                &payload.payload,
+                BasicProperties::default()
+                    .with_content_type(ShortString::from(content_type))
+                    .with_headers(table),
+            )
+            .await
+            .map_err(|err| PublishError::Broker(err.to_string()))?
+            .await
+            .map_err(|err| PublishError::Broker(err.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Never finishes a message, telling when it got one.
+#[derive(Default)]
+struct StuckHandler {
+    started: Notify,
+}
+
+#[async_trait]
+impl EventHandler for StuckHandler {
+    async fn exec(&self, _ctx: &Context, _data: &[u8]) -> Result<(), HandlerError> {
+        self.started.notify_one();
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+
+        Ok(())
+    }
+}
+
+fn message() -> TodoCreatedMessage {
+    TodoCreatedMessage {
+        id: "todo".to_owned(),
+        name: "name".to_owned(),
+        description: "description".to_owned(),
+        created_at: "2023-04-20T12:00:00+00:00".to_owned(),
+        owner_id: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs the local rabbitmq"]
+async fn messages_unfinished_at_the_drain_timeout_are_requeued() {
+    let cfg = ConfigBuilder::new().amqp().build::<Empty>().await.unwrap();
+    let (conn, channel) = new_amqp_channel(&cfg).await.unwrap();
+    let queue = format!("drain-test-{}", rand::random::<u64>());
+    channel
+        .queue_declare(
+            &queue,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .unwrap();
+
+    // the first one is in flight at the timeout, the second one only prefetched
+    let publisher = QueuePublisher {
+        channel: channel.clone(),
+    };
+    for _ in 0..2 {
+        publish_event(
+            &publisher,
+            &EventsConfigs::default(),
+            &Context::new(),
+            "",
+            &queue,
+            message(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let handler = Arc::new(StuckHandler::default());
+    let dispatcher = EventDispatcher::new()
+        .register::<TodoCreatedMessage>(&queue, handler.clone())
+        .concurrency(
+            &queue,
+            QueueConcurrency {
+                prefetch: 10,
+                parallelism: 1,
+            },
+        );
+    let shutdown = Shutdown::new(Duration::from_millis(100));
+
+    let (results, _) = tokio::time::timeout(Duration::from_secs(10), async {
+        tokio::join!(
+            dispatcher.consume_blocking(conn.clone(), &shutdown),
+            async {
+                handler.started.notified().await;
+                shutdown.trigger();
+            }
+        )
+    })
+    .await
+    .expect("consumer still running after the drain timeout");
+
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+
+    let mut requeued = 0;
+    while let Some(msg) = channel
+        .basic_get(&queue, BasicGetOptions { no_ack: true })
+        .await
+        .unwrap()
+    {
+        assert!(msg.delivery.redelivered);
+        requeued += 1;
+    }
+    assert_eq!(requeued, 2);
+
+    channel
+        .queue_delete(&queue, QueueDeleteOptions::default())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,158 @@
+//! Runs against the postgres of the local environment, with the `processed_messages` table of
+//! `.docker/migration.sql`:
+//!
+//! `RUST_ENV=local cargo test -p consumers --test idempotent -- --ignored`
+use async_trait::async_trait;
+use configs::Empty;
+use configs_builder::ConfigBuilder;
+use consumers::{
+    purge_expired, EventHandler, HandlerError, IdempotencyConfigs, Idempotent, MessageId, Shutdown,
+    TransactionalHandler,
+};
+use deadpool_postgres::{Pool, Transaction};
+use infra::repositories::{ProcessedMessageRepository, SlowQueryConfigs};
+use opentelemetry::Context;
+use sql_pool::postgres::conn_pool;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Counts its executions, failing the first `failures` of them.
+struct CountingHandler {
+    calls: AtomicUsize,
+    failures: usize,
+}
+
+impl CountingHandler {
+    fn new(failures: usize) -> Arc<CountingHandler> {
+        Arc::new(CountingHandler {
+            calls: AtomicUsize::new(0),
+            failures,
+        })
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl TransactionalHandler for CountingHandler {
+    async fn exec(
+        &self,
+        _ctx: &Context,
+        _tx: &Transaction<'_>,
+        _data: &[u8],
+    ) -> Result<(), HandlerError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call < self.failures {
+            return Err(HandlerError::Database("handler failure".to_owned()));
+        }
+
+        Ok(())
+    }
+}
+
+struct Store {
+    pool: Arc<Pool>,
+    processed: Arc<ProcessedMessageRepository>,
+}
+
+async fn store() -> Store {
+    let cfg = ConfigBuilder::new()
+        .postgres()
+        .build::<Empty>()
+        .await
+        .unwrap();
+    let pool = Arc::new(conn_pool(&cfg.postgres).unwrap());
+    let processed =
+        ProcessedMessageRepository::new(pool.clone(), &cfg.postgres, &SlowQueryConfigs::from_env());
+
+    Store { pool, processed }
+}
+
+fn idempotent(store: &Store, inner: Arc<CountingHandler>) -> Arc<Idempotent<CountingHandler>> {
+    Idempotent::new(
+        "idempotent-test",
+        store.pool.clone(),
+        store.processed.clone(),
+        inner,
+        &IdempotencyConfigs {
+            ttl: Duration::from_secs(60),
+            purge_interval: Duration::from_secs(60),
+        },
+    )
+}
+
+/// A context carrying a message id no other test run has used.
+fn delivery() -> Context {
+    Context::new().with_value(MessageId {
+        source: "/todos".to_owned(),
+        id: format!("idempotent-test-{}", rand::random::<u64>()),
+    })
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn duplicate_deliveries_are_skipped() {
+    let store = store().await;
+    let inner = CountingHandler::new(0);
+    let handler = idempotent(&store, inner.clone());
+    let ctx = delivery();
+
+    handler.exec(&ctx, b"{}").await.unwrap();
+    handler.exec(&ctx, b"{}").await.unwrap();
+
+    assert_eq!(inner.calls(), 1);
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn failed_deliveries_are_processed_again() {
+    let store = store().await;
+    let inner = CountingHandler::new(1);
+    let handler = idempotent(&store, inner.clone());
+    let ctx = delivery();
+
+    assert!(handler.exec(&ctx, b"{}").await.is_err());
+    handler.exec(&ctx, b"{}").await.unwrap();
+    handler.exec(&ctx, b"{}").await.unwrap();
+
+    assert_eq!(inner.calls(), 2);
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn deliveries_without_id_are_rejected() {
+    let store = store().await;
+    let inner = CountingHandler::new(0);
+    let handler = idempotent(&store, inner.clone());
+
+    let result = handler.exec(&Context::new(), b"{}").await;
+
+    assert!(matches!(result, Err(HandlerError::MissingMessageId)));
+    assert_eq!(inner.calls(), 0);
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn purge_stops_on_shutdown() {
+    let store = store().await;
+    let shutdown = Shutdown::new(Duration::from_secs(1));
+
+    let purge = tokio::spawn(purge_expired(
+        store.processed.clone(),
+        Duration::from_millis(10),
+        shutdown.clone(),
+    ));
+    shutdown.trigger();
+
+    tokio::time::timeout(Duration::from_secs(5), purge)
+        .await
+        .expect("purge still running after the shutdown")
+        .unwrap();
+}
@@ -0,0 +1,152 @@
+//! Runs against the postgres of the local environment, with the tables of
+//! `.docker/migration.sql`:
+//!
+//! `RUST_ENV=local cargo test -p consumers --test todo_stats -- --ignored`
+use configs::Empty;
+use configs_builder::ConfigBuilder;
+use consumers::{EventAttributes, TodoStatsProjection, TransactionalHandler};
+use deadpool_postgres::Pool;
+use infra::repositories::{SlowQueryConfigs, TodoRepositoryImpl, TodoStatsRepositoryImpl};
+use opentelemetry::Context;
+use serde::Serialize;
+use shared::{
+    events::{TODO_COMPLETED_EVENT, TODO_CREATED_EVENT, TODO_DELETED_EVENT},
+    models::{
+        todo::{CreateTodo, Todo, TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage},
+        todo_stats::TodoStats,
+    },
+    repositories::{TodoRepository, TodoStatsRepository},
+};
+use sql_pool::postgres::conn_pool;
+use std::sync::Arc;
+
+struct Projection {
+    pool: Arc<Pool>,
+    todos: Arc<TodoRepositoryImpl>,
+    stats: Arc<TodoStatsRepositoryImpl>,
+    projection: Arc<TodoStatsProjection>,
+}
+
+impl Projection {
+    async fn new() -> Projection {
+        let cfg = ConfigBuilder::new()
+            .postgres()
+            .build::<Empty>()
+            .await
+            .unwrap();
+        let pool = Arc::new(conn_pool(&cfg.postgres).unwrap());
+        let slow_query = SlowQueryConfigs::from_env();
+
+        let stats = TodoStatsRepositoryImpl::new(pool.clone(), &cfg.postgres, &slow_query);
+
+        Projection {
+            todos: TodoRepositoryImpl::new(pool.clone(), &cfg.postgres, &slow_query),
+            projection: TodoStatsProjection::new(stats.clone()),
+            stats,
+            pool,
+        }
+    }
+
+    /// A ToDo of an owner no other test run has used.
+    async fn create(&self) -> Todo {
+        self.todos
+            .create(
+                &Context::new(),
+                &CreateTodo {
+                    name: "name".to_owned(),
+                    description: "description".to_owned(),
+                    owner_id: Some(format!("todo-stats-test-{}", rand::random::<u64>())),
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    /// Projects the event in its own transaction, like a delivery does.
+    async fn project<E: Serialize>(&self, event_type: &str, data: &E) {
+        let ctx = Context::new().with_value(EventAttributes {
+            ty: event_type.to_owned(),
+            time: None,
+            datacontenttype: None,
+        });
+
+        let mut conn = self.pool.get().await.unwrap();
+        let tx = conn.transaction().await.unwrap();
+        self.projection
+            .exec(&ctx, &tx, &serde_json::to_vec(data).unwrap())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    async fn stats_of(&self, todo: &Todo) -> Vec<TodoStats> {
+        let day = &todo.created_at[..10];
+
+        self.stats
+            .list_by_owner(&Context::new(), todo.owner_id.as_deref().unwrap(), day, day)
+            .await
+            .unwrap()
+    }
+}
+
+fn counts(stats: &[TodoStats]) -> Vec<(i32, i32, i32)> {
+    stats
+        .iter()
+        .map(|s| (s.created, s.deleted, s.completed))
+        .collect()
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn each_change_is_counted_once() {
+    let projection = Projection::new().await;
+    let todo = projection.create().await;
+    let completed = projection
+        .todos
+        .complete(&Context::new(), &todo.id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    projection
+        .project(TODO_CREATED_EVENT, &TodoCreatedMessage::from(&todo))
+        .await;
+    // published twice, e.g. by a retried request, with different message ids
+    for _ in 0..2 {
+        projection
+            .project(
+                TODO_COMPLETED_EVENT,
+                &TodoCompletedMessage::from(&completed),
+            )
+            .await;
+    }
+
+    assert_eq!(counts(&projection.stats_of(&todo).await), vec![(1, 0, 1)]);
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn events_counted_by_a_rebuild_are_skipped() {
+    let projection = Projection::new().await;
+    let todo = projection.create().await;
+
+    projection.stats.rebuild(&Context::new()).await.unwrap();
+    assert_eq!(counts(&projection.stats_of(&todo).await), vec![(1, 0, 0)]);
+
+    // in flight during the rebuild
+    projection
+        .project(TODO_CREATED_EVENT, &TodoCreatedMessage::from(&todo))
+        .await;
+    // changed after the rebuild
+    let deleted = projection
+        .todos
+        .delete(&Context::new(), &todo.id)
+        .await
+        .unwrap()
+        .unwrap();
+    projection
+        .project(TODO_DELETED_EVENT, &TodoDeletedMessage::from(&deleted))
+        .await;
+
+    assert_eq!(counts(&projection.stats_of(&todo).await), vec![(1, 1, 0)]);
+}
@@ -0,0 +1,251 @@
+use lapin::{
+    message::BasicGetMessage,
+    options::{BasicGetOptions, BasicNackOptions},
+    types::{AMQPValue, FieldTable},
+    Channel,
+};
+use opentelemetry::{
+    global,
+    trace::{SpanContext, TraceContextExt},
+    Context,
+};
+use serde_json::{json, Map, Value};
+use shared::amqp::{decode_body, decode_event, event_context, HeaderExtractor};
+
+const X_DEATH_HEADER: &str = "x-death";
+const DLQ_REASON_HEADER: &str = "x-dlq-reason";
+const RETRY_ATTEMPT_HEADER: &str = "x-retry-attempt";
+
+/// Where a dead-lettered message was originally published to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    Exchange {
+        exchange: String,
+        routing_key: String,
+    },
+    Queue(String),
+}
+
+/// A message read from the DLQ of `queue`. It stays unacked, and so invisible to other readers,
+/// until it is replayed or released.
+pub struct DeadLetter {
+    pub message: BasicGetMessage,
+    /// CloudEvents `id`, else the AMQP `message_id`, else the position in the DLQ.
+    pub id: String,
+    pub event_type: Option<String>,
+    pub reason: Option<String>,
+    /// How many times the consumers retried the message, or else how many times the broker
+    /// dead-lettered it out of `queue`.
+    pub retries: i64,
+    pub origin: Origin,
+    /// Context of the span that published the message in the first place.
+    pub parent: Context,
+}
+
+impl DeadLetter {
+    fn new(queue: &str, position: usize, message: BasicGetMessage) -> DeadLetter {
+        let properties = &message.delivery.properties;
+        let headers = properties.headers().as_ref();
+
+        let (id, event_type, parent) = match decode_event(&message.delivery.data, properties) {
+            Ok(event) => (
+                Some(event.id.clone()),
+                Some(event.ty.clone()),
+                event_context(properties, &event),
+            ),
+            Err(_) => (
+                None,
+                None,
+                global::get_text_map_propagator(|propagator| {
+                    propagator.extract(&HeaderExtractor::new(properties))
+                }),
+            ),
+        };
+        let id = id
+            .or_else(|| properties.message_id().as_ref().map(|m| m.to_string()))
+            .unwrap_or_else(|| position.to_string());
+
+        let deaths: Vec<&FieldTable> = header(headers, X_DEATH_HEADER)
+            .and_then(|d| d.as_array())
+            .map(|d| {
+                d.as_slice()
+                    .iter()
+                    .filter_map(|e| e.as_field_table())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let from_queue: Vec<&FieldTable> = deaths
+            .into_iter()
+            .filter(|death| string(field(death, "queue")).as_deref() == Some(queue))
+            .collect();
+
+        let reason = header(headers, DLQ_REASON_HEADER)
+            .and_then(|r| string(Some(r)))
+            .or_else(|| from_queue.first().and_then(|d| string(field(d, "reason"))));
+
+        let retries = header(headers, RETRY_ATTEMPT_HEADER)
+            .and_then(integer)
+            .unwrap_or_else(|| {
+                from_queue
+                    .iter()
+                    .filter_map(|d| field(d, "count").and_then(integer))
+                    .sum()
+            });
+
+        let origin = from_queue
+            .first()
+            .and_then(|d| {
+                let exchange = string(field(d, "exchange"))?;
+                let routing_key = field(d, "routing-keys")
+                    .and_then(|k| k.as_array())
+                    .and_then(|k| k.as_slice().first())
+                    .and_then(|k| string(Some(k)))?;
+
+                match exchange.is_empty() {
+                    true => None,
+                    false => Some(Origin::Exchange {
+                        exchange,
+                        routing_key,
+                    }),
+                }
+            })
+            .unwrap_or_else(|| Origin::Queue(queue.to_owned()));
+
+        DeadLetter {
+            message,
+            id,
+            event_type,
+            reason,
+            retries,
+            origin,
+            parent,
+        }
+    }
+
+    pub fn span_context(&self) -> SpanContext {
+        self.parent.span().span_context().clone()
+    }
+
+    pub fn trace_id(&self) -> Option<String> {
+        let span_context = self.span_context();
+
+        match span_context.is_valid() {
+            true => Some(span_context.trace_id().to_string()),
+            false => None,
+        }
+    }
+
+    /// Headers as JSON, with AMQP types flattened to their values.
+    pub fn headers(&self) -> Value {
+        self.message
+            .delivery
+            .properties
+            .headers()
+            .as_ref()
+            .map(table_json)
+            .unwrap_or_else(|| json!({}))
+    }
+
+    /// Body decoded with its codec, or as text when it can not be decoded.
+    pub fn payload(&self) -> Value {
+        let data = &self.message.delivery.data;
+
+        decode_body(data, &self.message.delivery.properties)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(data).into_owned()))
+    }
+
+    /// Makes the message available in the DLQ again.
+    pub async fn release(&self) -> Result<(), lapin::Error> {
+        self.message
+            .delivery
+            .nack(BasicNackOptions {
+                requeue: true,
+                ..Default::default()
+            })
+            .await
+    }
+}
+
+/// Reads up to `limit` messages from the DLQ of `queue` without settling them. Messages that
+/// arrive while reading are left for the next run.
+pub async fn read(
+    channel: &Channel,
+    queue: &str,
+    dlq: &str,
+    limit: usize,
+) -> Result<Vec<DeadLetter>, lapin::Error> {
+    let mut dead_letters = vec![];
+    let mut limit = limit;
+
+    while dead_letters.len() < limit {
+        let message = match channel.basic_get(dlq, BasicGetOptions::default()).await? {
+            None => break,
+            Some(m) => m,
+        };
+
+        if dead_letters.is_empty() {
+            limit = limit.min(message.message_count as usize + 1);
+        }
+
+        dead_letters.push(DeadLetter::new(queue, dead_letters.len() + 1, message));
+    }
+
+    Ok(dead_letters)
+}
+
+fn header<'a>(headers: Option<&'a FieldTable>, name: &str) -> Option<&'a AMQPValue> {
+    headers?.inner().get(name)
+}
+
+fn field<'a>(table: &'a FieldTable, name: &str) -> Option<&'a AMQPValue> {
+    table.inner().get(name)
+}
+
+fn string(value: Option<&AMQPValue>) -> Option<String> {
+    match value? {
+        AMQPValue::LongString(s) => Some(String::from_utf8_lossy(s.as_bytes()).into_owned()),
+        AMQPValue::ShortString(s) => Some(s.as_str().to_owned()),
+        _ => None,
+    }
+}
+
+fn integer(value: &AMQPValue) -> Option<i64> {
+    match value {
+        AMQPValue::ShortShortInt(v) => Some(*v as i64),
+        AMQPValue::ShortShortUInt(v) => Some(*v as i64),
+        AMQPValue::ShortInt(v) => Some(*v as i64),
+        AMQPValue::ShortUInt(v) => Some(*v as i64),
+        AMQPValue::LongInt(v) => Some(*v as i64),
+        AMQPValue::LongUInt(v) => Some(*v as i64),
+        AMQPValue::LongLongInt(v) => Some(*v),
+        _ => None,
+    }
+}
+
+fn table_json(table: &FieldTable) -> Value {
+    Value::Object(
+        table
+            .inner()
+            .iter()
+            .map(|(k, v)| (k.as_str().to_owned(), value_json(v)))
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+fn value_json(value: &AMQPValue) -> Value {
+    if let Some(i) = integer(value) {
+        return json!(i);
+    }
+
+    match value {
+        AMQPValue::Boolean(b) => json!(b),
+        AMQPValue::Float(f) => json!(f),
+        AMQPValue::Double(d) => json!(d),
+        AMQPValue::Timestamp(t) => json!(t),
+        AMQPValue::LongString(_) | AMQPValue::ShortString(_) => json!(string(Some(value))),
+        AMQPValue::FieldArray(a) => Value::Array(a.as_slice().iter().map(value_json).collect()),
+        AMQPValue::FieldTable(t) => table_json(t),
+        AMQPValue::Void => Value::Null,
+        other => json!(format!("{:?}", other)),
+    }
+}
@@ -0,0 +1,207 @@
+//! Inspects and replays the dead-letter queues declared with `QueueDefinition::with_dlq`.
+//!
+//! `<queue>` is the queue the messages were dead-lettered from, its DLQ is `<queue>-dlq`:
+//!
+//! - `dlq-tool list <queue> [--limit N]` prints the id, type, death reason, retry count and
+//!   trace id of each message.
+//! - `dlq-tool show <queue> [--limit N]` also prints the headers and payload.
+//! - `dlq-tool replay <queue> (--all | <id>...) [--patch <file.json>] [--to-exchange]`
+//!   republishes the messages to `<queue>` itself, or to the exchange and routing key they were
+//!   first published with when `--to-exchange` is given, which also delivers them again to every
+//!   other queue bound to it. `--patch` applies a JSON merge patch to every
+//!   body, e.g. `{"data": {"name": "fixed"}}` for structured CloudEvents, decoding and encoding
+//!   it back with the codec of its `content-type`.
+//!
+//! Messages are read without being acked, so the ones not replayed stay in the DLQ in the same
+//! order. Every replay is traced in a new trace linked to the one that published the message.
+mod dead_letter;
+mod replay;
+#[cfg(test)]
+mod tests;
+
+use amqp::channel;
+use configs::{Configs, Empty};
+use configs_builder::ConfigBuilder;
+use dead_letter::{DeadLetter, Origin};
+use infra::telemetry::{self, TelemetryExporter};
+use replay::Replayer;
+use serde_json::{json, Value};
+use shared::amqp::dlq_name;
+use std::{env, error::Error};
+
+const USAGE: &str = "usage: dlq-tool list <queue> [--limit N]
+       dlq-tool show <queue> [--limit N]
+       dlq-tool replay <queue> (--all | <id>...) [--patch <file.json>] [--to-exchange] [--limit N]";
+
+const DEFAULT_LIMIT: usize = 100;
+
+enum Command {
+    List { payloads: bool },
+    Replay(ReplayArgs),
+}
+
+struct ReplayArgs {
+    all: bool,
+    ids: Vec<String>,
+    patch: Option<String>,
+    to_exchange: bool,
+}
+
+struct Args {
+    command: Command,
+    queue: String,
+    limit: usize,
+}
+
+impl Args {
+    fn parse(args: Vec<String>) -> Result<Args, String> {
+        let mut args = args.into_iter();
+        let command = args.next().ok_or(USAGE)?;
+        let queue = args.next().ok_or(USAGE)?;
+
+        let mut limit = DEFAULT_LIMIT;
+        let mut replay = ReplayArgs {
+            all: false,
+            ids: vec![],
+            patch: None,
+            to_exchange: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--limit" => {
+                    limit = args
+                        .next()
+                        .and_then(|l| l.parse().ok())
+                        .ok_or("--limit needs a number")?
+                }
+                "--all" => replay.all = true,
+                "--to-exchange" => replay.to_exchange = true,
+                "--patch" => replay.patch = Some(args.next().ok_or("--patch needs a file")?),
+                flag if flag.starts_with("--") => return Err(format!("unknown flag {}", flag)),
+                id => replay.ids.push(id.to_owned()),
+            }
+        }
+
+        let command = match command.as_str() {
+            "list" => Command::List { payloads: false },
+            "show" => Command::List { payloads: true },
+            "replay" if replay.all == replay.ids.is_empty() => {
+                return Err("replay needs either --all or message ids".to_owned())
+            }
+            "replay" => Command::Replay(replay),
+            _ => return Err(USAGE.to_owned()),
+        };
+
+        Ok(Args {
+            command,
+            queue,
+            limit,
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse(env::args().skip(1).collect())?;
+    let cfg = default_setup().await?;
+
+    let result = run(&cfg, &args).await;
+
+    telemetry::shutdown();
+    result
+}
+
+async fn run(cfg: &Configs<Empty>, args: &Args) -> Result<(), Box<dyn Error>> {
+    let patch = match &args.command {
+        Command::Replay(ReplayArgs {
+            patch: Some(file), ..
+        }) => Some(serde_json::from_slice::<Value>(
+            &tokio::fs::read(file).await?,
+        )?),
+        _ => None,
+    };
+
+    let (_conn, channel) = channel::new_amqp_channel(cfg).await?;
+    let dlq = dlq_name(&args.queue);
+    let dead_letters = dead_letter::read(&channel, &args.queue, &dlq, args.limit).await?;
+    let mut replayed: Vec<usize> = vec![];
+
+    match &args.command {
+        Command::List { payloads } => {
+            for dead_letter in &dead_letters {
+                print(dead_letter, *payloads);
+            }
+            println!("{}: {} messages", dlq, dead_letters.len());
+        }
+        Command::Replay(replay) => {
+            let replayer = Replayer::new(replay.to_exchange, patch);
+
+            for (position, dead_letter) in dead_letters.iter().enumerate() {
+                if !replay.all && !replay.ids.contains(&dead_letter.id) {
+                    continue;
+                }
+
+                match replayer.replay(&channel, &args.queue, dead_letter).await {
+                    Err(err) => eprintln!("{}: error to replay: {}", dead_letter.id, err),
+                    Ok(_) => {
+                        replayed.push(position);
+                        println!("{}: replayed", dead_letter.id);
+                    }
+                }
+            }
+            println!("{}: {} messages replayed", dlq, replayed.len());
+        }
+    }
+
+    // whatever was not replayed goes back to the DLQ
+    for (position, dead_letter) in dead_letters.iter().enumerate() {
+        if replayed.contains(&position) {
+            continue;
+        }
+
+        if let Err(err) = dead_letter.release().await {
+            eprintln!("{}: error to release: {}", dead_letter.id, err);
+        }
+    }
+
+    Ok(())
+}
+
+fn print(dead_letter: &DeadLetter, payloads: bool) {
+    let origin = match &dead_letter.origin {
+        Origin::Exchange {
+            exchange,
+            routing_key,
+        } => format!("{}/{}", exchange, routing_key),
+        Origin::Queue(queue) => queue.clone(),
+    };
+
+    let mut line = json!({
+        "id": dead_letter.id,
+        "type": dead_letter.event_type,
+        "reason": dead_letter.reason,
+        "retries": dead_letter.retries,
+        "trace_id": dead_letter.trace_id(),
+        "origin": origin,
+    });
+
+    if payloads {
+        line["headers"] = dead_letter.headers();
+        line["payload"] = dead_letter.payload();
+    }
+
+    println!("{}", line);
+}
+
+async fn default_setup() -> Result<Configs<Empty>, Box<dyn Error>> {
+    let configs = ConfigBuilder::new().amqp().otlp().build::<Empty>().await?;
+
+    let exporter = TelemetryExporter::from_env()?;
+    if exporter == TelemetryExporter::Otlp {
+        traces::otlp::setup(&configs)?;
+    }
+    telemetry::setup(exporter, &configs.app.name, telemetry::default_selector())?;
+
+    Ok(configs)
+}
@@ -0,0 +1,190 @@
+use crate::dead_letter::{DeadLetter, Origin};
+use lapin::{
+    options::{BasicAckOptions, BasicPublishOptions},
+    types::{AMQPValue, LongString, ShortString},
+    Channel,
+};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    trace::{Link, SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use serde_json::Value;
+use shared::amqp::{decode_body, encode_body};
+use std::{borrow::Cow, collections::HashMap, error::Error};
+
+/// Headers describing the previous deaths, dropped so the replayed message starts over with
+/// the retries of its queue.
+const DEATH_HEADERS: [&str; 6] = [
+    "x-death",
+    "x-first-death-exchange",
+    "x-first-death-queue",
+    "x-first-death-reason",
+    "x-dlq-reason",
+    "x-retry-attempt",
+];
+
+/// Republishes dead letters, each one in a new trace linked to the trace that published it.
+pub struct Replayer {
+    tracer: BoxedTracer,
+    /// Publishes to the original exchange and routing key instead of straight to the original
+    /// queue through the default exchange, delivering the message again to every other queue
+    /// bound to them.
+    to_exchange: bool,
+    /// JSON merge patch (RFC 7396) applied to every body before publishing.
+    patch: Option<Value>,
+}
+
+impl Replayer {
+    pub fn new(to_exchange: bool, patch: Option<Value>) -> Replayer {
+        Replayer {
+            tracer: global::tracer("dlq-tool"),
+            to_exchange,
+            patch,
+        }
+    }
+
+    /// Publishes the message and, once the broker confirms it, acks it in the DLQ.
+    pub async fn replay(
+        &self,
+        channel: &Channel,
+        queue: &str,
+        dead_letter: &DeadLetter,
+    ) -> Result<(), Box<dyn Error>> {
+        let (exchange, routing_key) = match (&dead_letter.origin, self.to_exchange) {
+            (
+                Origin::Exchange {
+                    exchange,
+                    routing_key,
+                },
+                true,
+            ) => (exchange.clone(), routing_key.clone()),
+            _ => (String::new(), queue.to_owned()),
+        };
+
+        let body = match &self.patch {
+            None => dead_letter.message.delivery.data.clone(),
+            Some(patch) => {
+                let delivery = &dead_letter.message.delivery;
+                let mut body = decode_body(&delivery.data, &delivery.properties)
+                    .map_err(|err| format!("{}: {}", dead_letter.id, err))?;
+                merge_patch(&mut body, patch);
+                encode_body(&body, &delivery.properties)
+                    .map_err(|err| format!("{}: {}", dead_letter.id, err))?
+            }
+        };
+
+        let destination = match exchange.is_empty() {
+            true => routing_key.clone(),
+            false => exchange.clone(),
+        };
+        let span = self
+            .tracer
+            .span_builder(format!("{} publish", destination))
+            .with_kind(SpanKind::Producer)
+            .with_links(vec![Link::new(dead_letter.span_context(), vec![])])
+            .with_attributes(vec![
+                KeyValue::new("messaging.system", "rabbitmq"),
+                KeyValue::new("messaging.destination.name", destination),
+                KeyValue::new(
+                    "messaging.rabbitmq.destination.routing_key",
+                    routing_key.clone(),
+                ),
+                KeyValue::new("messaging.message.id", dead_letter.id.clone()),
+                KeyValue::new("dlq.queue", queue.to_owned()),
+                KeyValue::new("dlq.retries", dead_letter.retries),
+            ])
+            .start(&self.tracer);
+        let ctx = Context::new().with_span(span);
+
+        let result = self
+            .publish(channel, &ctx, &exchange, &routing_key, &body, dead_letter)
+            .await;
+
+        if let Err(err) = &result {
+            ctx.span().set_status(Status::Error {
+                description: Cow::from(err.to_string()),
+            });
+        }
+        ctx.span().end();
+
+        result?;
+        dead_letter
+            .message
+            .delivery
+            .ack(BasicAckOptions::default())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn publish(
+        &self,
+        channel: &Channel,
+        ctx: &Context,
+        exchange: &str,
+        routing_key: &str,
+        body: &[u8],
+        dead_letter: &DeadLetter,
+    ) -> Result<(), lapin::Error> {
+        let properties = dead_letter.message.delivery.properties.clone();
+
+        let mut headers = properties.headers().clone().unwrap_or_default();
+        let mut inner = headers.inner().clone();
+        for header in DEATH_HEADERS {
+            inner.remove(header);
+        }
+        headers = inner.into();
+
+        let mut trace_headers: HashMap<String, String> = HashMap::new();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(ctx, &mut trace_headers)
+        });
+        for (key, value) in trace_headers {
+            headers.insert(
+                ShortString::from(key),
+                AMQPValue::LongString(LongString::from(value)),
+            );
+        }
+
+        channel
+            .basic_publish(
+                exchange,
+                routing_key,
+                BasicPublishOptions::default(),
+                body,
+                properties.with_headers(headers),
+            )
+            .await?
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Applies a JSON merge patch: objects are merged recursively, `null` removes a member and any
+/// other value replaces it.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        other => {
+            *target = other.clone();
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+
+    if let Value::Object(members) = target {
+        for (key, value) in patch {
+            match value {
+                Value::Null => {
+                    members.remove(key);
+                }
+                value => merge_patch(members.entry(key.clone()).or_insert(Value::Null), value),
+            }
+        }
+    }
+}
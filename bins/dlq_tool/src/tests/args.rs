@@ -0,0 +1,85 @@
+use crate::{Args, Command, DEFAULT_LIMIT, USAGE};
+
+fn parse(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|a| a.to_string()).collect())
+}
+
+fn replay(args: &[&str]) -> crate::ReplayArgs {
+    match parse(args).map(|a| a.command) {
+        Ok(Command::Replay(replay)) => replay,
+        _ => panic!("{:?} is not a replay", args),
+    }
+}
+
+#[test]
+fn list_and_show_differ_only_in_payloads() {
+    let list = parse(&["list", "simple-queue"]).unwrap();
+    assert!(matches!(list.command, Command::List { payloads: false }));
+    assert_eq!(list.queue, "simple-queue");
+    assert_eq!(list.limit, DEFAULT_LIMIT);
+
+    let show = parse(&["show", "simple-queue", "--limit", "5"]).unwrap();
+    assert!(matches!(show.command, Command::List { payloads: true }));
+    assert_eq!(show.limit, 5);
+}
+
+#[test]
+fn replay_goes_to_the_queue_unless_asked_for_the_exchange() {
+    let to_queue = replay(&["replay", "simple-queue", "--all"]);
+    assert!(to_queue.all);
+    assert!(!to_queue.to_exchange);
+
+    let to_exchange = replay(&["replay", "simple-queue", "a", "b", "--to-exchange"]);
+    assert_eq!(to_exchange.ids, vec!["a", "b"]);
+    assert!(to_exchange.to_exchange);
+}
+
+#[test]
+fn replay_takes_a_patch_file() {
+    let replay = replay(&["replay", "simple-queue", "--all", "--patch", "fix.json"]);
+
+    assert_eq!(replay.patch.as_deref(), Some("fix.json"));
+}
+
+#[test]
+fn replay_needs_either_all_or_ids() {
+    for args in [
+        vec!["replay", "simple-queue"],
+        vec!["replay", "simple-queue", "--all", "a"],
+    ] {
+        assert_eq!(
+            parse(&args).err().as_deref(),
+            Some("replay needs either --all or message ids"),
+            "{:?}",
+            args
+        );
+    }
+}
+
+#[test]
+fn invalid_args_are_rejected() {
+    assert_eq!(parse(&[]).err().as_deref(), Some(USAGE));
+    assert_eq!(parse(&["list"]).err().as_deref(), Some(USAGE));
+    assert_eq!(
+        parse(&["purge", "simple-queue"]).err().as_deref(),
+        Some(USAGE)
+    );
+    assert_eq!(
+        parse(&["replay", "simple-queue", "--all", "--to-queue"])
+            .err()
+            .as_deref(),
+        Some("unknown flag --to-queue")
+    );
+    assert_eq!(
+        parse(&["list", "simple-queue", "--limit", "all"])
+            .err()
+            .as_deref(),
+        Some("--limit needs a number")
+    );
+    assert_eq!(
+        parse(&["replay", "simple-queue", "--all", "--patch"])
+            .err()
+            .as_deref(),
+        Some("--patch needs a file")
+    );
+}
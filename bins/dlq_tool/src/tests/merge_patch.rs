@@ -0,0 +1,103 @@
+use crate::replay::merge_patch;
+use serde_json::{json, Value};
+
+fn patched(target: Value, patch: Value) -> Value {
+    let mut target = target;
+    merge_patch(&mut target, &patch);
+    target
+}
+
+#[test]
+fn members_are_merged_recursively() {
+    assert_eq!(
+        patched(
+            json!({ "type": "todo.created", "data": { "name": "broken", "description": "d" } }),
+            json!({ "data": { "name": "fixed" } }),
+        ),
+        json!({ "type": "todo.created", "data": { "name": "fixed", "description": "d" } })
+    );
+}
+
+#[test]
+fn null_removes_a_member() {
+    assert_eq!(
+        patched(
+            json!({ "data": { "name": "n", "owner_id": "o" } }),
+            json!({ "data": { "owner_id": null } }),
+        ),
+        json!({ "data": { "name": "n" } })
+    );
+}
+
+#[test]
+fn non_objects_replace_the_target() {
+    assert_eq!(
+        patched(json!({ "tags": ["a", "b"] }), json!({ "tags": ["c"] })),
+        json!({ "tags": ["c"] })
+    );
+    assert_eq!(patched(json!({ "a": 1 }), json!("text")), json!("text"));
+    assert_eq!(
+        patched(json!("text"), json!({ "a": { "b": null, "c": 1 } })),
+        json!({ "a": { "c": 1 } })
+    );
+}
+
+// RFC 7396, appendix A
+#[test]
+fn rfc_examples() {
+    let examples = [
+        (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+        (
+            json!({"a": "b"}),
+            json!({"b": "c"}),
+            json!({"a": "b", "b": "c"}),
+        ),
+        (json!({"a": "b"}), json!({"a": null}), json!({})),
+        (
+            json!({"a": "b", "b": "c"}),
+            json!({"a": null}),
+            json!({"b": "c"}),
+        ),
+        (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+        (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+        (
+            json!({"a": {"b": "c"}}),
+            json!({"a": {"b": "d", "c": null}}),
+            json!({"a": {"b": "d"}}),
+        ),
+        (
+            json!({"a": [{"b": "c"}]}),
+            json!({"a": [1]}),
+            json!({"a": [1]}),
+        ),
+        (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+        (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+        (json!({"a": "foo"}), json!(null), json!(null)),
+        (json!({"a": "foo"}), json!("bar"), json!("bar")),
+        (
+            json!({"e": null}),
+            json!({"a": 1}),
+            json!({"e": null, "a": 1}),
+        ),
+        (
+            json!([1, 2]),
+            json!({"a": "b", "c": null}),
+            json!({"a": "b"}),
+        ),
+        (
+            json!({}),
+            json!({"a": {"bb": {"ccc": null}}}),
+            json!({"a": {"bb": {}}}),
+        ),
+    ];
+
+    for (target, patch, expected) in examples {
+        assert_eq!(
+            patched(target.clone(), patch.clone()),
+            expected,
+            "{} + {}",
+            target,
+            patch
+        );
+    }
+}
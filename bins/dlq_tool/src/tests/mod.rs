@@ -0,0 +1,2 @@
+mod args;
+mod merge_patch;
@@ -0,0 +1,88 @@
+use auth::jwt_manager::JwtManager;
+use opentelemetry::Context;
+use shared::{
+    auth::{authenticate_api_key, AuthMethod, Principal, TokenClaims},
+    repositories::ApiKeyRepository,
+};
+use std::sync::Arc;
+use tonic::{metadata::MetadataMap, Status};
+use tracing::error;
+
+const AUTHORIZATION_METADATA_KEY: &str = "authorization";
+const API_KEY_METADATA_KEY: &str = "x-api-key";
+
+/// Authenticates RPCs the same way the HTTP server does: a bearer JWT in the `authorization`
+/// metadata, or an API key in `x-api-key`, whose scopes must include the RPC's scope (or
+/// `todos:admin`). Answers `unauthenticated` or `permission_denied` otherwise.
+pub struct GrpcAuthenticator {
+    jwt_manager: Arc<dyn JwtManager>,
+    api_keys: Arc<dyn ApiKeyRepository>,
+}
+
+impl GrpcAuthenticator {
+    pub fn new(
+        jwt_manager: Arc<dyn JwtManager>,
+        api_keys: Arc<dyn ApiKeyRepository>,
+    ) -> Arc<GrpcAuthenticator> {
+        Arc::new(GrpcAuthenticator {
+            jwt_manager,
+            api_keys,
+        })
+    }
+
+    pub async fn require(
+        &self,
+        ctx: &Context,
+        metadata: &MetadataMap,
+        scope: &str,
+    ) -> Result<Principal, Status> {
+        let principal = self.authenticate(ctx, metadata).await?;
+
+        if !principal.grants(scope) {
+            return Err(Status::permission_denied(format!(
+                "missing scope: {}",
+                scope
+            )));
+        }
+
+        Ok(principal)
+    }
+
+    async fn authenticate(
+        &self,
+        ctx: &Context,
+        metadata: &MetadataMap,
+    ) -> Result<Principal, Status> {
+        if let Some(key) = metadata
+            .get(API_KEY_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+        {
+            return authenticate_api_key(ctx, self.api_keys.as_ref(), key)
+                .await
+                .ok_or_else(|| Status::unauthenticated("invalid api key"));
+        }
+
+        let token = match metadata
+            .get(AUTHORIZATION_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+        {
+            None => Err(Status::unauthenticated("missing credentials")),
+            Some(t) => Ok(t),
+        }?;
+
+        let session = match self.jwt_manager.verify(ctx, token).await {
+            Err(err) => {
+                error!(error = err.to_string(), "invalid jwt");
+                Err(Status::unauthenticated("invalid token"))
+            }
+            Ok(s) => Ok(s),
+        }?;
+
+        Ok(Principal {
+            subject: session.sub,
+            scopes: TokenClaims::from_token(token).unwrap_or_default().scopes(),
+            method: AuthMethod::Jwt,
+        })
+    }
+}
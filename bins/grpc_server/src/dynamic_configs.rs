@@ -0,0 +1,24 @@
+use configs::DynamicConfigs;
+use std::env;
+
+const GRPC_PORT_ENV_KEY: &str = "GRPC_PORT";
+
+#[derive(Debug, Clone)]
+pub struct GrpcServerConfigs {
+    pub port: u16,
+}
+
+impl Default for GrpcServerConfigs {
+    fn default() -> Self {
+        GrpcServerConfigs { port: 50051 }
+    }
+}
+
+impl DynamicConfigs for GrpcServerConfigs {
+    fn load(&mut self) {
+        self.port = env::var(GRPC_PORT_ENV_KEY)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(self.port);
+    }
+}
@@ -0,0 +1,29 @@
+use opentelemetry::propagation::Extractor;
+use tonic::metadata::{KeyRef, MetadataMap};
+
+/// Reads W3C trace context from gRPC metadata, the gRPC counterpart of `HTTPExtractor`.
+pub struct MetadataExtractor<'a> {
+    metadata: &'a MetadataMap,
+}
+
+impl<'a> MetadataExtractor<'a> {
+    pub fn new(metadata: &'a MetadataMap) -> MetadataExtractor<'a> {
+        MetadataExtractor { metadata }
+    }
+}
+
+impl<'a> Extractor for MetadataExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.metadata
+            .keys()
+            .map(|key| match key {
+                KeyRef::Ascii(k) => k.as_str(),
+                KeyRef::Binary(k) => k.as_str(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,33 @@
+use crate::services::{proto::todo_service_server::TodoServiceServer, TodoGrpcService};
+use health_readiness::HealthReadinessService;
+use std::{sync::Arc, time::Duration};
+use tonic_health::server::HealthReporter;
+use tracing::warn;
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Keeps the gRPC health status of the todo service in line with its dependencies, running the
+/// same readiness checks as the HTTP server every `HEALTH_CHECK_INTERVAL`.
+pub fn report_health(mut reporter: HealthReporter, checker: Arc<dyn HealthReadinessService>) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            match checker.validate().await {
+                Err(err) => {
+                    warn!(error = err.to_string(), "dependencies unhealthy");
+                    reporter
+                        .set_not_serving::<TodoServiceServer<TodoGrpcService>>()
+                        .await;
+                }
+                Ok(_) => {
+                    reporter
+                        .set_serving::<TodoServiceServer<TodoGrpcService>>()
+                        .await;
+                }
+            }
+        }
+    });
+}
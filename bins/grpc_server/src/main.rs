@@ -0,0 +1,107 @@
+mod authenticator;
+mod dynamic_configs;
+mod extractors;
+mod health;
+mod services;
+
+use amqp::{
+    channel::new_amqp_channel,
+    exchange::ExchangeDefinition,
+    topology::{AmqpTopology, Topology},
+};
+use auth::jwt_manager::auth0::Auth0JwtManager;
+use authenticator::GrpcAuthenticator;
+use configs::Configs;
+use configs_builder::ConfigBuilder;
+use dynamic_configs::GrpcServerConfigs;
+use health_readiness::HealthReadinessServiceImpl;
+use infra::{
+    messaging::{ConfirmingPublisher, PublisherConfigs},
+    repositories::{ApiKeyRepositoryImpl, SlowQueryConfigs, TodoRepositoryImpl},
+    telemetry::{self, TelemetryExporter},
+};
+use services::{
+    proto::{todo_service_server::TodoServiceServer, FILE_DESCRIPTOR_SET},
+    TodoGrpcService,
+};
+use shared::amqp::{EventsConfigs, EXCHANGE};
+use sql_pool::postgres::conn_pool;
+use std::{error::Error, net::SocketAddr, sync::Arc};
+use tonic::transport::Server;
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cfg = default_setup().await?;
+
+    let (connection, channel) = new_amqp_channel(&cfg).await?;
+    let db_conn = Arc::new(conn_pool(&cfg.postgres)?);
+    let slow_query = SlowQueryConfigs::from_env();
+
+    AmqpTopology::new(channel.clone())
+        .exchange(&ExchangeDefinition::new(EXCHANGE).topic().durable())
+        .install()
+        .await?;
+
+    let authenticator = GrpcAuthenticator::new(
+        Auth0JwtManager::new(&cfg.auth0),
+        ApiKeyRepositoryImpl::new(db_conn.clone(), &cfg.postgres, &slow_query),
+    );
+
+    let service = TodoGrpcService::new(
+        authenticator,
+        TodoRepositoryImpl::new(db_conn.clone(), &cfg.postgres, &slow_query),
+        ConfirmingPublisher::new(channel.clone(), &PublisherConfigs::from_env()).await?,
+        EventsConfigs::from_env()?,
+    );
+
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    health::report_health(
+        health_reporter,
+        Arc::new(
+            HealthReadinessServiceImpl::default()
+                .amqp(connection.clone())
+                .postgres(db_conn.clone()),
+        ),
+    );
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(
+            tonic_health::proto::GRPC_HEALTH_V1_FILE_DESCRIPTOR_SET,
+        )
+        .build()?;
+
+    let addr: SocketAddr = format!("{}:{}", cfg.app.host, cfg.dynamic.port).parse()?;
+    info!("grpc server listening on {}", addr);
+
+    Server::builder()
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(TodoServiceServer::new(service))
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn default_setup() -> Result<Configs<GrpcServerConfigs>, Box<dyn Error>> {
+    let cfg = ConfigBuilder::new()
+        .use_aws_secret_manager()
+        .otlp()
+        .auth0()
+        .amqp()
+        .postgres()
+        .build::<GrpcServerConfigs>()
+        .await?;
+
+    let exporter = TelemetryExporter::from_env()?;
+    if exporter == TelemetryExporter::Otlp {
+        traces::otlp::setup(&cfg)?;
+    }
+    telemetry::setup(exporter, &cfg.app.name, telemetry::default_selector())?;
+
+    Ok(cfg)
+}
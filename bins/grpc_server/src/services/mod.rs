@@ -0,0 +1,9 @@
+mod todos;
+
+pub mod proto {
+    tonic::include_proto!("todos.v1");
+
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("todos_descriptor");
+}
+
+pub use todos::TodoGrpcService;
@@ -0,0 +1,267 @@
+use super::proto::{
+    todo_service_server::TodoService, CreateTodoRequest, DeleteTodoRequest, DeleteTodoResponse,
+    GetTodoRequest, ListTodosRequest, ListTodosResponse, Todo as TodoMessage,
+};
+use crate::{authenticator::GrpcAuthenticator, extractors::MetadataExtractor};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    trace::{SpanKind, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use shared::{
+    amqp::{
+        publish_event, EventPublisher, EventsConfigs, PublishError, UnpublishedKind, EXCHANGE,
+        TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY,
+    },
+    auth::{TODOS_READ_SCOPE, TODOS_WRITE_SCOPE},
+    models::todo::{CreateTodo, Todo, TodoCreatedMessage, TodoDeletedMessage},
+    repositories::TodoRepository,
+};
+use std::sync::Arc;
+use tonic::{metadata::MetadataMap, Request, Response, Status};
+use tracing::error;
+
+const SERVICE_NAME: &str = "todos.v1.TodoService";
+const MAX_PAGE_SIZE: i32 = 100;
+
+pub struct TodoGrpcService {
+    tracer: BoxedTracer,
+    auth: Arc<GrpcAuthenticator>,
+    repo: Arc<dyn TodoRepository>,
+    publisher: Arc<dyn EventPublisher>,
+    events: EventsConfigs,
+}
+
+impl TodoGrpcService {
+    pub fn new(
+        auth: Arc<GrpcAuthenticator>,
+        repo: Arc<dyn TodoRepository>,
+        publisher: Arc<dyn EventPublisher>,
+        events: EventsConfigs,
+    ) -> TodoGrpcService {
+        let tracer = global::tracer("grpc-server");
+
+        TodoGrpcService {
+            tracer,
+            auth,
+            repo,
+            publisher,
+            events,
+        }
+    }
+
+    /// Continues the caller's trace from the request metadata and opens the server span for the RPC.
+    fn server_context(&self, method: &str, metadata: &MetadataMap) -> Context {
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&MetadataExtractor::new(metadata))
+        });
+
+        let span = self
+            .tracer
+            .span_builder(format!("{}/{}", SERVICE_NAME, method))
+            .with_kind(SpanKind::Server)
+            .with_attributes(vec![
+                KeyValue::new("rpc.system", "grpc"),
+                KeyValue::new("rpc.service", SERVICE_NAME),
+                KeyValue::new("rpc.method", method.to_owned()),
+            ])
+            .start_with_context(&self.tracer, &parent);
+
+        parent.with_span(span)
+    }
+}
+
+impl From<&Todo> for TodoMessage {
+    fn from(value: &Todo) -> Self {
+        TodoMessage {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            created_at: value.created_at.clone(),
+            updated_at: value.updated_at.clone(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl TodoService for TodoGrpcService {
+    async fn create(
+        &self,
+        request: Request<CreateTodoRequest>,
+    ) -> Result<Response<TodoMessage>, Status> {
+        let ctx = self.server_context("Create", request.metadata());
+        let principal = self
+            .auth
+            .require(&ctx, request.metadata(), TODOS_WRITE_SCOPE)
+            .await?;
+        let req = request.into_inner();
+
+        let created = match self
+            .repo
+            .create(
+                &ctx,
+                &CreateTodo {
+                    name: req.name,
+                    description: req.description,
+                    owner_id: Some(principal.subject),
+                },
+            )
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to create todo");
+                Err(Status::internal("error to create todo"))
+            }
+            Ok(t) => Ok(t),
+        }?;
+
+        match publish_event(
+            self.publisher.as_ref(),
+            &self.events,
+            &ctx,
+            EXCHANGE,
+            TODO_CREATED_ROUTING_KEY,
+            TodoCreatedMessage::from(&created),
+        )
+        .await
+        {
+            Err(
+                err @ (PublishError::ConfirmTimeout(_)
+                | PublishError::Nacked
+                | PublishError::Unroutable(_)),
+            ) => Err(unpublished_event("todo created", &err)),
+            Err(err) => {
+                error!(error = err.to_string(), "error to create todo");
+                Err(Status::internal("error to create todo"))
+            }
+            _ => Ok(Response::new(TodoMessage::from(&created))),
+        }
+    }
+
+    async fn get(&self, request: Request<GetTodoRequest>) -> Result<Response<TodoMessage>, Status> {
+        let ctx = self.server_context("Get", request.metadata());
+        let _ = self
+            .auth
+            .require(&ctx, request.metadata(), TODOS_READ_SCOPE)
+            .await?;
+        let req = request.into_inner();
+
+        match self.repo.get_by_id(&ctx, &req.id).await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get todo");
+                Err(Status::invalid_argument("error to get todo"))
+            }
+            Ok(None) => Err(Status::not_found("todo not found")),
+            Ok(Some(todo)) => Ok(Response::new(TodoMessage::from(&todo))),
+        }
+    }
+
+    async fn list(
+        &self,
+        request: Request<ListTodosRequest>,
+    ) -> Result<Response<ListTodosResponse>, Status> {
+        let ctx = self.server_context("List", request.metadata());
+        let _ = self
+            .auth
+            .require(&ctx, request.metadata(), TODOS_READ_SCOPE)
+            .await?;
+        let req = request.into_inner();
+
+        let limit = match req.limit {
+            0 => 10,
+            l => l.clamp(1, MAX_PAGE_SIZE),
+        };
+
+        match self
+            .repo
+            .list_paginated(&ctx, limit, req.offset.max(0))
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to list todo");
+                Err(Status::internal("error to list todo"))
+            }
+            Ok(todos) => Ok(Response::new(ListTodosResponse {
+                todos: todos.iter().map(TodoMessage::from).collect(),
+            })),
+        }
+    }
+
+    async fn delete(
+        &self,
+        request: Request<DeleteTodoRequest>,
+    ) -> Result<Response<DeleteTodoResponse>, Status> {
+        let ctx = self.server_context("Delete", request.metadata());
+        let principal = self
+            .auth
+            .require(&ctx, request.metadata(), TODOS_WRITE_SCOPE)
+            .await?;
+        let req = request.into_inner();
+
+        let existing = match self.repo.get_by_id(&ctx, &req.id).await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get todo");
+                Err(Status::invalid_argument("error to delete todo"))
+            }
+            Ok(t) => Ok(t),
+        }?;
+
+        match existing {
+            None => return Ok(Response::new(DeleteTodoResponse {})),
+            Some(todo) if !principal.can_change(&todo) => {
+                return Err(Status::permission_denied(
+                    "only the owner of the todo or an admin may delete it",
+                ))
+            }
+            _ => {}
+        }
+
+        let deleted = match self.repo.delete(&ctx, &req.id).await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to delete todo");
+                Err(Status::invalid_argument("error to delete todo"))
+            }
+            Ok(d) => Ok(d),
+        }?;
+
+        let deleted = match deleted {
+            None => return Ok(Response::new(DeleteTodoResponse {})),
+            Some(d) => d,
+        };
+
+        match publish_event(
+            self.publisher.as_ref(),
+            &self.events,
+            &ctx,
+            EXCHANGE,
+            TODO_DELETED_ROUTING_KEY,
+            TodoDeletedMessage::from(&deleted),
+        )
+        .await
+        {
+            Err(
+                err @ (PublishError::ConfirmTimeout(_)
+                | PublishError::Nacked
+                | PublishError::Unroutable(_)),
+            ) => Err(unpublished_event("todo deleted", &err)),
+            Err(err) => {
+                error!(error = err.to_string(), "error to delete todo");
+                Err(Status::internal("error to delete todo"))
+            }
+            _ => Ok(Response::new(DeleteTodoResponse {})),
+        }
+    }
+}
+
+/// Status of a call whose `change`, e.g. `todo created`, was committed but whose event the
+/// broker did not take: unavailable when the event was not confirmed in time or was nacked, and
+/// failed precondition when it was returned as unroutable, since no queue would receive it.
+fn unpublished_event(change: &str, err: &PublishError) -> Status {
+    let unpublished = err.unpublished(change);
+
+    match unpublished.kind {
+        UnpublishedKind::Unavailable => Status::unavailable(unpublished.message),
+        UnpublishedKind::Unroutable => Status::failed_precondition(unpublished.message),
+        UnpublishedKind::Failed => Status::internal(unpublished.message),
+    }
+}
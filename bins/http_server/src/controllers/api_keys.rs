@@ -0,0 +1,222 @@
+use crate::{
+    extractors::{AuthMethod, RequireScope, ScopeRequirement, TodosAdmin, TodosRead, TodosWrite},
+    viewmodels::{ApiKeyResponse, CreateApiKeyRequest, CreatedApiKeyResponse},
+};
+use actix_web::{
+    delete, get,
+    http::StatusCode,
+    post,
+    web::{Data, Json, Path},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use http_components::{middlewares::otel::HTTPExtractor, viewmodels::HTTPError};
+use opentelemetry::global;
+use shared::{
+    models::api_key::{generate_api_key, hash_api_key, CreateApiKey},
+    repositories::ApiKeyRepository,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+const KNOWN_SCOPES: [&str; 3] = [TodosRead::SCOPE, TodosWrite::SCOPE, TodosAdmin::SCOPE];
+
+/// Request to create a new API key for machine clients.
+///
+/// The plaintext key is only returned in this response, only its hash is stored. Keys for another
+/// owner can only be minted by admins authenticated with a JWT, so a leaked admin key cannot mint
+/// keys on behalf of anyone else. Every key records the admin that minted it.
+///
+#[utoipa::path(
+    post,
+    path = "",
+    context_path = "/v1/api-keys",
+    tag = "api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created", body = CreatedApiKeyResponse),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:admin"]),
+        ("api_key" = ["todos:admin"])
+    )
+)]
+#[post("")]
+pub async fn create_api_key(
+    req: HttpRequest,
+    auth: RequireScope<TodosAdmin>,
+    body: Json<CreateApiKeyRequest>,
+    repo: Data<Arc<dyn ApiKeyRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let body = body.into_inner();
+
+    if let Some(scope) = body
+        .scopes
+        .iter()
+        .find(|s| !KNOWN_SCOPES.contains(&s.as_str()))
+    {
+        return Err(HTTPError {
+            status_code: StatusCode::BAD_REQUEST.into(),
+            message: "invalid scope".to_owned(),
+            details: format!("unknown scope: {}", scope),
+        });
+    }
+
+    let owner_id = body.owner_id.unwrap_or_else(|| auth.subject());
+    if owner_id != auth.principal.subject && auth.principal.method != AuthMethod::Jwt {
+        return Err(HTTPError {
+            status_code: StatusCode::FORBIDDEN.into(),
+            message: "forbidden".to_owned(),
+            details: "keys for another owner can only be created with a user token".to_owned(),
+        });
+    }
+
+    let key = generate_api_key();
+
+    let create = CreateApiKey {
+        name: body.name,
+        key_hash: hash_api_key(&key),
+        owner_id,
+        scopes: body.scopes,
+        expires_at: body.expires_at,
+        created_by: auth.subject(),
+    };
+
+    match repo.create(&ctx, &create).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to create api key");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to create api key".to_owned(),
+                details: "error to create api key".to_owned(),
+            })
+        }
+        Ok(created) => {
+            info!(
+                audit = "api_key.created",
+                actor = auth.principal.subject.as_str(),
+                key_id = created.id.as_str(),
+                owner_id = created.owner_id.as_str(),
+                scopes = created.scopes.join(" "),
+                "api key created"
+            );
+            Ok(HttpResponse::Created().json(CreatedApiKeyResponse::new(&created, key)))
+        }
+    }
+}
+
+/// Request to list every API key, including the revoked ones.
+///
+/// If the request was process correctly this endpoint will return 200 Ok and 4xx/5xx if some error occur.
+///
+#[utoipa::path(
+    get,
+    path = "",
+    context_path = "/v1/api-keys",
+    tag = "api-keys",
+    responses(
+        (status = 200, description = "Success", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:admin"]),
+        ("api_key" = ["todos:admin"])
+    )
+)]
+#[get("")]
+pub async fn list_api_keys(
+    req: HttpRequest,
+    _: RequireScope<TodosAdmin>,
+    repo: Data<Arc<dyn ApiKeyRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    match repo.list(&ctx).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to list api keys");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to list api keys".to_owned(),
+                details: "error to list api keys".to_owned(),
+            })
+        }
+        Ok(keys) => Ok(HttpResponse::Ok().json(
+            keys.iter()
+                .map(ApiKeyResponse::from)
+                .collect::<Vec<ApiKeyResponse>>(),
+        )),
+    }
+}
+
+/// Request to revoke an API key by ID.
+///
+/// If the request was process correctly this endpoint will return 200 Ok, 404 when there is no
+/// active key with this ID and 4xx/5xx if some error occur.
+///
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    context_path = "/v1/api-keys",
+    tag = "api-keys",
+    responses(
+        (status = 200, description = "Revoked"),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 404, description = "No active key with this ID", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:admin"]),
+        ("api_key" = ["todos:admin"])
+    )
+)]
+#[delete("/{id}")]
+pub async fn revoke_api_key(
+    req: HttpRequest,
+    path: Path<(String,)>,
+    auth: RequireScope<TodosAdmin>,
+    repo: Data<Arc<dyn ApiKeyRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let (id,) = path.into_inner();
+
+    match repo.revoke(&ctx, &id).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to revoke api key");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to revoke api key".to_owned(),
+                details: "error to revoke api key".to_owned(),
+            })
+        }
+        Ok(false) => Err(HTTPError {
+            status_code: StatusCode::NOT_FOUND.into(),
+            message: "api key not found".to_owned(),
+            details: format!("no active api key with id {}", id),
+        }),
+        Ok(true) => {
+            info!(
+                audit = "api_key.revoked",
+                actor = auth.principal.subject.as_str(),
+                key_id = id.as_str(),
+                "api key revoked"
+            );
+            Ok(HttpResponse::Ok().finish())
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    events::TodoEventsBroadcaster,
+    extractors::{RequireScope, TodosRead},
+};
+use actix_web::{
+    get,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    web::{Bytes, Data},
+    HttpRequest, HttpResponse, Responder,
+};
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+const HEARTBEAT_FRAME: &[u8] = b": heartbeat\n\n";
+
+/// Stream of changes to the authenticated owner's ToDo's.
+///
+/// Server-Sent Events stream pushing `created`, `updated`, `deleted`, `restored` and `completed` events. Reconnecting clients can send the
+/// `Last-Event-ID` header to receive the events they missed while they were away.
+///
+#[utoipa::path(
+    get,
+    path = "/events",
+    context_path = "/v1/todos",
+    tag = "todos",
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last event received by the client")
+    ),
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = TodoEventResponse),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
+    )
+)]
+#[get("/events")]
+pub async fn events(
+    req: HttpRequest,
+    auth: RequireScope<TodosRead>,
+    broadcaster: Data<Arc<TodoEventsBroadcaster>>,
+) -> impl Responder {
+    let last_event_id = req
+        .headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    let owner_id = auth.subject();
+    let broadcaster = broadcaster.get_ref().clone();
+    let (missed, mut receiver) = broadcaster.subscribe(&owner_id, last_event_id);
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, Infallible>>(32);
+
+    tokio::spawn(async move {
+        let _guard = broadcaster.client_connected();
+
+        for event in missed {
+            if tx.send(Ok(event.to_frame())).await.is_err() {
+                return;
+            }
+        }
+
+        let mut heartbeat = tokio::time::interval(broadcaster.heartbeat_interval);
+
+        loop {
+            let frame = tokio::select! {
+                _ = heartbeat.tick() => Bytes::from_static(HEARTBEAT_FRAME),
+                received = receiver.recv() => match received {
+                    Ok(event) if event.belongs_to(&owner_id) => event.to_frame(),
+                    Ok(_) => continue,
+                    // the client fell behind, closing the stream makes it reconnect with
+                    // Last-Event-ID and recover the missed events from the replay buffer
+                    Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return,
+                },
+            };
+
+            if tx.send(Ok(frame)).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(ReceiverStream::new(rx))
+}
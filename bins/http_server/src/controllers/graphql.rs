@@ -0,0 +1,51 @@
+use crate::{extractors::authenticate, graphql::TodoSchema};
+use actix_web::{
+    web::{Data, Payload},
+    HttpRequest, HttpResponse, Result,
+};
+use async_graphql::Data as GraphQLData;
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use http_components::middlewares::otel::HTTPExtractor;
+use opentelemetry::{global, trace::FutureExt};
+
+/// Executes GraphQL queries and mutations.
+///
+/// The route only authenticates the caller, every resolver then requires the scope it needs, so a
+/// `todos:write` caller can run mutations without also holding `todos:read`.
+pub async fn graphql(
+    req: HttpRequest,
+    schema: Data<TodoSchema>,
+    gql: GraphQLRequest,
+) -> Result<GraphQLResponse> {
+    let principal = authenticate(&req).await?;
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let request = gql.into_inner().data(principal);
+
+    Ok(schema.execute(request).with_context(ctx).await.into())
+}
+
+/// Upgrades the connection to a GraphQL over WebSocket session for subscriptions.
+///
+/// The trace context of the upgrade request is kept in the session data, so every subscription
+/// resolver of the connection is traced under it.
+pub async fn graphql_ws(
+    req: HttpRequest,
+    payload: Payload,
+    schema: Data<TodoSchema>,
+) -> Result<HttpResponse> {
+    let principal = authenticate(&req).await?;
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let mut data = GraphQLData::default();
+    data.insert(principal);
+    data.insert(ctx);
+
+    GraphQLSubscription::new(schema.get_ref().clone())
+        .with_data(data)
+        .start(&req, payload)
+}
@@ -1,3 +1,23 @@
+mod api_keys;
+mod events;
+mod graphql;
+mod publish_errors;
+mod stats;
 mod todos;
+mod todos_v2;
 
-pub use todos::{__path_delete, __path_get, __path_list, __path_post, delete, get, list, post};
+pub use api_keys::{
+    __path_create_api_key, __path_list_api_keys, __path_revoke_api_key, create_api_key,
+    list_api_keys, revoke_api_key,
+};
+pub use events::{__path_events, events};
+pub use graphql::{graphql, graphql_ws};
+pub use stats::{__path_stats, stats};
+pub use todos::{
+    __path_complete, __path_delete, __path_get, __path_list, __path_post, complete, delete, get,
+    list, post,
+};
+pub use todos_v2::{
+    __path_delete_v2, __path_get_v2, __path_list_v2, __path_post_v2, delete_v2, get_v2, list_v2,
+    openapi_v2, post_v2,
+};
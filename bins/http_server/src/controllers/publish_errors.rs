@@ -0,0 +1,22 @@
+use actix_web::http::StatusCode;
+use http_components::viewmodels::HTTPError;
+use shared::amqp::{PublishError, UnpublishedKind};
+
+/// Error of a request whose `change`, e.g. `todo created`, was committed but whose event the
+/// broker did not take: 503 when the event was not confirmed in time or was nacked, so retrying
+/// later may work, and 502 when it was returned as unroutable, since no queue would receive it.
+pub(crate) fn unpublished_event(change: &str, err: &PublishError) -> HTTPError {
+    let unpublished = err.unpublished(change);
+
+    let status_code = match unpublished.kind {
+        UnpublishedKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        UnpublishedKind::Unroutable => StatusCode::BAD_GATEWAY,
+        UnpublishedKind::Failed => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    HTTPError {
+        status_code: status_code.into(),
+        message: unpublished.message,
+        details: unpublished.details,
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    extractors::{RequireScope, TodosRead},
+    viewmodels::{TodoStatsQuery, TodoStatsResponse},
+};
+use actix_web::{
+    get,
+    http::StatusCode,
+    web::{Data, Query},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use http_components::{middlewares::otel::HTTPExtractor, viewmodels::HTTPError};
+use opentelemetry::global;
+use shared::repositories::TodoStatsRepository;
+use std::sync::Arc;
+use tracing::error;
+
+const DEFAULT_STATS_DAYS: i64 = 30;
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+/// Request the daily statistics of the authenticated owner's ToDo's.
+///
+/// Returns how many ToDo's were created, deleted and completed on each day with activity between `from` and `to`. The statistics are projected from the ToDo events, so they can lag behind the ToDo's for a moment.
+///
+#[utoipa::path(
+    get,
+    path = "/stats",
+    context_path = "/v1/todos",
+    tag = "todos",
+    params(TodoStatsQuery),
+    responses(
+        (status = 200, description = "Success", body = Vec<TodoStatsResponse>),
+        (status = 400, description = "Invalid `from` or `to` day", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
+    )
+)]
+#[get("/stats")]
+pub async fn stats(
+    req: HttpRequest,
+    auth: RequireScope<TodosRead>,
+    query: Query<TodoStatsQuery>,
+    repo: Data<Arc<dyn TodoStatsRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let today = Utc::now().date_naive();
+    let to = match &query.to {
+        None => today,
+        Some(to) => parse_day("to", to)?,
+    };
+    let from = match &query.from {
+        None => today - Duration::days(DEFAULT_STATS_DAYS - 1),
+        Some(from) => parse_day("from", from)?,
+    };
+
+    if from > to {
+        return Err(HTTPError {
+            status_code: StatusCode::BAD_REQUEST.into(),
+            message: "invalid stats range".to_owned(),
+            details: "`from` must not be after `to`".to_owned(),
+        });
+    }
+
+    match repo
+        .list_by_owner(
+            &ctx,
+            &auth.subject(),
+            &from.format(DAY_FORMAT).to_string(),
+            &to.format(DAY_FORMAT).to_string(),
+        )
+        .await
+    {
+        Err(err) => {
+            error!(error = err.to_string(), "error to get todo stats");
+            Err(HTTPError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR.into(),
+                message: "error to get todo stats".to_owned(),
+                details: "error to get todo stats".to_owned(),
+            })
+        }
+        Ok(stats) => Ok(HttpResponse::Ok().json(
+            stats
+                .iter()
+                .map(TodoStatsResponse::from)
+                .collect::<Vec<TodoStatsResponse>>(),
+        )),
+    }
+}
+
+fn parse_day(param: &str, day: &str) -> Result<NaiveDate, HTTPError> {
+    match NaiveDate::parse_from_str(day, DAY_FORMAT) {
+        Err(err) => {
+            error!(error = err.to_string(), param = param, "invalid stats day");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "invalid stats range".to_owned(),
+                details: format!("`{}` must be a day formatted as YYYY-MM-DD", param),
+            })
+        }
+        Ok(d) => Ok(d),
+    }
+}
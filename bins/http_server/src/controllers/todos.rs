@@ -1,4 +1,9 @@
-use crate::viewmodels::{CreateTodoRequest, TodoResponse};
+use super::publish_errors::unpublished_event;
+use crate::{
+    extractors::{todo_to_change, RequireScope, TodosRead, TodosWrite},
+    http_cache::{todo_cache_control, todos_cache_control, Validators},
+    viewmodels::{CreateTodoRequest, TodoResponse},
+};
 use actix_web::{
     delete, get,
     http::StatusCode,
@@ -6,14 +11,14 @@ use actix_web::{
     web::{Data, Json, Path},
     HttpRequest, HttpResponse, Responder, ResponseError,
 };
-use amqp::publisher::{Payload, Publisher};
-use http_components::{
-    extractors::JwtAuthenticateExtractor, middlewares::otel::HTTPExtractor, viewmodels::HTTPError,
-};
+use http_components::{middlewares::otel::HTTPExtractor, viewmodels::HTTPError};
 use opentelemetry::global;
 use shared::{
-    amqp::{EXCHANGE, ROUTING_KEY},
-    models::todo::TodoCreatedMessage,
+    amqp::{
+        publish_event, EventPublisher, EventsConfigs, PublishError, EXCHANGE,
+        TODO_COMPLETED_ROUTING_KEY, TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY,
+    },
+    models::todo::{CreateTodo, TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage},
     repositories::TodoRepository,
 };
 use std::sync::Arc;
@@ -34,34 +39,32 @@ use tracing::error;
         (status = 400, description = "Bad request", body = HTTPError),
         (status = 401, description = "Unauthorized", body = HTTPError),
         (status = 403, description = "Forbidden", body = HTTPError),
-        (status = 500, description = "Internal error", body = HTTPError)
+        (status = 500, description = "Internal error", body = HTTPError),
+        (status = 502, description = "Event returned by the broker as unroutable", body = HTTPError),
+        (status = 503, description = "Event not confirmed in time or nacked by the broker", body = HTTPError)
     ),
-    security()
+    security(
+        ("auth" = ["todos:write"]),
+        ("api_key" = ["todos:write"])
+    )
 )]
 #[post("")]
 pub async fn post(
     req: HttpRequest,
+    auth: RequireScope<TodosWrite>,
     todo: Json<CreateTodoRequest>,
     repo: Data<Arc<dyn TodoRepository>>,
-    publisher: Data<Arc<dyn Publisher>>,
+    publisher: Data<Arc<dyn EventPublisher>>,
+    events: Data<EventsConfigs>,
 ) -> Result<impl Responder, impl ResponseError> {
     let ctx = global::get_text_map_propagator(|propagator| {
         propagator.extract(&HTTPExtractor::new(req.headers()))
     });
 
-    let created = match repo.create(&ctx, &todo.0.into()).await {
-        Err(err) => {
-            error!(error = err.to_string(), "error to create todo");
-            Err(HTTPError {
-                status_code: StatusCode::BAD_REQUEST.into(),
-                message: "error to create todo".to_owned(),
-                details: "error to create todo".to_owned(),
-            })
-        }
-        Ok(t) => Ok(t),
-    }?;
+    let mut create: CreateTodo = todo.0.into();
+    create.owner_id = Some(auth.subject());
 
-    let payload = match Payload::new(&TodoCreatedMessage::from(&created)) {
+    let created = match repo.create(&ctx, &create).await {
         Err(err) => {
             error!(error = err.to_string(), "error to create todo");
             Err(HTTPError {
@@ -70,13 +73,24 @@ pub async fn post(
                 details: "error to create todo".to_owned(),
             })
         }
-        Ok(p) => Ok(p),
+        Ok(t) => Ok(t),
     }?;
 
-    match publisher
-        .publish(&ctx, EXCHANGE, ROUTING_KEY, &payload, None)
-        .await
+    match publish_event(
+        publisher.get_ref().as_ref(),
+        &events,
+        &ctx,
+        EXCHANGE,
+        TODO_CREATED_ROUTING_KEY,
+        TodoCreatedMessage::from(&created),
+    )
+    .await
     {
+        Err(
+            err @ (PublishError::ConfirmTimeout(_)
+            | PublishError::Nacked
+            | PublishError::Unroutable(_)),
+        ) => Err(unpublished_event("todo created", &err)),
         Err(err) => {
             error!(error = err.to_string(), "error to create todo");
             Err(HTTPError {
@@ -100,19 +114,21 @@ pub async fn post(
     tag = "todos",
     responses(
         (status = 200, description = "Success", body = Vec<ThingResponse>),
+        (status = 304, description = "Not modified since the ETag or date sent by the client"),
         (status = 400, description = "Bad request", body = HTTPError),
         (status = 401, description = "Unauthorized", body = HTTPError),
         (status = 403, description = "Forbidden", body = HTTPError),
         (status = 500, description = "Internal error", body = HTTPError)
     ),
     security(
-        ("auth" = [])
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
     )
 )]
 #[get("")]
 pub async fn list(
     req: HttpRequest,
-    _: JwtAuthenticateExtractor,
+    _: RequireScope<TodosRead>,
     repo: Data<Arc<dyn TodoRepository>>,
 ) -> Result<impl Responder, impl ResponseError> {
     let ctx = global::get_text_map_propagator(|propagator| {
@@ -128,12 +144,22 @@ pub async fn list(
                 details: "error to list todo".to_owned(),
             })
         }
-        Ok(todos) => Ok(HttpResponse::Ok().json(
-            todos
-                .iter()
-                .map(|e| TodoResponse::from(e))
-                .collect::<Vec<TodoResponse>>(),
-        )),
+        Ok(todos) => {
+            let validators = Validators::for_todos(&todos);
+            if validators.is_not_modified(&req) {
+                return Ok(validators.not_modified(todos_cache_control()));
+            }
+
+            let mut builder = HttpResponse::Ok();
+            validators.apply(&mut builder, todos_cache_control());
+
+            Ok(builder.json(
+                todos
+                    .iter()
+                    .map(|e| TodoResponse::from(e))
+                    .collect::<Vec<TodoResponse>>(),
+            ))
+        }
     }
 }
 
@@ -148,20 +174,22 @@ pub async fn list(
     tag = "todos",
     responses(
         (status = 200, description = "Success", body = ThingResponse),
+        (status = 304, description = "Not modified since the ETag or date sent by the client"),
         (status = 400, description = "Bad request", body = HTTPError),
         (status = 401, description = "Unauthorized", body = HTTPError),
         (status = 403, description = "Forbidden", body = HTTPError),
         (status = 500, description = "Internal error", body = HTTPError)
     ),
     security(
-        ("auth" = [])
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
     )
 )]
 #[get("/{id}")]
 pub async fn get(
     req: HttpRequest,
     path: Path<(String,)>,
-    _: JwtAuthenticateExtractor,
+    _: RequireScope<TodosRead>,
     repo: Data<Arc<dyn TodoRepository>>,
 ) -> Result<impl Responder, impl ResponseError> {
     let ctx = global::get_text_map_propagator(|propagator| {
@@ -181,7 +209,15 @@ pub async fn get(
         }
         Ok(todo) => {
             if let Some(t) = todo {
-                return Ok(HttpResponse::Ok().json(TodoResponse::from(&t)));
+                let validators = Validators::for_todo(&t);
+                if validators.is_not_modified(&req) {
+                    return Ok(validators.not_modified(todo_cache_control()));
+                }
+
+                let mut builder = HttpResponse::Ok();
+                validators.apply(&mut builder, todo_cache_control());
+
+                return Ok(builder.json(TodoResponse::from(&t)));
             }
 
             Ok(HttpResponse::Ok().finish())
@@ -191,7 +227,7 @@ pub async fn get(
 
 /// Request to delete a specific ToDo by ID.
 ///
-/// If the request was process correctly this endpoint will return 200 Ok and 4xx/5xx if some error occur.
+/// If the request was process correctly this endpoint will return 200 Ok and 4xx/5xx if some error occur. Only the owner of the ToDo or a `todos:admin` holder may delete it, anyone else gets 403.
 ///
 #[utoipa::path(
     delete,
@@ -203,18 +239,23 @@ pub async fn get(
         (status = 400, description = "Bad request", body = HTTPError),
         (status = 401, description = "Unauthorized", body = HTTPError),
         (status = 403, description = "Forbidden", body = HTTPError),
-        (status = 500, description = "Internal error", body = HTTPError)
+        (status = 500, description = "Internal error", body = HTTPError),
+        (status = 502, description = "Event returned by the broker as unroutable", body = HTTPError),
+        (status = 503, description = "Event not confirmed in time or nacked by the broker", body = HTTPError)
     ),
     security(
-        ("auth" = [])
+        ("auth" = ["todos:write"]),
+        ("api_key" = ["todos:write"])
     )
 )]
 #[delete("/{id}")]
 pub async fn delete(
     req: HttpRequest,
     path: Path<(String,)>,
-    _: JwtAuthenticateExtractor,
+    auth: RequireScope<TodosWrite>,
     repo: Data<Arc<dyn TodoRepository>>,
+    publisher: Data<Arc<dyn EventPublisher>>,
+    events: Data<EventsConfigs>,
 ) -> Result<impl Responder, impl ResponseError> {
     let ctx = global::get_text_map_propagator(|propagator| {
         propagator.extract(&HTTPExtractor::new(req.headers()))
@@ -222,7 +263,20 @@ pub async fn delete(
 
     let (id,) = path.into_inner();
 
-    match repo.delete(&ctx, &id).await {
+    if todo_to_change(
+        &ctx,
+        repo.get_ref().as_ref(),
+        &auth.principal,
+        &id,
+        "delete",
+    )
+    .await?
+    .is_none()
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let deleted = match repo.delete(&ctx, &id).await {
         Err(err) => {
             error!(error = err.to_string(), "error to create todo");
             Err(HTTPError {
@@ -231,6 +285,132 @@ pub async fn delete(
                 details: "error to create todo".to_owned(),
             })
         }
+        Ok(d) => Ok(d),
+    }?;
+
+    let deleted = match deleted {
+        None => return Ok(HttpResponse::Ok().finish()),
+        Some(d) => d,
+    };
+
+    match publish_event(
+        publisher.get_ref().as_ref(),
+        &events,
+        &ctx,
+        EXCHANGE,
+        TODO_DELETED_ROUTING_KEY,
+        TodoDeletedMessage::from(&deleted),
+    )
+    .await
+    {
+        Err(
+            err @ (PublishError::ConfirmTimeout(_)
+            | PublishError::Nacked
+            | PublishError::Unroutable(_)),
+        ) => Err(unpublished_event("todo deleted", &err)),
+        Err(err) => {
+            error!(error = err.to_string(), "error to delete todo");
+            Err(HTTPError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR.into(),
+                message: "error to delete todo".to_owned(),
+                details: "error to delete todo".to_owned(),
+            })
+        }
+        _ => Ok(HttpResponse::Ok().finish()),
+    }
+}
+
+/// Request to complete a specific ToDo by ID.
+///
+/// If the request was process correctly this endpoint will return 200 Ok and 4xx/5xx if some error occur. Completing a deleted or already completed ToDo does nothing. Only the owner of the ToDo or a `todos:admin` holder may complete it, anyone else gets 403.
+///
+#[utoipa::path(
+    post,
+    path = "/{id}/complete",
+    context_path = "/v1/todos",
+    tag = "todos",
+    responses(
+        (status = 200, description = "Completed"),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError),
+        (status = 502, description = "Event returned by the broker as unroutable", body = HTTPError),
+        (status = 503, description = "Event not confirmed in time or nacked by the broker", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:write"]),
+        ("api_key" = ["todos:write"])
+    )
+)]
+#[post("/{id}/complete")]
+pub async fn complete(
+    req: HttpRequest,
+    path: Path<(String,)>,
+    auth: RequireScope<TodosWrite>,
+    repo: Data<Arc<dyn TodoRepository>>,
+    publisher: Data<Arc<dyn EventPublisher>>,
+    events: Data<EventsConfigs>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let (id,) = path.into_inner();
+
+    if todo_to_change(
+        &ctx,
+        repo.get_ref().as_ref(),
+        &auth.principal,
+        &id,
+        "complete",
+    )
+    .await?
+    .is_none()
+    {
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let completed = match repo.complete(&ctx, &id).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to complete todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to complete todo".to_owned(),
+                details: "error to complete todo".to_owned(),
+            })
+        }
+        Ok(d) => Ok(d),
+    }?;
+
+    let completed = match completed {
+        None => return Ok(HttpResponse::Ok().finish()),
+        Some(c) => c,
+    };
+
+    match publish_event(
+        publisher.get_ref().as_ref(),
+        &events,
+        &ctx,
+        EXCHANGE,
+        TODO_COMPLETED_ROUTING_KEY,
+        TodoCompletedMessage::from(&completed),
+    )
+    .await
+    {
+        Err(
+            err @ (PublishError::ConfirmTimeout(_)
+            | PublishError::Nacked
+            | PublishError::Unroutable(_)),
+        ) => Err(unpublished_event("todo completed", &err)),
+        Err(err) => {
+            error!(error = err.to_string(), "error to complete todo");
+            Err(HTTPError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR.into(),
+                message: "error to complete todo".to_owned(),
+                details: "error to complete todo".to_owned(),
+            })
+        }
         _ => Ok(HttpResponse::Ok().finish()),
     }
 }
@@ -0,0 +1,328 @@
+use super::publish_errors::unpublished_event;
+use crate::{
+    extractors::{todo_to_change, RequireScope, TodosRead, TodosWrite},
+    http_cache::{todo_cache_control, todos_cache_control, Validators},
+    openapi::ApiDocV2,
+    viewmodels::{CreateTodoV2Request, TodoPageV2Response, TodoV2Response, TODOS_V2_PATH},
+};
+use actix_web::{
+    delete, get,
+    http::{header::LOCATION, StatusCode},
+    post,
+    web::{Data, Json, Path},
+    HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use http_components::{middlewares::otel::HTTPExtractor, viewmodels::HTTPError};
+use opentelemetry::global;
+use shared::{
+    amqp::{
+        publish_event, EventPublisher, EventsConfigs, PublishError, EXCHANGE,
+        TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY,
+    },
+    models::todo::{CreateTodo, TodoCreatedMessage, TodoDeletedMessage},
+    repositories::TodoRepository,
+};
+use std::sync::Arc;
+use tracing::error;
+use utoipa::OpenApi;
+
+/// Request to create a new ToDo.
+///
+/// If the request was registered correctly this endpoint will return 201 Created with the ToDo location and 4xx/5xx if some error occur.
+///
+#[utoipa::path(
+    post,
+    path = "",
+    context_path = "/v2/todos",
+    tag = "todos",
+    request_body = CreateTodoV2Request,
+    responses(
+        (status = 201, description = "Todo created successfully", body = TodoV2Response),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError),
+        (status = 502, description = "Event returned by the broker as unroutable", body = HTTPError),
+        (status = 503, description = "Event not confirmed in time or nacked by the broker", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:write"]),
+        ("api_key" = ["todos:write"])
+    )
+)]
+#[post("")]
+pub async fn post_v2(
+    req: HttpRequest,
+    auth: RequireScope<TodosWrite>,
+    todo: Json<CreateTodoV2Request>,
+    repo: Data<Arc<dyn TodoRepository>>,
+    publisher: Data<Arc<dyn EventPublisher>>,
+    events: Data<EventsConfigs>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let mut create: CreateTodo = todo.0.into();
+    create.owner_id = Some(auth.subject());
+
+    let created = match repo.create(&ctx, &create).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to create todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to create todo".to_owned(),
+                details: "error to create todo".to_owned(),
+            })
+        }
+        Ok(t) => Ok(t),
+    }?;
+
+    match publish_event(
+        publisher.get_ref().as_ref(),
+        &events,
+        &ctx,
+        EXCHANGE,
+        TODO_CREATED_ROUTING_KEY,
+        TodoCreatedMessage::from(&created),
+    )
+    .await
+    {
+        Err(
+            err @ (PublishError::ConfirmTimeout(_)
+            | PublishError::Nacked
+            | PublishError::Unroutable(_)),
+        ) => Err(unpublished_event("todo created", &err)),
+        Err(err) => {
+            error!(error = err.to_string(), "error to create todo");
+            Err(HTTPError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR.into(),
+                message: "error to create todo".to_owned(),
+                details: "error to create todo".to_owned(),
+            })
+        }
+        _ => Ok(HttpResponse::Created()
+            .insert_header((LOCATION, format!("{}/{}", TODOS_V2_PATH, created.id)))
+            .json(TodoV2Response::from(&created))),
+    }
+}
+
+/// Request to get a page of ToDo's.
+///
+/// If the request was process correctly this endpoint will return 200 Ok and 4xx/5xx if some error occur.
+///
+#[utoipa::path(
+    get,
+    path = "",
+    context_path = "/v2/todos",
+    tag = "todos",
+    responses(
+        (status = 200, description = "Success", body = TodoPageV2Response),
+        (status = 304, description = "Not modified since the ETag or date sent by the client"),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
+    )
+)]
+#[get("")]
+pub async fn list_v2(
+    req: HttpRequest,
+    _: RequireScope<TodosRead>,
+    repo: Data<Arc<dyn TodoRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    match repo.list_paginated(&ctx, 10, 0).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to list todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to list todo".to_owned(),
+                details: "error to list todo".to_owned(),
+            })
+        }
+        Ok(todos) => {
+            let validators = Validators::for_todos(&todos);
+            if validators.is_not_modified(&req) {
+                return Ok(validators.not_modified(todos_cache_control()));
+            }
+
+            let mut builder = HttpResponse::Ok();
+            validators.apply(&mut builder, todos_cache_control());
+
+            Ok(builder.json(TodoPageV2Response::from(todos.as_slice())))
+        }
+    }
+}
+
+/// Request to get a specific ToDo by ID.
+///
+/// If the request was process correctly this endpoint will return 200 Ok, 404 when the ToDo does not exist and 4xx/5xx if some error occur.
+///
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    context_path = "/v2/todos",
+    tag = "todos",
+    responses(
+        (status = 200, description = "Success", body = TodoV2Response),
+        (status = 304, description = "Not modified since the ETag or date sent by the client"),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 404, description = "Not found", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:read"]),
+        ("api_key" = ["todos:read"])
+    )
+)]
+#[get("/{id}")]
+pub async fn get_v2(
+    req: HttpRequest,
+    path: Path<(String,)>,
+    _: RequireScope<TodosRead>,
+    repo: Data<Arc<dyn TodoRepository>>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let (id,) = path.into_inner();
+
+    let todo = match repo.get_by_id(&ctx, &id).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to get todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to get todo".to_owned(),
+                details: "error to get todo".to_owned(),
+            })
+        }
+        Ok(None) => Err(HTTPError {
+            status_code: StatusCode::NOT_FOUND.into(),
+            message: "todo not found".to_owned(),
+            details: format!("todo {} not found", id),
+        }),
+        Ok(Some(t)) => Ok(t),
+    }?;
+
+    let validators = Validators::for_todo(&todo);
+    if validators.is_not_modified(&req) {
+        return Ok(validators.not_modified(todo_cache_control()));
+    }
+
+    let mut builder = HttpResponse::Ok();
+    validators.apply(&mut builder, todo_cache_control());
+
+    Ok(builder.json(TodoV2Response::from(&todo)))
+}
+
+/// Request to delete a specific ToDo by ID.
+///
+/// If the request was process correctly this endpoint will return 204 No Content and 4xx/5xx if some error occur. Only the owner of the ToDo or a `todos:admin` holder may delete it, anyone else gets 403.
+///
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    context_path = "/v2/todos",
+    tag = "todos",
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 400, description = "Bad request", body = HTTPError),
+        (status = 401, description = "Unauthorized", body = HTTPError),
+        (status = 403, description = "Forbidden", body = HTTPError),
+        (status = 500, description = "Internal error", body = HTTPError),
+        (status = 502, description = "Event returned by the broker as unroutable", body = HTTPError),
+        (status = 503, description = "Event not confirmed in time or nacked by the broker", body = HTTPError)
+    ),
+    security(
+        ("auth" = ["todos:write"]),
+        ("api_key" = ["todos:write"])
+    )
+)]
+#[delete("/{id}")]
+pub async fn delete_v2(
+    req: HttpRequest,
+    path: Path<(String,)>,
+    auth: RequireScope<TodosWrite>,
+    repo: Data<Arc<dyn TodoRepository>>,
+    publisher: Data<Arc<dyn EventPublisher>>,
+    events: Data<EventsConfigs>,
+) -> Result<impl Responder, impl ResponseError> {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HTTPExtractor::new(req.headers()))
+    });
+
+    let (id,) = path.into_inner();
+
+    if todo_to_change(
+        &ctx,
+        repo.get_ref().as_ref(),
+        &auth.principal,
+        &id,
+        "delete",
+    )
+    .await?
+    .is_none()
+    {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let deleted = match repo.delete(&ctx, &id).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to delete todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: "error to delete todo".to_owned(),
+                details: "error to delete todo".to_owned(),
+            })
+        }
+        Ok(d) => Ok(d),
+    }?;
+
+    let deleted = match deleted {
+        None => return Ok(HttpResponse::NoContent().finish()),
+        Some(d) => d,
+    };
+
+    match publish_event(
+        publisher.get_ref().as_ref(),
+        &events,
+        &ctx,
+        EXCHANGE,
+        TODO_DELETED_ROUTING_KEY,
+        TodoDeletedMessage::from(&deleted),
+    )
+    .await
+    {
+        Err(
+            err @ (PublishError::ConfirmTimeout(_)
+            | PublishError::Nacked
+            | PublishError::Unroutable(_)),
+        ) => Err(unpublished_event("todo deleted", &err)),
+        Err(err) => {
+            error!(error = err.to_string(), "error to delete todo");
+            Err(HTTPError {
+                status_code: StatusCode::INTERNAL_SERVER_ERROR.into(),
+                message: "error to delete todo".to_owned(),
+                details: "error to delete todo".to_owned(),
+            })
+        }
+        _ => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+/// OpenAPI document of the v2 API, kept apart from the v1 one served by the HTTP server.
+#[get("/openapi.json")]
+pub async fn openapi_v2() -> impl Responder {
+    HttpResponse::Ok().json(ApiDocV2::openapi())
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, TimeZone, Utc};
+use configs::DynamicConfigs;
+use std::{collections::HashMap, env, str::FromStr, time::Duration};
+
+const SSE_REPLAY_BUFFER_SIZE_ENV_KEY: &str = "SSE_REPLAY_BUFFER_SIZE";
+const SSE_HEARTBEAT_INTERVAL_ENV_KEY: &str = "SSE_HEARTBEAT_INTERVAL";
+const GRAPHQL_MAX_DEPTH_ENV_KEY: &str = "GRAPHQL_MAX_DEPTH";
+const GRAPHQL_MAX_COMPLEXITY_ENV_KEY: &str = "GRAPHQL_MAX_COMPLEXITY";
+const RATE_LIMIT_DEFAULT_ENV_KEY: &str = "RATE_LIMIT_DEFAULT";
+const RATE_LIMIT_ROUTES_ENV_KEY: &str = "RATE_LIMIT_ROUTES";
+const TODO_CACHE_ENABLED_ENV_KEY: &str = "TODO_CACHE_ENABLED";
+const TODO_CACHE_CAPACITY_ENV_KEY: &str = "TODO_CACHE_CAPACITY";
+const TODO_CACHE_TTL_ENV_KEY: &str = "TODO_CACHE_TTL";
+const V1_DEPRECATION_DATE_ENV_KEY: &str = "V1_DEPRECATION_DATE";
+const V1_SUNSET_DATE_ENV_KEY: &str = "V1_SUNSET_DATE";
+const HTTP_DURATION_BUCKETS_ENV_KEY: &str = "HTTP_DURATION_BUCKETS";
+const HTTP_SIZE_BUCKETS_ENV_KEY: &str = "HTTP_SIZE_BUCKETS";
+
+/// Token bucket settings: `requests` tokens refilled evenly over `period`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitRule {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl FromStr for RateLimitRule {
+    type Err = String;
+
+    /// Parses rules written as `<requests>/<seconds>`, e.g. `100/60`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, seconds) = s
+            .trim()
+            .split_once('/')
+            .ok_or_else(|| format!("invalid rate limit rule: {}", s))?;
+
+        let requests = requests
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("invalid rate limit requests: {}", s))?;
+        let seconds = seconds
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid rate limit period: {}", s))?;
+
+        if requests == 0 || seconds == 0 {
+            return Err(format!("rate limit rule must be greater than zero: {}", s));
+        }
+
+        Ok(RateLimitRule {
+            requests,
+            period: Duration::from_secs(seconds),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpServerConfigs {
+    /// How many events are kept in memory to serve `Last-Event-ID` resumes.
+    pub sse_replay_buffer_size: usize,
+    /// Interval between SSE heartbeat comments.
+    pub sse_heartbeat_interval: Duration,
+    /// Deepest selection set accepted by the GraphQL endpoint.
+    pub graphql_max_depth: usize,
+    /// Highest query complexity accepted by the GraphQL endpoint.
+    pub graphql_max_complexity: usize,
+    /// Rate limit applied to routes without a specific rule.
+    pub rate_limit_default: RateLimitRule,
+    /// Rate limits keyed by `<METHOD> <route template>`, e.g. `POST /v1/todos`.
+    pub rate_limit_routes: HashMap<String, RateLimitRule>,
+    /// Wraps the todo repository with `CachingTodoRepository`.
+    pub todo_cache_enabled: bool,
+    pub todo_cache_capacity: usize,
+    pub todo_cache_ttl: Duration,
+    /// When /v1 was deprecated in favour of /v2, sent in the `Deprecation` header.
+    pub v1_deprecation_date: DateTime<Utc>,
+    /// When /v1 is going to be removed, sent in the `Sunset` header.
+    pub v1_sunset_date: DateTime<Utc>,
+    /// Histogram boundaries, in milliseconds, of `http.server.duration`.
+    pub http_duration_buckets: Vec<f64>,
+    /// Histogram boundaries, in bytes, of the request and response body sizes.
+    pub http_size_buckets: Vec<f64>,
+}
+
+impl Default for HttpServerConfigs {
+    fn default() -> Self {
+        HttpServerConfigs {
+            sse_replay_buffer_size: 256,
+            sse_heartbeat_interval: Duration::from_secs(15),
+            graphql_max_depth: 8,
+            graphql_max_complexity: 256,
+            rate_limit_default: RateLimitRule {
+                requests: 100,
+                period: Duration::from_secs(60),
+            },
+            rate_limit_routes: HashMap::default(),
+            todo_cache_enabled: false,
+            todo_cache_capacity: 1000,
+            todo_cache_ttl: Duration::from_secs(60),
+            v1_deprecation_date: Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap(),
+            v1_sunset_date: Utc.with_ymd_and_hms(2027, 5, 1, 0, 0, 0).unwrap(),
+            http_duration_buckets: vec![
+                5.0, 10.0, 25.0, 50.0, 75.0, 100.0, 250.0, 500.0, 750.0, 1000.0, 2500.0, 5000.0,
+                7500.0, 10000.0,
+            ],
+            http_size_buckets: vec![
+                128.0, 512.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0,
+            ],
+        }
+    }
+}
+
+impl DynamicConfigs for HttpServerConfigs {
+    fn load(&mut self) {
+        self.sse_replay_buffer_size =
+            env_or(SSE_REPLAY_BUFFER_SIZE_ENV_KEY, self.sse_replay_buffer_size);
+        self.sse_heartbeat_interval = Duration::from_secs(env_or(
+            SSE_HEARTBEAT_INTERVAL_ENV_KEY,
+            self.sse_heartbeat_interval.as_secs(),
+        ));
+        self.graphql_max_depth = env_or(GRAPHQL_MAX_DEPTH_ENV_KEY, self.graphql_max_depth);
+        self.graphql_max_complexity =
+            env_or(GRAPHQL_MAX_COMPLEXITY_ENV_KEY, self.graphql_max_complexity);
+        self.rate_limit_default = env_or(RATE_LIMIT_DEFAULT_ENV_KEY, self.rate_limit_default);
+        self.rate_limit_routes = route_rules(RATE_LIMIT_ROUTES_ENV_KEY);
+        self.todo_cache_enabled = env_or(TODO_CACHE_ENABLED_ENV_KEY, self.todo_cache_enabled);
+        self.todo_cache_capacity = env_or(TODO_CACHE_CAPACITY_ENV_KEY, self.todo_cache_capacity);
+        self.todo_cache_ttl = Duration::from_secs(env_or(
+            TODO_CACHE_TTL_ENV_KEY,
+            self.todo_cache_ttl.as_secs(),
+        ));
+        self.v1_deprecation_date = env_or(V1_DEPRECATION_DATE_ENV_KEY, self.v1_deprecation_date);
+        self.v1_sunset_date = env_or(V1_SUNSET_DATE_ENV_KEY, self.v1_sunset_date);
+        self.http_duration_buckets =
+            buckets(HTTP_DURATION_BUCKETS_ENV_KEY, &self.http_duration_buckets);
+        self.http_size_buckets = buckets(HTTP_SIZE_BUCKETS_ENV_KEY, &self.http_size_buckets);
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+/// Reads entries such as `POST /v1/todos=10/60,GET /v1/todos=100/60`.
+fn route_rules(key: &str) -> HashMap<String, RateLimitRule> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| {
+            let (route, rule) = entry.split_once('=')?;
+            Some((route.trim().to_owned(), rule.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Reads histogram boundaries such as `5,10,25,50`, keeping the defaults when any is invalid.
+fn buckets(key: &str, default: &[f64]) -> Vec<f64> {
+    let parsed = env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|b| !b.trim().is_empty())
+        .map(|b| b.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>();
+
+    match parsed {
+        Ok(b) if !b.is_empty() => b,
+        _ => default.to_vec(),
+    }
+}
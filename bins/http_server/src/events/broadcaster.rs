@@ -0,0 +1,171 @@
+use crate::dynamic_configs::HttpServerConfigs;
+use actix_web::web::Bytes;
+use opentelemetry::{global, metrics::UpDownCounter, Context};
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoEventKind {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+    Completed,
+}
+
+impl Display for TodoEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TodoEventKind::Created => write!(f, "created"),
+            TodoEventKind::Updated => write!(f, "updated"),
+            TodoEventKind::Deleted => write!(f, "deleted"),
+            TodoEventKind::Restored => write!(f, "restored"),
+            TodoEventKind::Completed => write!(f, "completed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TodoEvent {
+    pub id: u64,
+    pub kind: TodoEventKind,
+    pub todo_id: String,
+    pub owner_id: Option<String>,
+    pub data: String,
+}
+
+impl TodoEvent {
+    pub fn belongs_to(&self, owner_id: &str) -> bool {
+        self.owner_id.as_deref() == Some(owner_id)
+    }
+
+    /// Encodes the event as a Server-Sent Events frame.
+    pub fn to_frame(&self) -> Bytes {
+        Bytes::from(format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.id, self.kind, self.data
+        ))
+    }
+}
+
+struct ReplayBuffer {
+    last_id: u64,
+    events: VecDeque<Arc<TodoEvent>>,
+}
+
+/// Fans out todo events received from the broker to every connected SSE client,
+/// keeping the most recent ones around so clients can resume with `Last-Event-ID`.
+pub struct TodoEventsBroadcaster {
+    sender: Sender<Arc<TodoEvent>>,
+    replay: Mutex<ReplayBuffer>,
+    replay_size: usize,
+    pub(crate) heartbeat_interval: Duration,
+    connected_clients: UpDownCounter<i64>,
+}
+
+impl TodoEventsBroadcaster {
+    pub fn new(cfg: &HttpServerConfigs) -> Arc<TodoEventsBroadcaster> {
+        let meter = global::meter("http-server-meter");
+
+        let connected_clients = meter
+            .i64_up_down_counter("http.server.sse.connected_clients")
+            .with_description("SSE Clients Currently Connected")
+            .init();
+
+        let (sender, _) = broadcast::channel(cfg.sse_replay_buffer_size.max(1));
+
+        Arc::new(TodoEventsBroadcaster {
+            sender,
+            replay: Mutex::new(ReplayBuffer {
+                last_id: 0,
+                events: VecDeque::with_capacity(cfg.sse_replay_buffer_size),
+            }),
+            replay_size: cfg.sse_replay_buffer_size,
+            heartbeat_interval: cfg.sse_heartbeat_interval,
+            connected_clients,
+        })
+    }
+
+    pub fn publish(
+        &self,
+        kind: TodoEventKind,
+        todo_id: String,
+        owner_id: Option<String>,
+        data: String,
+    ) {
+        let mut replay = self.replay.lock().unwrap();
+
+        replay.last_id += 1;
+        let event = Arc::new(TodoEvent {
+            id: replay.last_id,
+            kind,
+            todo_id,
+            owner_id,
+            data,
+        });
+
+        if self.replay_size > 0 {
+            if replay.events.len() == self.replay_size {
+                replay.events.pop_front();
+            }
+            replay.events.push_back(event.clone());
+        }
+
+        // an error here only means there is no client connected right now
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns the buffered events the owner missed since `last_event_id` together with a
+    /// receiver for the upcoming ones. Both are taken under the same lock, so no event is
+    /// lost or duplicated between the replay and the live stream.
+    pub fn subscribe(
+        &self,
+        owner_id: &str,
+        last_event_id: Option<u64>,
+    ) -> (Vec<Arc<TodoEvent>>, Receiver<Arc<TodoEvent>>) {
+        let replay = self.replay.lock().unwrap();
+
+        let missed = match last_event_id {
+            None => vec![],
+            Some(last) => replay
+                .events
+                .iter()
+                .filter(|e| e.id > last && e.belongs_to(owner_id))
+                .cloned()
+                .collect(),
+        };
+
+        (missed, self.sender.subscribe())
+    }
+
+    /// Receiver for upcoming events only, regardless of their owner.
+    pub fn receiver(&self) -> Receiver<Arc<TodoEvent>> {
+        self.sender.subscribe()
+    }
+
+    pub fn client_connected(self: &Arc<Self>) -> ConnectedClientGuard {
+        self.connected_clients.add(&Context::current(), 1, &[]);
+
+        ConnectedClientGuard {
+            broadcaster: self.clone(),
+        }
+    }
+}
+
+/// Keeps the connected clients gauge up to date for as long as the SSE stream is alive.
+pub struct ConnectedClientGuard {
+    broadcaster: Arc<TodoEventsBroadcaster>,
+}
+
+impl Drop for ConnectedClientGuard {
+    fn drop(&mut self) {
+        self.broadcaster
+            .connected_clients
+            .add(&Context::current(), -1, &[]);
+    }
+}
@@ -0,0 +1,31 @@
+use super::TodoEventsBroadcaster;
+use infra::repositories::CachingTodoRepository;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// Drops cached ToDo's whenever a todo event arrives, so every replica stops serving a ToDo that
+/// was changed elsewhere. Lagging behind could leave stale entries around, so it clears the
+/// whole cache in that case.
+pub fn invalidate_on_events(
+    broadcaster: &Arc<TodoEventsBroadcaster>,
+    cache: Arc<CachingTodoRepository>,
+) {
+    let mut receiver = broadcaster.receiver();
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => cache.invalidate(&event.todo_id),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        skipped = skipped,
+                        "cache invalidation lagged, clearing cache"
+                    );
+                    cache.clear();
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
@@ -0,0 +1,232 @@
+use super::{TodoEventKind, TodoEventsBroadcaster};
+use crate::{
+    dynamic_configs::HttpServerConfigs,
+    viewmodels::{TodoChangedEventResponse, TodoEventResponse},
+};
+use amqp::channel::new_amqp_channel;
+use configs::Configs;
+use futures_util::StreamExt;
+use lapin::{
+    message::Delivery,
+    options::{BasicConsumeOptions, QueueBindOptions, QueueDeclareOptions},
+    types::FieldTable,
+    Channel, Connection, Consumer,
+};
+use shared::{
+    amqp::{
+        decode_event, EnvelopeError, EXCHANGE, TODO_COMPLETED_ROUTING_KEY,
+        TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY, TODO_EVENTS_BINDING_KEY,
+        TODO_RESTORED_ROUTING_KEY, TODO_UPDATED_ROUTING_KEY,
+    },
+    models::todo::{
+        TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage, TodoRestoredMessage,
+        TodoUpdatedMessage,
+    },
+};
+use std::{error::Error, sync::Arc, time::Duration};
+use tracing::{debug, error, info, warn};
+
+pub const SSE_CONSUMER_TAG: &str = "http-server-sse";
+
+const RECONNECT_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Declares an exclusive, server-named queue bound to every todo event and feeds every
+/// delivery into the broadcaster. Each http-server replica gets its own copy of the events.
+///
+/// The first subscription happens before returning, so a broken broker still fails the startup.
+/// When the broker goes away later the queue is declared again, on a new connection if the
+/// current one is closed, retrying with an exponential backoff.
+pub async fn consume_todo_events(
+    cfg: Configs<HttpServerConfigs>,
+    conn: Arc<Connection>,
+    broadcaster: Arc<TodoEventsBroadcaster>,
+) -> Result<(), Box<dyn Error>> {
+    let (channel, consumer) = subscribe(&conn).await?;
+
+    tokio::spawn(async move {
+        let mut conn = conn;
+        let mut channel = channel;
+        let mut consumer = consumer;
+
+        loop {
+            forward(&mut consumer, &broadcaster).await;
+            warn!("todo events consumer stopped, resubscribing");
+
+            let _ = channel.close(200, "resubscribing").await;
+            (conn, channel, consumer) = resubscribe(&cfg, conn).await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn forward(consumer: &mut Consumer, broadcaster: &TodoEventsBroadcaster) {
+    while let Some(delivery) = consumer.next().await {
+        let delivery = match delivery {
+            Err(err) => {
+                error!(error = err.to_string(), "error to receive todo event");
+                continue;
+            }
+            Ok(d) => d,
+        };
+
+        match todo_event(&delivery) {
+            Err(err) => {
+                error!(error = err.to_string(), "error to parse todo event");
+            }
+            Ok(None) => {
+                debug!(
+                    routing_key = delivery.routing_key.as_str(),
+                    "ignoring unknown todo event"
+                )
+            }
+            Ok(Some((kind, todo_id, owner_id, data))) => {
+                broadcaster.publish(kind, todo_id, owner_id, data)
+            }
+        }
+    }
+}
+
+/// Retries until the queue is declared and consumed again, reconnecting when `conn` is closed.
+async fn resubscribe(
+    cfg: &Configs<HttpServerConfigs>,
+    mut conn: Arc<Connection>,
+) -> (Arc<Connection>, Channel, Consumer) {
+    let mut backoff = RECONNECT_MIN_BACKOFF;
+
+    loop {
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+
+        if !conn.status().connected() {
+            conn = match new_amqp_channel(cfg).await {
+                Err(err) => {
+                    error!(error = err.to_string(), "error to reconnect to the broker");
+                    continue;
+                }
+                Ok((c, _)) => c,
+            };
+        }
+
+        match subscribe(&conn).await {
+            Err(err) => {
+                error!(
+                    error = err.to_string(),
+                    "error to resubscribe to todo events"
+                );
+            }
+            Ok((channel, consumer)) => {
+                info!("todo events consumer resubscribed");
+                return (conn, channel, consumer);
+            }
+        }
+    }
+}
+
+async fn subscribe(conn: &Connection) -> Result<(Channel, Consumer), lapin::Error> {
+    let channel = conn.create_channel().await?;
+    let queue = declare_exclusive_queue(&channel).await?;
+
+    let consumer = channel
+        .basic_consume(
+            queue.as_str(),
+            SSE_CONSUMER_TAG,
+            BasicConsumeOptions {
+                no_ack: true,
+                exclusive: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    Ok((channel, consumer))
+}
+
+type TodoEventFields = (TodoEventKind, String, Option<String>, String);
+
+/// Reads the kind, ToDo, owner and SSE payload of a todo event, or `None` when the routing key is
+/// not a todo event the SSE stream knows about.
+fn todo_event(delivery: &Delivery) -> Result<Option<TodoEventFields>, EnvelopeError> {
+    let event = decode_event(&delivery.data, &delivery.properties)?;
+
+    let fields = match delivery.routing_key.as_str() {
+        TODO_CREATED_ROUTING_KEY => {
+            let msg = event.data_as::<TodoCreatedMessage>()?;
+            (
+                TodoEventKind::Created,
+                msg.id.clone(),
+                msg.owner_id.clone(),
+                TodoEventResponse::from(&msg).to_json(),
+            )
+        }
+        TODO_UPDATED_ROUTING_KEY => {
+            let msg = event.data_as::<TodoUpdatedMessage>()?;
+            (
+                TodoEventKind::Updated,
+                msg.id.clone(),
+                msg.owner_id.clone(),
+                TodoChangedEventResponse::from(&msg).to_json(),
+            )
+        }
+        TODO_DELETED_ROUTING_KEY => {
+            let msg = event.data_as::<TodoDeletedMessage>()?;
+            (
+                TodoEventKind::Deleted,
+                msg.id.clone(),
+                msg.owner_id.clone(),
+                TodoChangedEventResponse::from(&msg).to_json(),
+            )
+        }
+        TODO_RESTORED_ROUTING_KEY => {
+            let msg = event.data_as::<TodoRestoredMessage>()?;
+            (
+                TodoEventKind::Restored,
+                msg.id.clone(),
+                msg.owner_id.clone(),
+                TodoChangedEventResponse::from(&msg).to_json(),
+            )
+        }
+        TODO_COMPLETED_ROUTING_KEY => {
+            let msg = event.data_as::<TodoCompletedMessage>()?;
+            (
+                TodoEventKind::Completed,
+                msg.id.clone(),
+                msg.owner_id.clone(),
+                TodoChangedEventResponse::from(&msg).to_json(),
+            )
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(fields))
+}
+
+async fn declare_exclusive_queue(channel: &Channel) -> Result<String, lapin::Error> {
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                auto_delete: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await?;
+
+    let name = queue.name().to_string();
+
+    channel
+        .queue_bind(
+            &name,
+            EXCHANGE,
+            TODO_EVENTS_BINDING_KEY,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await?;
+
+    Ok(name)
+}
@@ -0,0 +1,7 @@
+mod broadcaster;
+mod cache;
+mod consumer;
+
+pub use broadcaster::{TodoEvent, TodoEventKind, TodoEventsBroadcaster};
+pub use cache::invalidate_on_events;
+pub use consumer::consume_todo_events;
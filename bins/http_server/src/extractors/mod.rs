@@ -0,0 +1,8 @@
+mod ownership;
+mod scopes;
+
+pub use ownership::todo_to_change;
+pub use scopes::{
+    authenticate, RequireScope, ScopeRequirement, TodosAdmin, TodosRead, TodosWrite, API_KEY_HEADER,
+};
+pub use shared::auth::{AuthMethod, Principal};
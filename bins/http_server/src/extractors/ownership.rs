@@ -0,0 +1,36 @@
+use actix_web::http::StatusCode;
+use http_components::viewmodels::HTTPError;
+use opentelemetry::Context;
+use shared::{auth::Principal, models::todo::Todo, repositories::TodoRepository};
+use tracing::error;
+
+/// Loads the todo `id` the principal wants to `action`, e.g. `delete`, answering 403 unless the
+/// principal owns it or holds `todos:admin`. `None` when there is no such todo.
+pub async fn todo_to_change(
+    ctx: &Context,
+    repo: &dyn TodoRepository,
+    principal: &Principal,
+    id: &str,
+    action: &str,
+) -> Result<Option<Todo>, HTTPError> {
+    let todo = match repo.get_by_id(ctx, id).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to get todo");
+            Err(HTTPError {
+                status_code: StatusCode::BAD_REQUEST.into(),
+                message: format!("error to {} todo", action),
+                details: format!("error to {} todo", action),
+            })
+        }
+        Ok(t) => Ok(t),
+    }?;
+
+    match todo {
+        Some(t) if !principal.can_change(&t) => Err(HTTPError {
+            status_code: StatusCode::FORBIDDEN.into(),
+            message: "forbidden".to_owned(),
+            details: format!("only the owner of the todo or an admin may {} it", action),
+        }),
+        todo => Ok(todo),
+    }
+}
@@ -0,0 +1,164 @@
+use actix_web::{
+    dev::Payload,
+    http::{header::AUTHORIZATION, StatusCode},
+    web::Data,
+    Error, FromRequest, HttpMessage, HttpRequest,
+};
+use futures_util::future::{ready, LocalBoxFuture};
+use http_components::{
+    extractors::JwtAuthenticateExtractor, middlewares::otel::HTTPExtractor, viewmodels::HTTPError,
+};
+use opentelemetry::{global, Context};
+use shared::{
+    auth::{
+        AuthMethod, Principal, TokenClaims, TODOS_ADMIN_SCOPE, TODOS_READ_SCOPE, TODOS_WRITE_SCOPE,
+    },
+    repositories::ApiKeyRepository,
+};
+use std::{marker::PhantomData, sync::Arc};
+use tracing::error;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+pub trait ScopeRequirement {
+    const SCOPE: &'static str;
+}
+
+pub struct TodosRead;
+
+impl ScopeRequirement for TodosRead {
+    const SCOPE: &'static str = TODOS_READ_SCOPE;
+}
+
+pub struct TodosWrite;
+
+impl ScopeRequirement for TodosWrite {
+    const SCOPE: &'static str = TODOS_WRITE_SCOPE;
+}
+
+/// Grants every other todo scope.
+pub struct TodosAdmin;
+
+impl ScopeRequirement for TodosAdmin {
+    const SCOPE: &'static str = TODOS_ADMIN_SCOPE;
+}
+
+/// Authenticates the request, either with a bearer JWT through `JwtAuthenticateExtractor` or with
+/// an `X-Api-Key`, and then requires the caller to hold `S::SCOPE` (or `todos:admin`), answering
+/// 403 with the missing scope otherwise.
+pub struct RequireScope<S: ScopeRequirement> {
+    pub principal: Principal,
+    _scope: PhantomData<S>,
+}
+
+impl<S: ScopeRequirement> RequireScope<S> {
+    pub fn subject(&self) -> String {
+        self.principal.subject.clone()
+    }
+
+    fn authorize(principal: Principal) -> Result<Self, Error> {
+        if !principal.grants(S::SCOPE) {
+            return Err(HTTPError {
+                status_code: StatusCode::FORBIDDEN.into(),
+                message: "forbidden".to_owned(),
+                details: format!("missing scope: {}", S::SCOPE),
+            }
+            .into());
+        }
+
+        Ok(RequireScope {
+            principal,
+            _scope: PhantomData,
+        })
+    }
+}
+
+fn unauthorized(details: &str) -> Error {
+    HTTPError {
+        status_code: StatusCode::UNAUTHORIZED.into(),
+        message: "unauthorized".to_owned(),
+        details: details.to_owned(),
+    }
+    .into()
+}
+
+async fn authenticate_api_key(
+    ctx: Context,
+    repo: Option<Data<Arc<dyn ApiKeyRepository>>>,
+    key: String,
+) -> Result<Principal, Error> {
+    let repo = match repo {
+        None => {
+            error!("api key repository not registered");
+            Err(unauthorized("api keys are not supported"))
+        }
+        Some(r) => Ok(r.get_ref().clone()),
+    }?;
+
+    shared::auth::authenticate_api_key(&ctx, repo.as_ref(), &key)
+        .await
+        .ok_or_else(|| unauthorized("invalid api key"))
+}
+
+/// Validates the credentials of the request, an `X-Api-Key` or a bearer JWT, and keeps the
+/// principal in the request extensions, so middlewares and extractors running on the same request
+/// only validate them once.
+pub fn authenticate(req: &HttpRequest) -> LocalBoxFuture<'static, Result<Principal, Error>> {
+    if let Some(principal) = req.extensions().get::<Principal>() {
+        return Box::pin(ready(Ok(principal.clone())));
+    }
+
+    let req = req.clone();
+    let api_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+
+    let principal: LocalBoxFuture<'static, Result<Principal, Error>> = match api_key {
+        Some(key) => {
+            let ctx = global::get_text_map_propagator(|propagator| {
+                propagator.extract(&HTTPExtractor::new(req.headers()))
+            });
+            let repo = req.app_data::<Data<Arc<dyn ApiKeyRepository>>>().cloned();
+
+            Box::pin(authenticate_api_key(ctx, repo, key))
+        }
+        None => {
+            let authenticate = JwtAuthenticateExtractor::from_request(&req, &mut Payload::None);
+            let claims = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .and_then(TokenClaims::from_token)
+                .unwrap_or_default();
+
+            Box::pin(async move {
+                let auth = authenticate.await.map_err(Into::into)?;
+                Ok(Principal {
+                    subject: auth.session.sub,
+                    scopes: claims.scopes(),
+                    method: AuthMethod::Jwt,
+                })
+            })
+        }
+    };
+
+    Box::pin(async move {
+        let principal = principal.await?;
+        req.extensions_mut().insert(principal.clone());
+        Ok(principal)
+    })
+}
+
+impl<S: ScopeRequirement + 'static> FromRequest for RequireScope<S> {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let principal = authenticate(req);
+
+        Box::pin(async move { Self::authorize(principal.await?) })
+    }
+}
@@ -0,0 +1,68 @@
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextResolve, ResolveInfo},
+    ServerResult, Value,
+};
+use async_trait::async_trait;
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    trace::{FutureExt, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Opens one span per root resolver (queries, mutations and subscriptions), as a child of the
+/// context attached to the request future, or of the `Context` in the connection data for
+/// WebSocket sessions. Resolvers read it back with `Context::current()`.
+pub struct ResolverTracing;
+
+impl ExtensionFactory for ResolverTracing {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ResolverTracingExtension {
+            tracer: global::tracer("graphql-resolvers"),
+        })
+    }
+}
+
+struct ResolverTracingExtension {
+    tracer: BoxedTracer,
+}
+
+#[async_trait]
+impl Extension for ResolverTracingExtension {
+    async fn resolve(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        info: ResolveInfo<'_>,
+        next: NextResolve<'_>,
+    ) -> ServerResult<Option<Value>> {
+        if info.is_for_introspection || info.path_node.parent.is_some() {
+            return next.run(ctx, info).await;
+        }
+
+        let parent = ctx
+            .data_opt::<Context>()
+            .cloned()
+            .unwrap_or_else(Context::current);
+        let mut span = self.tracer.start_with_context(
+            format!("graphql {}.{}", info.parent_type, info.name),
+            &parent,
+        );
+        span.set_attributes(vec![
+            KeyValue::new("graphql.parent_type", info.parent_type.to_owned()),
+            KeyValue::new("graphql.field", info.name.to_owned()),
+            KeyValue::new("graphql.return_type", info.return_type.to_owned()),
+        ]);
+
+        let otel_ctx = parent.with_span(span);
+        let result = next.run(ctx, info).with_context(otel_ctx.clone()).await;
+
+        if let Err(err) = &result {
+            let span = otel_ctx.span();
+            span.set_status(Status::Error {
+                description: Cow::from(err.message.clone()),
+            });
+        }
+
+        result
+    }
+}
@@ -0,0 +1,5 @@
+mod extensions;
+mod schema;
+mod types;
+
+pub use schema::{schema, TodoSchema};
@@ -0,0 +1,251 @@
+use super::{
+    extensions::ResolverTracing,
+    types::{CreateTodoInput, TodoObject, TodoPage},
+};
+use crate::{
+    dynamic_configs::HttpServerConfigs,
+    events::{TodoEventKind, TodoEventsBroadcaster},
+    extractors::{ScopeRequirement, TodosRead, TodosWrite},
+    viewmodels::TodoEventResponse,
+};
+use async_graphql::{Context, Error, Object, Result, Schema, Subscription, ID};
+use futures_util::Stream;
+use shared::{
+    amqp::{
+        publish_event, EventPublisher, EventsConfigs, PublishError, EXCHANGE,
+        TODO_CREATED_ROUTING_KEY, TODO_DELETED_ROUTING_KEY,
+    },
+    auth::Principal,
+    models::todo::{TodoCreatedMessage, TodoDeletedMessage},
+    repositories::TodoRepository,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
+
+pub type TodoSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+const MAX_PAGE_SIZE: i32 = 100;
+
+pub fn schema(
+    cfg: &HttpServerConfigs,
+    repo: Arc<dyn TodoRepository>,
+    publisher: Arc<dyn EventPublisher>,
+    events: EventsConfigs,
+    broadcaster: Arc<TodoEventsBroadcaster>,
+) -> TodoSchema {
+    Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+        .data(repo)
+        .data(publisher)
+        .data(events)
+        .data(broadcaster)
+        .extension(ResolverTracing)
+        .limit_depth(cfg.graphql_max_depth)
+        .limit_complexity(cfg.graphql_max_complexity)
+        .finish()
+}
+
+/// Caller of the request, authenticated by the route before the request reaches the schema.
+fn principal<'ctx>(ctx: &Context<'ctx>) -> Result<&'ctx Principal> {
+    ctx.data::<Principal>()
+        .map_err(|_| Error::new("unauthorized"))
+}
+
+fn require_scope<'ctx, S: ScopeRequirement>(ctx: &Context<'ctx>) -> Result<&'ctx Principal> {
+    let principal = principal(ctx)?;
+
+    if !principal.grants(S::SCOPE) {
+        return Err(Error::new(format!("missing scope: {}", S::SCOPE)));
+    }
+
+    Ok(principal)
+}
+
+/// Error of a mutation whose `change`, e.g. `todo created`, was committed but whose event the
+/// broker did not take.
+fn unpublished_event(change: &str, err: &PublishError) -> Error {
+    Error::new(err.unpublished(change).message)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// A single ToDo by ID.
+    async fn todo(&self, ctx: &Context<'_>, id: ID) -> Result<Option<TodoObject>> {
+        require_scope::<TodosRead>(ctx)?;
+        let repo = ctx.data_unchecked::<Arc<dyn TodoRepository>>();
+
+        match repo
+            .get_by_id(&opentelemetry::Context::current(), &id)
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get todo");
+                Err(Error::new("error to get todo"))
+            }
+            Ok(todo) => Ok(todo.as_ref().map(TodoObject::from)),
+        }
+    }
+
+    /// A page of ToDo's.
+    #[graphql(complexity = "limit.clamp(1, MAX_PAGE_SIZE) as usize * child_complexity")]
+    async fn todos(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 10)] limit: i32,
+        #[graphql(default = 0)] offset: i32,
+    ) -> Result<TodoPage> {
+        require_scope::<TodosRead>(ctx)?;
+        let repo = ctx.data_unchecked::<Arc<dyn TodoRepository>>();
+
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let offset = offset.max(0);
+
+        match repo
+            .list_paginated(&opentelemetry::Context::current(), limit, offset)
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to list todo");
+                Err(Error::new("error to list todo"))
+            }
+            Ok(todos) => Ok(TodoPage {
+                items: todos.iter().map(TodoObject::from).collect(),
+                limit,
+                offset,
+            }),
+        }
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Creates a ToDo and publishes its creation event.
+    async fn create_todo(&self, ctx: &Context<'_>, input: CreateTodoInput) -> Result<TodoObject> {
+        let principal = require_scope::<TodosWrite>(ctx)?;
+        let repo = ctx.data_unchecked::<Arc<dyn TodoRepository>>();
+        let publisher = ctx.data_unchecked::<Arc<dyn EventPublisher>>();
+        let events = ctx.data_unchecked::<EventsConfigs>();
+        let otel_ctx = opentelemetry::Context::current();
+
+        let created = match repo
+            .create(&otel_ctx, &input.into_create_todo(&principal.subject))
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to create todo");
+                Err(Error::new("error to create todo"))
+            }
+            Ok(t) => Ok(t),
+        }?;
+
+        match publish_event(
+            publisher.as_ref(),
+            events,
+            &otel_ctx,
+            EXCHANGE,
+            TODO_CREATED_ROUTING_KEY,
+            TodoCreatedMessage::from(&created),
+        )
+        .await
+        {
+            Err(
+                err @ (PublishError::ConfirmTimeout(_)
+                | PublishError::Nacked
+                | PublishError::Unroutable(_)),
+            ) => Err(unpublished_event("todo created", &err)),
+            Err(err) => {
+                error!(error = err.to_string(), "error to create todo");
+                Err(Error::new("error to create todo"))
+            }
+            _ => Ok(TodoObject::from(&created)),
+        }
+    }
+
+    /// Deletes a ToDo by ID and publishes its deletion event. Only its owner or an admin may.
+    async fn delete_todo(&self, ctx: &Context<'_>, id: ID) -> Result<bool> {
+        let principal = require_scope::<TodosWrite>(ctx)?;
+        let repo = ctx.data_unchecked::<Arc<dyn TodoRepository>>();
+        let publisher = ctx.data_unchecked::<Arc<dyn EventPublisher>>();
+        let events = ctx.data_unchecked::<EventsConfigs>();
+        let otel_ctx = opentelemetry::Context::current();
+
+        let existing = match repo.get_by_id(&otel_ctx, &id).await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get todo");
+                Err(Error::new("error to delete todo"))
+            }
+            Ok(t) => Ok(t),
+        }?;
+
+        match existing {
+            None => return Ok(true),
+            Some(todo) if !principal.can_change(&todo) => return Err(Error::new("forbidden")),
+            _ => {}
+        }
+
+        let deleted = match repo.delete(&otel_ctx, &id).await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to delete todo");
+                Err(Error::new("error to delete todo"))
+            }
+            Ok(d) => Ok(d),
+        }?;
+
+        let deleted = match deleted {
+            None => return Ok(true),
+            Some(d) => d,
+        };
+
+        match publish_event(
+            publisher.as_ref(),
+            events,
+            &otel_ctx,
+            EXCHANGE,
+            TODO_DELETED_ROUTING_KEY,
+            TodoDeletedMessage::from(&deleted),
+        )
+        .await
+        {
+            Err(
+                err @ (PublishError::ConfirmTimeout(_)
+                | PublishError::Nacked
+                | PublishError::Unroutable(_)),
+            ) => Err(unpublished_event("todo deleted", &err)),
+            Err(err) => {
+                error!(error = err.to_string(), "error to delete todo");
+                Err(Error::new("error to delete todo"))
+            }
+            _ => Ok(true),
+        }
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// ToDo's created by the authenticated owner from now on.
+    async fn todo_created(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = TodoObject>> {
+        let owner_id = require_scope::<TodosRead>(ctx)?.subject.clone();
+        let broadcaster = ctx.data_unchecked::<Arc<TodoEventsBroadcaster>>();
+        let mut receiver = broadcaster.receiver();
+
+        Ok(async_stream::stream! {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.kind == TodoEventKind::Created && event.belongs_to(&owner_id) => {
+                        if let Ok(todo) = serde_json::from_str::<TodoEventResponse>(&event.data) {
+                            yield TodoObject::from(todo);
+                        }
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
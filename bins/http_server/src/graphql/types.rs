@@ -0,0 +1,60 @@
+use crate::viewmodels::TodoEventResponse;
+use async_graphql::{InputObject, SimpleObject};
+use shared::models::todo::{CreateTodo, Todo};
+
+#[derive(SimpleObject)]
+#[graphql(name = "Todo")]
+pub struct TodoObject {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+impl From<&Todo> for TodoObject {
+    fn from(value: &Todo) -> Self {
+        TodoObject {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            created_at: value.created_at.clone(),
+            updated_at: Some(value.updated_at.clone()),
+        }
+    }
+}
+
+impl From<TodoEventResponse> for TodoObject {
+    fn from(value: TodoEventResponse) -> Self {
+        TodoObject {
+            id: value.id,
+            name: value.name,
+            description: value.description,
+            created_at: value.created_at,
+            updated_at: None,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TodoPage {
+    pub items: Vec<TodoObject>,
+    pub limit: i32,
+    pub offset: i32,
+}
+
+#[derive(InputObject)]
+pub struct CreateTodoInput {
+    pub name: String,
+    pub description: String,
+}
+
+impl CreateTodoInput {
+    pub fn into_create_todo(self, owner_id: &str) -> CreateTodo {
+        CreateTodo {
+            name: self.name,
+            description: self.description,
+            owner_id: Some(owner_id.to_owned()),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use actix_web::{
+    http::header::{
+        CacheControl, CacheDirective, ETag, EntityTag, Header, HttpDate, IfModifiedSince,
+        IfNoneMatch, LastModified, IF_NONE_MATCH,
+    },
+    HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use chrono::DateTime;
+use sha2::{Digest, Sha256};
+use shared::models::todo::Todo;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// `Cache-Control` directives for a single ToDo: clients may keep it but must revalidate.
+pub fn todo_cache_control() -> CacheControl {
+    CacheControl(vec![
+        CacheDirective::Private,
+        CacheDirective::MaxAge(0),
+        CacheDirective::MustRevalidate,
+    ])
+}
+
+/// `Cache-Control` directives for ToDo pages, which change whenever any ToDo changes.
+pub fn todos_cache_control() -> CacheControl {
+    CacheControl(vec![CacheDirective::Private, CacheDirective::NoCache])
+}
+
+/// Validators computed from `id`/`updated_at`, so the conditional check can run before the
+/// response body is built.
+pub struct Validators {
+    etag: EntityTag,
+    last_modified: Option<HttpDate>,
+}
+
+impl Validators {
+    pub fn for_todo(todo: &Todo) -> Validators {
+        Self::for_todos(std::slice::from_ref(todo))
+    }
+
+    pub fn for_todos(todos: &[Todo]) -> Validators {
+        let mut hasher = Sha256::new();
+        for todo in todos {
+            hasher.update(todo.id.as_bytes());
+            hasher.update(b"|");
+            hasher.update(todo.updated_at.as_bytes());
+            hasher.update(b";");
+        }
+        let digest = hasher.finalize();
+
+        Validators {
+            etag: EntityTag::new_strong(hex::encode(&digest[..16])),
+            last_modified: todos.iter().filter_map(|t| http_date(&t.updated_at)).max(),
+        }
+    }
+
+    /// Evaluates `If-None-Match` and, only when it is absent, `If-Modified-Since` (RFC 7232).
+    pub fn is_not_modified(&self, req: &HttpRequest) -> bool {
+        // an absent header parses as an empty list, which must not shadow If-Modified-Since
+        if req.headers().contains_key(IF_NONE_MATCH) {
+            return match IfNoneMatch::parse(req) {
+                Ok(IfNoneMatch::Any) => true,
+                Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&self.etag)),
+                Err(_) => false,
+            };
+        }
+
+        match (IfModifiedSince::parse(req), self.last_modified) {
+            (Ok(IfModifiedSince(since)), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    }
+
+    pub fn not_modified(&self, cache_control: CacheControl) -> HttpResponse {
+        let mut builder = HttpResponse::NotModified();
+        self.apply(&mut builder, cache_control);
+        builder.finish()
+    }
+
+    pub fn apply(&self, builder: &mut HttpResponseBuilder, cache_control: CacheControl) {
+        builder.insert_header(ETag(self.etag.clone()));
+        builder.insert_header(cache_control);
+
+        if let Some(modified) = self.last_modified {
+            builder.insert_header(LastModified(modified));
+        }
+    }
+}
+
+/// HTTP dates only carry seconds, so the timestamp is truncated to make comparisons with
+/// `If-Modified-Since` meaningful.
+fn http_date(rfc3339: &str) -> Option<HttpDate> {
+    let secs = DateTime::parse_from_rfc3339(rfc3339).ok()?.timestamp();
+    let time: SystemTime = UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?);
+
+    Some(HttpDate::from(time))
+}
@@ -1,30 +1,52 @@
 mod controllers;
+mod dynamic_configs;
+mod events;
+mod extractors;
+mod graphql;
+mod http_cache;
+mod middlewares;
 mod openapi;
 mod routes;
+#[cfg(test)]
+mod tests;
 mod viewmodels;
 
 use actix_web::web::{Data, ServiceConfig};
 use amqp::{
     channel::new_amqp_channel,
-    publisher::{AmqpPublisher, Publisher},
+    exchange::ExchangeDefinition,
+    topology::{AmqpTopology, Topology},
 };
 use auth::jwt_manager::auth0::Auth0JwtManager;
-use configs::{Configs, Empty};
+use configs::{Configs, PostgresConfigs};
 use configs_builder::ConfigBuilder;
 use deadpool_postgres::Pool;
+use dynamic_configs::HttpServerConfigs;
+use events::TodoEventsBroadcaster;
+use graphql::TodoSchema;
 use health_readiness::HealthReadinessServiceImpl;
 use http_components::CustomServiceConfigure;
 use httpw::server::HTTPServer;
-use infra::repositories::TodoRepositoryImpl;
-use lapin::Channel;
+use infra::{
+    health::HealthMeter,
+    messaging::{ConfirmingPublisher, PublisherConfigs},
+    repositories::{
+        ApiKeyRepositoryImpl, CachingTodoRepository, SlowQueryConfigs, TodoRepositoryImpl,
+        TodoStatsRepositoryImpl,
+    },
+    telemetry::{self, TelemetryExporter},
+};
+use middlewares::{Deprecation, HttpMetrics, RateLimiter};
 use openapi::ApiDoc;
-use opentelemetry::{global, Context};
 use routes as todos_routes;
-use shared::repositories::TodoRepository;
+use shared::{
+    amqp::{EventPublisher, EventsConfigs, EXCHANGE},
+    repositories::{ApiKeyRepository, TodoRepository, TodoStatsRepository},
+};
 use sql_pool::postgres::conn_pool;
 use std::{error::Error, sync::Arc};
-use tracing::error;
 use utoipa::OpenApi;
+use viewmodels::TODOS_V2_PATH;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -33,7 +55,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let (connection, channel) = new_amqp_channel(&cfg).await?;
     let db_conn = Arc::new(conn_pool(&cfg.postgres)?);
 
+    AmqpTopology::new(channel.clone())
+        .exchange(&ExchangeDefinition::new(EXCHANGE).topic().durable())
+        .install()
+        .await?;
+
+    let publisher =
+        ConfirmingPublisher::new(channel.clone(), &PublisherConfigs::from_env()).await?;
+
+    let broadcaster = TodoEventsBroadcaster::new(&cfg.dynamic);
+    events::consume_todo_events(cfg.clone(), connection.clone(), broadcaster.clone()).await?;
+
     let auth0 = Auth0JwtManager::new(&cfg.auth0);
+    let rate_limiter = RateLimiter::new(&cfg.dynamic);
+    let http_metrics = HttpMetrics::new();
+    let v1_deprecation = Deprecation::new(
+        "v1",
+        TODOS_V2_PATH,
+        &cfg.dynamic.v1_deprecation_date,
+        &cfg.dynamic.v1_sunset_date,
+    );
 
     let health_checker = HealthReadinessServiceImpl::default()
         .amqp(connection.clone())
@@ -41,62 +82,110 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let doc = ApiDoc::openapi();
     let server = HTTPServer::new(&cfg.app)
-        .custom_configure(container(channel.clone(), db_conn.clone()))
-        .custom_configure(todos_routes::routes())
+        .custom_configure(container(
+            cfg.dynamic.clone(),
+            cfg.postgres.clone(),
+            SlowQueryConfigs::from_env(),
+            publisher,
+            EventsConfigs::from_env()?,
+            db_conn.clone(),
+            broadcaster.clone(),
+        ))
+        .custom_configure(todos_routes::routes(
+            rate_limiter.clone(),
+            v1_deprecation.clone(),
+            http_metrics.clone(),
+        ))
+        .custom_configure(routes::todos_v2_routes(
+            rate_limiter.clone(),
+            http_metrics.clone(),
+        ))
+        .custom_configure(routes::graphql_routes(
+            rate_limiter.clone(),
+            http_metrics.clone(),
+        ))
+        .custom_configure(routes::api_keys_routes(
+            rate_limiter.clone(),
+            http_metrics.clone(),
+        ))
         .jwt_manager(auth0)
         .health_check(Arc::new(health_checker))
         .openapi(&doc);
 
-    declare_health_meter()?;
+    HealthMeter::new("http-server-meter", "http.server")
+        .rabbitmq(connection.clone())
+        .postgres(db_conn.clone())
+        .install()?;
 
     server.start().await?;
 
     Ok(())
 }
 
-async fn default_setup<'cfg>() -> Result<Configs<Empty>, Box<dyn Error>> {
+async fn default_setup<'cfg>() -> Result<Configs<HttpServerConfigs>, Box<dyn Error>> {
     let cfg = ConfigBuilder::new()
         .use_aws_secret_manager()
         .otlp()
         .auth0()
         .amqp()
         .postgres()
-        .build::<Empty>()
+        .build::<HttpServerConfigs>()
         .await?;
 
-    traces::otlp::setup(&cfg)?;
-    metrics::otlp::setup(&cfg)?;
+    let exporter = TelemetryExporter::from_env()?;
+    if exporter == TelemetryExporter::Otlp {
+        traces::otlp::setup(&cfg)?;
+    }
+    telemetry::setup(
+        exporter,
+        &cfg.app.name,
+        HttpMetrics::aggregator_selector(&cfg.dynamic),
+    )?;
 
     Ok(cfg)
 }
 
-fn container(channel: Arc<Channel>, db_pool: Arc<Pool>) -> CustomServiceConfigure {
-    CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
-        let publisher = AmqpPublisher::new(channel.clone());
-        let repository = TodoRepositoryImpl::new(db_pool.clone());
+fn container(
+    dynamic: HttpServerConfigs,
+    postgres: PostgresConfigs,
+    slow_query: SlowQueryConfigs,
+    publisher: Arc<dyn EventPublisher>,
+    events: EventsConfigs,
+    db_pool: Arc<Pool>,
+    broadcaster: Arc<TodoEventsBroadcaster>,
+) -> CustomServiceConfigure {
+    let repository: Arc<dyn TodoRepository> = match dynamic.todo_cache_enabled {
+        false => TodoRepositoryImpl::new(db_pool.clone(), &postgres, &slow_query),
+        true => {
+            let cache = CachingTodoRepository::new(
+                TodoRepositoryImpl::new(db_pool.clone(), &postgres, &slow_query),
+                dynamic.todo_cache_capacity,
+                dynamic.todo_cache_ttl,
+            );
+            events::invalidate_on_events(&broadcaster, cache.clone());
+            cache
+        }
+    };
 
-        cfg.app_data(Data::<Arc<dyn Publisher>>::new(publisher));
+    CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
+        let publisher = publisher.clone();
+        let repository = repository.clone();
+        let api_keys = ApiKeyRepositoryImpl::new(db_pool.clone(), &postgres, &slow_query);
+        let todo_stats = TodoStatsRepositoryImpl::new(db_pool.clone(), &postgres, &slow_query);
+        let schema = graphql::schema(
+            &dynamic,
+            repository.clone(),
+            publisher.clone(),
+            events.clone(),
+            broadcaster.clone(),
+        );
+
+        cfg.app_data(Data::<Arc<dyn EventPublisher>>::new(publisher));
+        cfg.app_data(Data::<EventsConfigs>::new(events.clone()));
         cfg.app_data(Data::<Arc<dyn TodoRepository>>::new(repository));
+        cfg.app_data(Data::<Arc<dyn ApiKeyRepository>>::new(api_keys));
+        cfg.app_data(Data::<Arc<dyn TodoStatsRepository>>::new(todo_stats));
+        cfg.app_data(Data::<Arc<TodoEventsBroadcaster>>::new(broadcaster.clone()));
+        cfg.app_data(Data::<TodoSchema>::new(schema));
     })
 }
-
-fn declare_health_meter() -> Result<(), Box<dyn Error>> {
-    let meter = global::meter("http-server-meter");
-    let health_counter = meter
-        .u64_observable_counter("http.server.health")
-        .with_description("HTTP Server Health Counter")
-        .init();
-
-    let callback = move |ctx: &Context| {
-        health_counter.observe(ctx, 10, &[]);
-    };
-    match meter.register_callback(callback) {
-        Err(err) => {
-            error!(error = err.to_string(), "error to register health counter");
-            Err(err)
-        }
-        _ => Ok(()),
-    }?;
-
-    Ok(())
-}
@@ -0,0 +1,24 @@
+use crate::extractors::authenticate;
+use actix_web::dev::ServiceRequest;
+use futures_util::future::LocalBoxFuture;
+
+/// Identifies the caller by its validated principal, falling back to the peer IP for anonymous
+/// requests and for credentials that do not validate.
+///
+/// Subjects and keys are only trusted once validated, otherwise a client could get a fresh
+/// identity per request by forging the `sub` claim or sending random keys. The peer address is
+/// used instead of `X-Forwarded-For` for the same reason.
+pub(crate) fn client_identity(req: &ServiceRequest) -> LocalBoxFuture<'static, String> {
+    let ip = format!(
+        "ip:{}",
+        req.connection_info().peer_addr().unwrap_or("unknown")
+    );
+    let principal = authenticate(req.request());
+
+    Box::pin(async move {
+        match principal.await {
+            Ok(p) => format!("{}:{}", p.method.as_str(), p.subject),
+            Err(_) => ip,
+        }
+    })
+}
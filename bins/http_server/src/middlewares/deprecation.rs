@@ -0,0 +1,155 @@
+use crate::extractors::Principal;
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderName, HeaderValue, HttpDate, LINK},
+    Error, HttpMessage,
+};
+use chrono::{DateTime, Utc};
+use futures_util::future::LocalBoxFuture;
+use http_components::middlewares::otel::HTTPExtractor;
+use opentelemetry::{global, metrics::Counter, KeyValue};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+    time::SystemTime,
+};
+
+const DEPRECATION: &str = "deprecation";
+const SUNSET: &str = "sunset";
+const UNMATCHED_ROUTE: &str = "unmatched";
+const ANONYMOUS: &str = "anonymous";
+
+struct DeprecationState {
+    deprecation: HeaderValue,
+    sunset: HeaderValue,
+    link: HeaderValue,
+    usage: Counter<u64>,
+}
+
+/// Flags every response of a deprecated API version with the `Deprecation` (RFC 9745) and
+/// `Sunset` (RFC 8594) headers, pointing to its successor through a `Link` header, and counts
+/// how callers still reach it, per route and auth method.
+#[derive(Clone)]
+pub struct Deprecation {
+    state: Arc<DeprecationState>,
+}
+
+impl Deprecation {
+    pub fn new(
+        version: &str,
+        successor: &str,
+        deprecated_at: &DateTime<Utc>,
+        sunset_at: &DateTime<Utc>,
+    ) -> Deprecation {
+        let meter = global::meter("http-server-meter");
+
+        let usage = meter
+            .u64_counter(format!("http.server.{}.requests", version))
+            .with_description("Requests Received by a Deprecated API Version")
+            .init();
+
+        Deprecation {
+            state: Arc::new(DeprecationState {
+                deprecation: deprecation_value(deprecated_at),
+                sunset: sunset_value(sunset_at),
+                link: HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+                    .unwrap_or_else(|_| HeaderValue::from_static("")),
+                usage,
+            }),
+        }
+    }
+}
+
+/// `Deprecation` carries the date as a structured field: `@` followed by the unix timestamp.
+fn deprecation_value(date: &DateTime<Utc>) -> HeaderValue {
+    HeaderValue::from_str(&format!("@{}", date.timestamp()))
+        .unwrap_or_else(|_| HeaderValue::from_static("true"))
+}
+
+fn sunset_value(date: &DateTime<Utc>) -> HeaderValue {
+    let date = HttpDate::from(SystemTime::from(*date)).to_string();
+    HeaderValue::from_str(&date).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Deprecation
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = DeprecationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(DeprecationMiddleware {
+            service: Rc::new(service),
+            state: self.state.clone(),
+        }))
+    }
+}
+
+pub struct DeprecationMiddleware<S> {
+    service: Rc<S>,
+    state: Arc<DeprecationState>,
+}
+
+impl<S, B> Service<ServiceRequest> for DeprecationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let ctx = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HTTPExtractor::new(req.headers()))
+        });
+        let method = req.method().to_string();
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+
+        let state = self.state.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+
+            // the principal is only there once an extractor validated the credentials, so the
+            // label is bounded to the auth methods and can not be forged
+            let auth = res
+                .request()
+                .extensions()
+                .get::<Principal>()
+                .map(|p| p.method.as_str())
+                .unwrap_or(ANONYMOUS);
+
+            state.usage.add(
+                &ctx,
+                1,
+                &[
+                    KeyValue::new("http.method", method),
+                    KeyValue::new("http.route", route),
+                    KeyValue::new("auth", auth),
+                ],
+            );
+
+            let headers = res.headers_mut();
+            headers.insert(
+                HeaderName::from_static(DEPRECATION),
+                state.deprecation.clone(),
+            );
+            headers.insert(HeaderName::from_static(SUNSET), state.sunset.clone());
+            headers.append(LINK, state.link.clone());
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,234 @@
+use crate::dynamic_configs::HttpServerConfigs;
+use actix_web::{
+    body::{BodySize, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header::CONTENT_LENGTH, Version},
+    Error,
+};
+use futures_util::future::LocalBoxFuture;
+use http_components::middlewares::otel::HTTPExtractor;
+use infra::telemetry::DEFAULT_DURATION_BUCKETS;
+use opentelemetry::{
+    global,
+    metrics::{Histogram, Unit, UpDownCounter},
+    sdk::{
+        export::metrics::AggregatorSelector,
+        metrics::{
+            aggregators::{self, Aggregator},
+            sdk_api::{Descriptor, InstrumentKind},
+        },
+    },
+    KeyValue,
+};
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
+
+const DURATION_METRIC: &str = "http.server.duration";
+const ACTIVE_REQUESTS_METRIC: &str = "http.server.active_requests";
+const REQUEST_SIZE_METRIC: &str = "http.server.request.size";
+const RESPONSE_SIZE_METRIC: &str = "http.server.response.size";
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+struct HttpMetricsState {
+    duration: Histogram<f64>,
+    active_requests: UpDownCounter<i64>,
+    request_size: Histogram<u64>,
+    response_size: Histogram<u64>,
+}
+
+/// Records the RED metrics (rate, errors and duration) of every request it wraps.
+///
+/// Attributes follow the OpenTelemetry HTTP semantic conventions and use the matched route
+/// template (`/v1/todos/{id}`) instead of the raw path, so cardinality stays bounded.
+#[derive(Clone)]
+pub struct HttpMetrics {
+    state: Arc<HttpMetricsState>,
+}
+
+impl HttpMetrics {
+    pub fn new() -> HttpMetrics {
+        let meter = global::meter("http-server-meter");
+
+        let duration = meter
+            .f64_histogram(DURATION_METRIC)
+            .with_description("HTTP Server Request Duration")
+            .with_unit(Unit::new("ms"))
+            .init();
+
+        let active_requests = meter
+            .i64_up_down_counter(ACTIVE_REQUESTS_METRIC)
+            .with_description("HTTP Server Requests in Flight")
+            .init();
+
+        let request_size = meter
+            .u64_histogram(REQUEST_SIZE_METRIC)
+            .with_description("HTTP Server Request Body Size")
+            .with_unit(Unit::new("By"))
+            .init();
+
+        let response_size = meter
+            .u64_histogram(RESPONSE_SIZE_METRIC)
+            .with_description("HTTP Server Response Body Size")
+            .with_unit(Unit::new("By"))
+            .init();
+
+        HttpMetrics {
+            state: Arc::new(HttpMetricsState {
+                duration,
+                active_requests,
+                request_size,
+                response_size,
+            }),
+        }
+    }
+
+    /// Aggregator selector for the stdout and file exporters. `HTTP_DURATION_BUCKETS` and
+    /// `HTTP_SIZE_BUCKETS` only apply to the HTTP histograms, any other histogram recorded by the
+    /// binary keeps `DEFAULT_DURATION_BUCKETS`. The OTLP pipeline uses its own aggregation.
+    pub fn aggregator_selector(cfg: &HttpServerConfigs) -> HttpMetricsSelector {
+        HttpMetricsSelector {
+            duration_buckets: cfg.http_duration_buckets.clone(),
+            size_buckets: cfg.http_size_buckets.clone(),
+        }
+    }
+}
+
+impl Default for HttpMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpMetricsSelector {
+    duration_buckets: Vec<f64>,
+    size_buckets: Vec<f64>,
+}
+
+impl AggregatorSelector for HttpMetricsSelector {
+    fn aggregator_for(&self, descriptor: &Descriptor) -> Option<Arc<dyn Aggregator + Send + Sync>> {
+        match (descriptor.instrument_kind(), descriptor.name()) {
+            (InstrumentKind::GaugeObserver, _) => Some(Arc::new(aggregators::last_value())),
+            (InstrumentKind::Histogram, REQUEST_SIZE_METRIC | RESPONSE_SIZE_METRIC) => {
+                Some(Arc::new(aggregators::histogram(&self.size_buckets)))
+            }
+            (InstrumentKind::Histogram, DURATION_METRIC) => {
+                Some(Arc::new(aggregators::histogram(&self.duration_buckets)))
+            }
+            (InstrumentKind::Histogram, _) => {
+                Some(Arc::new(aggregators::histogram(&DEFAULT_DURATION_BUCKETS)))
+            }
+            _ => Some(Arc::new(aggregators::sum())),
+        }
+    }
+}
+
+fn flavor(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "0.9",
+        Version::HTTP_10 => "1.0",
+        Version::HTTP_2 => "2.0",
+        Version::HTTP_3 => "3.0",
+        _ => "1.1",
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = HttpMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpMetricsMiddleware {
+            service: Rc::new(service),
+            state: self.state.clone(),
+        }))
+    }
+}
+
+pub struct HttpMetricsMiddleware<S> {
+    service: Rc<S>,
+    state: Arc<HttpMetricsState>,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+
+        let ctx = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HTTPExtractor::new(req.headers()))
+        });
+
+        let mut attributes = vec![
+            KeyValue::new("http.method", req.method().to_string()),
+            KeyValue::new("http.scheme", req.connection_info().scheme().to_owned()),
+            KeyValue::new("http.flavor", flavor(req.version())),
+            KeyValue::new(
+                "http.route",
+                req.match_pattern()
+                    .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned()),
+            ),
+        ];
+
+        let request_size = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or_default();
+
+        // active requests are only keyed by what is known before routing finishes
+        let active_attributes = attributes[..2].to_vec();
+        self.state.active_requests.add(&ctx, 1, &active_attributes);
+
+        let state = self.state.clone();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+
+            let (status, response_size) = match &result {
+                Ok(res) => (
+                    res.status().as_u16(),
+                    match res.response().body().size() {
+                        BodySize::Sized(size) => size,
+                        _ => 0,
+                    },
+                ),
+                Err(err) => (err.as_response_error().status_code().as_u16(), 0),
+            };
+            attributes.push(KeyValue::new("http.status_code", status as i64));
+
+            state.active_requests.add(&ctx, -1, &active_attributes);
+            state.duration.record(
+                &ctx,
+                started_at.elapsed().as_secs_f64() * 1000.0,
+                &attributes,
+            );
+            state.request_size.record(&ctx, request_size, &attributes);
+            state.response_size.record(&ctx, response_size, &attributes);
+
+            result
+        })
+    }
+}
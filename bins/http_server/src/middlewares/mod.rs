@@ -0,0 +1,9 @@
+mod client;
+mod deprecation;
+mod metrics;
+mod rate_limit;
+
+pub(crate) use client::client_identity;
+pub use deprecation::Deprecation;
+pub use metrics::HttpMetrics;
+pub use rate_limit::RateLimiter;
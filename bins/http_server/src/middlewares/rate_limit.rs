@@ -0,0 +1,263 @@
+use super::client_identity;
+use crate::dynamic_configs::{HttpServerConfigs, RateLimitRule};
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+        StatusCode,
+    },
+    Error, HttpResponse,
+};
+use futures_util::future::LocalBoxFuture;
+use http_components::{middlewares::otel::HTTPExtractor, viewmodels::HTTPError};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    metrics::Counter,
+    trace::{Span, SpanKind, Status, Tracer},
+    KeyValue,
+};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    future::{ready, Ready},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const RATE_LIMIT_LIMIT: &str = "ratelimit-limit";
+const RATE_LIMIT_REMAINING: &str = "ratelimit-remaining";
+const RATE_LIMIT_RESET: &str = "ratelimit-reset";
+
+/// Buckets untouched for this long are dropped when the map is pruned.
+const STALE_BUCKET_AGE: Duration = Duration::from_secs(600);
+const PRUNE_THRESHOLD: usize = 10_000;
+/// Pruning walks every bucket under the lock, so past `PRUNE_THRESHOLD` it runs at most this
+/// often rather than on every request.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Buckets {
+    entries: HashMap<String, Bucket>,
+    pruned_at: Instant,
+}
+
+impl Buckets {
+    fn prune(&mut self, now: Instant) {
+        if self.entries.len() <= PRUNE_THRESHOLD
+            || now.duration_since(self.pruned_at) < PRUNE_INTERVAL
+        {
+            return;
+        }
+
+        self.entries
+            .retain(|_, b| now.duration_since(b.updated_at) < STALE_BUCKET_AGE);
+        self.pruned_at = now;
+    }
+}
+
+struct Decision {
+    allowed: bool,
+    rule: RateLimitRule,
+    remaining: u32,
+    /// Seconds until the next token becomes available.
+    retry_after: u64,
+    /// Seconds until the bucket is full again.
+    reset: u64,
+}
+
+struct RateLimiterState {
+    default_rule: RateLimitRule,
+    routes: HashMap<String, RateLimitRule>,
+    buckets: Mutex<Buckets>,
+    tracer: BoxedTracer,
+    rejections: Counter<u64>,
+}
+
+/// Token bucket rate limiter keyed by the authenticated principal, falling back to the client IP.
+///
+/// Limits are looked up by `<METHOD> <route template>` and default to `RATE_LIMIT_DEFAULT`.
+#[derive(Clone)]
+pub struct RateLimiter {
+    state: Arc<RateLimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &HttpServerConfigs) -> RateLimiter {
+        let meter = global::meter("http-server-meter");
+
+        let rejections = meter
+            .u64_counter("http.server.rate_limit.rejections")
+            .with_description("Requests Rejected by the Rate Limiter")
+            .init();
+
+        RateLimiter {
+            state: Arc::new(RateLimiterState {
+                default_rule: cfg.rate_limit_default,
+                routes: cfg.rate_limit_routes.clone(),
+                buckets: Mutex::new(Buckets {
+                    entries: HashMap::default(),
+                    pruned_at: Instant::now(),
+                }),
+                tracer: global::tracer("http-server-rate-limiter"),
+                rejections,
+            }),
+        }
+    }
+}
+
+impl RateLimiterState {
+    fn check(&self, route: &str, client: &str) -> Decision {
+        let rule = self.routes.get(route).copied().unwrap_or(self.default_rule);
+
+        let capacity = rule.requests as f64;
+        let refill_per_sec = capacity / rule.period.as_secs_f64();
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.prune(now);
+
+        let bucket = buckets
+            .entries
+            .entry(format!("{}|{}", route, client))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated_at: now,
+            });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Decision {
+            allowed,
+            rule,
+            remaining: bucket.tokens.floor() as u32,
+            retry_after: ((1.0 - bucket.tokens).max(0.0) / refill_per_sec).ceil() as u64,
+            reset: ((capacity - bucket.tokens) / refill_per_sec).ceil() as u64,
+        }
+    }
+}
+
+impl Decision {
+    fn write_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            HeaderName::from_static(RATE_LIMIT_LIMIT),
+            HeaderValue::from(self.rule.requests),
+        );
+        headers.insert(
+            HeaderName::from_static(RATE_LIMIT_REMAINING),
+            HeaderValue::from(self.remaining),
+        );
+        headers.insert(
+            HeaderName::from_static(RATE_LIMIT_RESET),
+            HeaderValue::from(self.reset),
+        );
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service: Rc::new(service),
+            state: self.state.clone(),
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+    state: Arc<RateLimiterState>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let state = self.state.clone();
+        let client = client_identity(&req);
+
+        Box::pin(async move {
+            let method = req.method().to_string();
+            let template = req
+                .match_pattern()
+                .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+            let decision = state.check(&format!("{} {}", method, template), &client.await);
+
+            if decision.allowed {
+                let mut res = service.call(req).await?;
+                decision.write_headers(res.headers_mut());
+                return Ok(res.map_into_left_body());
+            }
+
+            let ctx = global::get_text_map_propagator(|propagator| {
+                propagator.extract(&HTTPExtractor::new(req.headers()))
+            });
+
+            let mut span = state
+                .tracer
+                .span_builder("rate_limited")
+                .with_kind(SpanKind::Server)
+                .with_attributes(vec![
+                    KeyValue::new("http.method", method.clone()),
+                    KeyValue::new("http.route", template.clone()),
+                    KeyValue::new("http.status_code", 429_i64),
+                ])
+                .start_with_context(&state.tracer, &ctx);
+            span.set_status(Status::Error {
+                description: Cow::from("rate limit exceeded"),
+            });
+            span.end();
+
+            state.rejections.add(
+                &ctx,
+                1,
+                &[
+                    KeyValue::new("http.method", method),
+                    KeyValue::new("http.route", template),
+                ],
+            );
+
+            let mut res = HttpResponse::TooManyRequests().json(HTTPError {
+                status_code: StatusCode::TOO_MANY_REQUESTS.into(),
+                message: "too many requests".to_owned(),
+                details: format!("retry after {} seconds", decision.retry_after),
+            });
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(decision.retry_after));
+            decision.write_headers(res.headers_mut());
+
+            Ok(req.into_response(res).map_into_right_body())
+        })
+    }
+}
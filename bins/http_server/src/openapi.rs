@@ -1,9 +1,9 @@
-use crate::{controllers as tc, viewmodels as tvm};
+use crate::{controllers as tc, extractors::API_KEY_HEADER, viewmodels as tvm};
 use http_components::viewmodels::HTTPError;
 use utoipa::{
     openapi::{
         self,
-        security::{Http, HttpAuthScheme, SecurityScheme},
+        security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme},
     },
     Modify, OpenApi,
 };
@@ -11,22 +11,26 @@ use utoipa::{
 #[derive(OpenApi)]
 #[openapi(
   paths(
-    tc::post, tc::get, tc::list, tc::delete,
+    tc::post, tc::get, tc::list, tc::delete, tc::complete, tc::events, tc::stats,
+    tc::create_api_key, tc::list_api_keys, tc::revoke_api_key,
   ),
   components(
     schemas(
       HTTPError,
-      tvm::CreateTodoRequest, tvm::TodoResponse,
+      tvm::CreateTodoRequest, tvm::TodoResponse, tvm::TodoEventResponse, tvm::TodoChangedEventResponse,
+      tvm::TodoStatsResponse,
+      tvm::CreateApiKeyRequest, tvm::CreatedApiKeyResponse, tvm::ApiKeyResponse,
     )
   ),
   tags(
-    (name = "todos", description = "ToDo's management endpoints.")
+    (name = "todos", description = "ToDo's management endpoints."),
+    (name = "api-keys", description = "API keys management endpoints for machine clients.")
   ),
   modifiers(&SecurityAddon),
   info(
     title = "HTTP API",
     version = "v0.0.1",
-    description = "HTTP API's built in rust"
+    description = "HTTP API's built in rust. The /v1 endpoints are deprecated in favour of /v2"
   ),
 )]
 #[cfg_attr(debug_assertions, openapi(
@@ -41,6 +45,40 @@ use utoipa::{
 ))]
 pub struct ApiDoc;
 
+#[derive(OpenApi)]
+#[openapi(
+  paths(
+    tc::post_v2, tc::get_v2, tc::list_v2, tc::delete_v2,
+  ),
+  components(
+    schemas(
+      HTTPError,
+      tvm::CreateTodoV2Request, tvm::TodoV2Response, tvm::TodoPageV2Response,
+      tvm::TodoLinksResponse, tvm::TodoPageLinksResponse, tvm::LinkResponse,
+    )
+  ),
+  tags(
+    (name = "todos", description = "ToDo's management endpoints."),
+  ),
+  modifiers(&SecurityAddon),
+  info(
+    title = "HTTP API",
+    version = "v2.0.0",
+    description = "HTTP API's built in rust, version 2"
+  ),
+)]
+#[cfg_attr(debug_assertions, openapi(
+  servers(
+    (url = "http://localhost:4444", description = "Local server"),
+  ),
+))]
+#[cfg_attr(not(debug_assertions), openapi(
+  servers(
+    (url = "https://stg.something.com.br", description = "Staging server"),
+  ),
+))]
+pub struct ApiDocV2;
+
 pub struct SecurityAddon;
 
 impl Modify for SecurityAddon {
@@ -49,6 +87,10 @@ impl Modify for SecurityAddon {
         components.add_security_scheme(
             "auth",
             SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
         )
     }
 }
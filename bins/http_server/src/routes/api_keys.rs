@@ -0,0 +1,19 @@
+use crate::{
+    controllers,
+    middlewares::{HttpMetrics, RateLimiter},
+};
+use actix_web::web::{self, ServiceConfig};
+use http_components::CustomServiceConfigure;
+
+pub fn routes(rate_limiter: RateLimiter, http_metrics: HttpMetrics) -> CustomServiceConfigure {
+    CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
+        cfg.service(
+            web::scope("/v1/api-keys")
+                .wrap(rate_limiter.clone())
+                .wrap(http_metrics.clone())
+                .service(controllers::create_api_key)
+                .service(controllers::list_api_keys)
+                .service(controllers::revoke_api_key),
+        );
+    })
+}
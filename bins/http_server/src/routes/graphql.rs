@@ -0,0 +1,25 @@
+use crate::{
+    controllers,
+    middlewares::{HttpMetrics, RateLimiter},
+};
+use actix_web::{
+    guard,
+    web::{self, ServiceConfig},
+};
+use http_components::CustomServiceConfigure;
+
+pub fn routes(rate_limiter: RateLimiter, http_metrics: HttpMetrics) -> CustomServiceConfigure {
+    CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
+        cfg.service(
+            web::resource("/graphql")
+                .wrap(rate_limiter.clone())
+                .wrap(http_metrics.clone())
+                .route(web::post().to(controllers::graphql))
+                .route(
+                    web::get()
+                        .guard(guard::Header("upgrade", "websocket"))
+                        .to(controllers::graphql_ws),
+                ),
+        );
+    })
+}
@@ -1,3 +1,9 @@
+mod api_keys;
+mod graphql;
 mod todos;
+mod todos_v2;
 
+pub use api_keys::routes as api_keys_routes;
+pub use graphql::routes as graphql_routes;
 pub use todos::routes;
+pub use todos_v2::routes as todos_v2_routes;
@@ -1,15 +1,28 @@
-use crate::controllers;
+use crate::{
+    controllers,
+    middlewares::{Deprecation, HttpMetrics, RateLimiter},
+};
 use actix_web::web::{self, ServiceConfig};
 use http_components::CustomServiceConfigure;
 
-pub fn routes() -> CustomServiceConfigure {
+pub fn routes(
+    rate_limiter: RateLimiter,
+    deprecation: Deprecation,
+    http_metrics: HttpMetrics,
+) -> CustomServiceConfigure {
     CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
         cfg.service(
             web::scope("/v1/todos")
+                .wrap(rate_limiter.clone())
+                .wrap(deprecation.clone())
+                .wrap(http_metrics.clone())
                 .service(controllers::post)
                 .service(controllers::list)
+                .service(controllers::events)
+                .service(controllers::stats)
                 .service(controllers::get)
-                .service(controllers::delete),
+                .service(controllers::delete)
+                .service(controllers::complete),
         );
     })
 }
@@ -0,0 +1,21 @@
+use crate::{
+    controllers,
+    middlewares::{HttpMetrics, RateLimiter},
+};
+use actix_web::web::{self, ServiceConfig};
+use http_components::CustomServiceConfigure;
+
+pub fn routes(rate_limiter: RateLimiter, http_metrics: HttpMetrics) -> CustomServiceConfigure {
+    CustomServiceConfigure::new(move |cfg: &mut ServiceConfig| {
+        cfg.service(
+            web::scope("/v2/todos")
+                .wrap(rate_limiter.clone())
+                .wrap(http_metrics.clone())
+                .service(controllers::post_v2)
+                .service(controllers::list_v2)
+                .service(controllers::get_v2)
+                .service(controllers::delete_v2),
+        );
+        cfg.service(web::scope("/v2/docs").service(controllers::openapi_v2));
+    })
+}
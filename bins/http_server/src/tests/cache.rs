@@ -0,0 +1,53 @@
+use super::fakes::FakeTodoRepository;
+use crate::{
+    dynamic_configs::HttpServerConfigs,
+    events::{self, TodoEventKind, TodoEventsBroadcaster},
+};
+use infra::repositories::CachingTodoRepository;
+use opentelemetry::Context;
+use shared::{models::todo::CreateTodo, repositories::TodoRepository};
+use std::time::Duration;
+
+#[actix_web::test]
+async fn deleted_event_from_another_replica_invalidates_the_cached_todo() {
+    let ctx = Context::new();
+    let cfg = HttpServerConfigs::default();
+    let broadcaster = TodoEventsBroadcaster::new(&cfg);
+
+    let database = FakeTodoRepository::new();
+    let cache = CachingTodoRepository::new(database.clone(), 10, Duration::from_secs(60));
+    events::invalidate_on_events(&broadcaster, cache.clone());
+
+    let todo = database
+        .create(
+            &ctx,
+            &CreateTodo {
+                name: "name".to_owned(),
+                description: "description".to_owned(),
+                owner_id: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert!(cache.get_by_id(&ctx, &todo.id).await.unwrap().is_some());
+
+    // another replica deletes the todo and its event reaches this one
+    database.delete(&ctx, &todo.id).await.unwrap();
+    broadcaster.publish(
+        TodoEventKind::Deleted,
+        todo.id.clone(),
+        None,
+        "{}".to_owned(),
+    );
+
+    let mut invalidated = false;
+    for _ in 0..50 {
+        if cache.get_by_id(&ctx, &todo.id).await.unwrap().is_none() {
+            invalidated = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    assert!(invalidated, "deleted todo is still served from the cache");
+}
@@ -0,0 +1,376 @@
+//! In-process stand-ins for Postgres and RabbitMQ. They emit the same kind of spans as the real
+//! clients and carry the trace context the same way, through message headers.
+use amqp::{
+    errors::AmqpError,
+    publisher::{Payload, Publisher},
+};
+use async_trait::async_trait;
+use consumers::{DispatchError, EventDispatcher};
+use lapin::{
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties,
+};
+use opentelemetry::{
+    global,
+    trace::{SpanKind, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use shared::{
+    amqp::{decode_event, EventPublisher, PublishError},
+    models::{
+        api_key::{ApiKey, CreateApiKey},
+        todo::{CreateTodo, Todo},
+        todo_stats::TodoStats,
+    },
+    repositories::{ApiKeyRepository, TodoRepository, TodoStatsRepository},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+pub const QUEUE: &str = "simple-queue";
+/// Database name the fake repositories report, like `POSTGRES_DB` does for the real ones.
+pub const DATABASE: &str = "todos-test";
+
+#[derive(Default)]
+pub struct FakeTodoRepository {
+    todos: Mutex<Vec<Todo>>,
+}
+
+impl FakeTodoRepository {
+    pub fn new() -> Arc<FakeTodoRepository> {
+        Arc::new(FakeTodoRepository::default())
+    }
+
+    pub fn with_todos(todos: Vec<Todo>) -> Arc<FakeTodoRepository> {
+        Arc::new(FakeTodoRepository {
+            todos: Mutex::new(todos),
+        })
+    }
+
+    /// Mirrors the Client span `PostgresClient` starts for every statement, named
+    /// `<operation> <db>.<table>`.
+    fn span(&self, ctx: &Context, operation: &'static str) -> Context {
+        let tracer = global::tracer("fake-todo-repository");
+        let span = tracer
+            .span_builder(format!("{} {}.todos", operation, DATABASE))
+            .with_kind(SpanKind::Client)
+            .with_attributes(vec![
+                KeyValue::new("db.system", "postgresql"),
+                KeyValue::new("db.name", DATABASE),
+                KeyValue::new("db.operation", operation),
+                KeyValue::new("db.sql.table", "todos"),
+            ])
+            .start_with_context(&tracer, ctx);
+
+        ctx.with_span(span)
+    }
+}
+
+#[async_trait]
+impl TodoRepository for FakeTodoRepository {
+    async fn create(&self, ctx: &Context, todo: &CreateTodo) -> Result<Todo, String> {
+        let ctx = self.span(ctx, "INSERT");
+
+        let mut todos = self.todos.lock().map_err(|err| err.to_string())?;
+        let created = Todo {
+            id: format!("todo-{}", todos.len() + 1),
+            name: todo.name.clone(),
+            description: todo.description.clone(),
+            owner_id: todo.owner_id.clone(),
+            ..Default::default()
+        };
+        todos.push(created.clone());
+
+        ctx.span().end();
+        Ok(created)
+    }
+
+    async fn get_by_id(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let ctx = self.span(ctx, "SELECT");
+
+        let todos = self.todos.lock().map_err(|err| err.to_string())?;
+        let todo = todos.iter().find(|t| t.id == id).cloned();
+
+        ctx.span().end();
+        Ok(todo)
+    }
+
+    async fn list_paginated(
+        &self,
+        ctx: &Context,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<Todo>, String> {
+        let ctx = self.span(ctx, "SELECT");
+
+        let todos = self.todos.lock().map_err(|err| err.to_string())?;
+        let page = todos
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+
+        ctx.span().end();
+        Ok(page)
+    }
+
+    async fn delete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let ctx = self.span(ctx, "UPDATE");
+
+        let mut todos = self.todos.lock().map_err(|err| err.to_string())?;
+        let deleted = todos
+            .iter()
+            .position(|t| t.id == id)
+            .map(|i| todos.remove(i));
+
+        ctx.span().end();
+        Ok(deleted)
+    }
+
+    async fn complete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let ctx = self.span(ctx, "UPDATE");
+
+        let mut todos = self.todos.lock().map_err(|err| err.to_string())?;
+        let completed = todos
+            .iter_mut()
+            .find(|t| t.id == id && t.completed_at.is_none())
+            .map(|t| {
+                t.completed_at = Some(t.updated_at.clone());
+                t.clone()
+            });
+
+        ctx.span().end();
+        Ok(completed)
+    }
+}
+
+/// Accepts any key as one of `fake-owner`, granting it `scopes`, unless built with `rejecting`.
+pub struct FakeApiKeyRepository {
+    owner_id: String,
+    scopes: Vec<String>,
+    accepts: bool,
+}
+
+impl FakeApiKeyRepository {
+    pub fn new(scopes: &[&str]) -> Arc<FakeApiKeyRepository> {
+        FakeApiKeyRepository::owned_by("fake-owner", scopes)
+    }
+
+    /// Accepts any key as one of `owner_id`.
+    pub fn owned_by(owner_id: &str, scopes: &[&str]) -> Arc<FakeApiKeyRepository> {
+        Arc::new(FakeApiKeyRepository {
+            owner_id: owner_id.to_owned(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            accepts: true,
+        })
+    }
+
+    /// Knows no key at all.
+    pub fn rejecting() -> Arc<FakeApiKeyRepository> {
+        Arc::new(FakeApiKeyRepository {
+            owner_id: "fake-owner".to_owned(),
+            scopes: vec![],
+            accepts: false,
+        })
+    }
+
+    fn key(&self) -> ApiKey {
+        ApiKey {
+            id: "fake-key".to_owned(),
+            owner_id: self.owner_id.clone(),
+            scopes: self.scopes.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for FakeApiKeyRepository {
+    async fn create(&self, _ctx: &Context, _key: &CreateApiKey) -> Result<ApiKey, String> {
+        Ok(self.key())
+    }
+
+    async fn list(&self, _ctx: &Context) -> Result<Vec<ApiKey>, String> {
+        Ok(vec![self.key()])
+    }
+
+    async fn get_active_by_hash(
+        &self,
+        _ctx: &Context,
+        _hash: &str,
+    ) -> Result<Option<ApiKey>, String> {
+        Ok(Some(self.key()).filter(|_| self.accepts))
+    }
+
+    async fn touch_last_used(&self, _ctx: &Context, _id: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn revoke(&self, _ctx: &Context, _id: &str) -> Result<bool, String> {
+        Ok(self.accepts)
+    }
+}
+
+/// Answers every query with its stats, remembering the days asked for.
+#[derive(Default)]
+pub struct FakeTodoStatsRepository {
+    stats: Vec<TodoStats>,
+    ranges: Mutex<Vec<(String, String)>>,
+}
+
+impl FakeTodoStatsRepository {
+    pub fn with_stats(stats: Vec<TodoStats>) -> Arc<FakeTodoStatsRepository> {
+        Arc::new(FakeTodoStatsRepository {
+            stats,
+            ..Default::default()
+        })
+    }
+
+    /// `from` and `to` of every query.
+    pub fn ranges(&self) -> Vec<(String, String)> {
+        self.ranges.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl TodoStatsRepository for FakeTodoStatsRepository {
+    async fn list_by_owner(
+        &self,
+        _ctx: &Context,
+        _owner_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<TodoStats>, String> {
+        let mut ranges = self.ranges.lock().map_err(|err| err.to_string())?;
+        ranges.push((from.to_owned(), to.to_owned()));
+
+        Ok(self.stats.clone())
+    }
+}
+
+struct Message {
+    properties: BasicProperties,
+    body: Vec<u8>,
+}
+
+/// Queues published messages until `deliver` hands them to a consumer, like a single queue bound
+/// to every exchange.
+#[derive(Default)]
+pub struct FakeBroker {
+    messages: Mutex<Vec<Message>>,
+}
+
+impl FakeBroker {
+    pub fn new() -> Arc<FakeBroker> {
+        Arc::new(FakeBroker::default())
+    }
+
+    /// Delivers every queued message to `QUEUE` through the dispatcher, returning how many were
+    /// delivered.
+    pub async fn deliver(&self, dispatcher: &EventDispatcher) -> Result<usize, DispatchError> {
+        let messages: Vec<Message> = match self.messages.lock() {
+            Ok(mut messages) => messages.drain(..).collect(),
+            Err(_) => vec![],
+        };
+        let delivered = messages.len();
+
+        for msg in messages {
+            dispatcher
+                .dispatch(QUEUE, &msg.body, &msg.properties)
+                .await?;
+        }
+
+        Ok(delivered)
+    }
+
+    /// CloudEvents types of the queued messages, in publishing order.
+    pub fn event_types(&self) -> Vec<String> {
+        match self.messages.lock() {
+            Ok(messages) => messages
+                .iter()
+                .filter_map(|msg| decode_event(&msg.body, &msg.properties).ok())
+                .map(|event| event.ty)
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+#[async_trait]
+impl Publisher for FakeBroker {
+    async fn publish(
+        &self,
+        ctx: &Context,
+        exchange: &str,
+        _key: &str,
+        msg: &Payload,
+        params: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), AmqpError> {
+        let tracer = global::tracer("fake-broker");
+        let span = tracer
+            .span_builder(format!("{} publish", exchange))
+            .with_kind(SpanKind::Producer)
+            .with_attributes(vec![
+                KeyValue::new("messaging.system", "rabbitmq"),
+                KeyValue::new("messaging.destination.name", exchange.to_owned()),
+            ])
+            .start_with_context(&tracer, ctx);
+        let ctx = ctx.with_span(span);
+
+        let mut trace_headers = HashMap::new();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&ctx, &mut trace_headers)
+        });
+
+        let mut headers = FieldTable::default();
+        for (key, value) in params.unwrap_or_default() {
+            headers.insert(ShortString::from(key), value);
+        }
+        for (key, value) in trace_headers {
+            headers.insert(
+                ShortString::from(key),
+                AMQPValue::LongString(LongString::from(value)),
+            );
+        }
+
+        if let Ok(mut messages) = self.messages.lock() {
+            messages.push(Message {
+                properties: BasicProperties::default().with_headers(headers),
+                body: msg.payload.to_vec(),
+            });
+        }
+
+        ctx.span().end();
+        Ok(())
+    }
+}
+
+/// A publisher the broker never takes a message from, failing every publish with the error made
+/// by `error`.
+pub struct FailingPublisher {
+    error: fn() -> PublishError,
+}
+
+impl FailingPublisher {
+    pub fn new(error: fn() -> PublishError) -> Arc<FailingPublisher> {
+        Arc::new(FailingPublisher { error })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for FailingPublisher {
+    async fn publish(
+        &self,
+        _ctx: &Context,
+        _exchange: &str,
+        _routing_key: &str,
+        _payload: &Payload,
+        _content_type: &str,
+        _headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError> {
+        Err((self.error)())
+    }
+}
@@ -0,0 +1,106 @@
+use super::fakes::{FakeBroker, FakeTodoRepository};
+use crate::{
+    dynamic_configs::HttpServerConfigs,
+    events::TodoEventsBroadcaster,
+    graphql::{self, TodoSchema},
+};
+use async_graphql::Request;
+use opentelemetry::Context;
+use shared::{
+    amqp::EventsConfigs,
+    auth::{AuthMethod, Principal},
+    models::todo::CreateTodo,
+    repositories::TodoRepository,
+};
+use std::sync::Arc;
+
+async fn schema_with_todo(owner_id: &str) -> (TodoSchema, Arc<FakeTodoRepository>, String) {
+    let cfg = HttpServerConfigs::default();
+    let repo = FakeTodoRepository::new();
+    let todo = repo
+        .create(
+            &Context::new(),
+            &CreateTodo {
+                name: "name".to_owned(),
+                description: "description".to_owned(),
+                owner_id: Some(owner_id.to_owned()),
+            },
+        )
+        .await
+        .unwrap();
+
+    let schema = graphql::schema(
+        &cfg,
+        repo.clone(),
+        FakeBroker::new(),
+        EventsConfigs::default(),
+        TodoEventsBroadcaster::new(&cfg),
+    );
+
+    (schema, repo, todo.id)
+}
+
+fn principal(subject: &str, scopes: &[&str]) -> Principal {
+    Principal {
+        subject: subject.to_owned(),
+        scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        method: AuthMethod::Jwt,
+    }
+}
+
+fn delete(id: &str, principal: Principal) -> Request {
+    Request::new(format!(r#"mutation {{ deleteTodo(id: "{}") }}"#, id)).data(principal)
+}
+
+#[actix_web::test]
+async fn delete_todo_of_another_owner_is_forbidden() {
+    let (schema, repo, id) = schema_with_todo("owner").await;
+
+    let res = schema
+        .execute(delete(&id, principal("intruder", &["todos:write"])))
+        .await;
+
+    assert_eq!(res.errors.len(), 1);
+    assert_eq!(res.errors[0].message, "forbidden");
+    assert!(repo
+        .get_by_id(&Context::new(), &id)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[actix_web::test]
+async fn delete_todo_is_allowed_to_its_owner_and_admins() {
+    for principal in [
+        principal("owner", &["todos:write"]),
+        principal("admin", &["todos:admin"]),
+    ] {
+        let (schema, repo, id) = schema_with_todo("owner").await;
+
+        let res = schema.execute(delete(&id, principal)).await;
+
+        assert!(res.errors.is_empty(), "{:?}", res.errors);
+        assert!(repo
+            .get_by_id(&Context::new(), &id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[actix_web::test]
+async fn resolvers_require_their_own_scope() {
+    let (schema, repo, id) = schema_with_todo("owner").await;
+
+    let res = schema
+        .execute(delete(&id, principal("owner", &["todos:read"])))
+        .await;
+
+    assert_eq!(res.errors.len(), 1);
+    assert_eq!(res.errors[0].message, "missing scope: todos:write");
+    assert!(repo
+        .get_by_id(&Context::new(), &id)
+        .await
+        .unwrap()
+        .is_some());
+}
@@ -0,0 +1,107 @@
+use super::fakes::{FakeApiKeyRepository, FakeTodoRepository};
+use crate::{controllers, extractors::API_KEY_HEADER};
+use actix_web::{
+    http::{
+        header::{Header, HttpDate, IfModifiedSince, IfNoneMatch, ETAG},
+        StatusCode,
+    },
+    test,
+    web::{self, Data},
+    App,
+};
+use shared::{
+    models::todo::Todo,
+    repositories::{ApiKeyRepository, TodoRepository},
+};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+const UPDATED_AT: &str = "2023-04-20T12:00:00+00:00";
+
+fn todo_repository() -> Data<Arc<dyn TodoRepository>> {
+    Data::new(FakeTodoRepository::with_todos(vec![Todo {
+        id: "todo".to_owned(),
+        name: "name".to_owned(),
+        description: "description".to_owned(),
+        created_at: UPDATED_AT.to_owned(),
+        updated_at: UPDATED_AT.to_owned(),
+        ..Default::default()
+    }]))
+}
+
+fn api_key_repository() -> Data<Arc<dyn ApiKeyRepository>> {
+    Data::new(FakeApiKeyRepository::new(&["todos:read"]))
+}
+
+fn get_todo() -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/v1/todos/todo")
+        .insert_header((API_KEY_HEADER, "tdk_fake"))
+}
+
+fn updated_at() -> SystemTime {
+    SystemTime::from(chrono::DateTime::parse_from_rfc3339(UPDATED_AT).unwrap())
+}
+
+#[actix_web::test]
+async fn if_modified_since_alone_answers_not_modified() {
+    let app = test::init_service(
+        App::new()
+            .app_data(todo_repository())
+            .app_data(api_key_repository())
+            .service(web::scope("/v1/todos").service(controllers::get)),
+    )
+    .await;
+
+    let req = get_todo()
+        .insert_header(IfModifiedSince(HttpDate::from(updated_at())))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+
+    let req = get_todo()
+        .insert_header(IfModifiedSince(HttpDate::from(
+            updated_at() - Duration::from_secs(60),
+        )))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn if_none_match_takes_precedence_over_if_modified_since() {
+    let app = test::init_service(
+        App::new()
+            .app_data(todo_repository())
+            .app_data(api_key_repository())
+            .service(web::scope("/v1/todos").service(controllers::get)),
+    )
+    .await;
+
+    let res = test::call_service(&app, get_todo().to_request()).await;
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let req = get_todo()
+        .insert_header((IfNoneMatch::name(), etag))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_MODIFIED
+    );
+
+    // a stale ETag wins over a matching date
+    let req = get_todo()
+        .insert_header((IfNoneMatch::name(), "\"stale\""))
+        .insert_header(IfModifiedSince(HttpDate::from(updated_at())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
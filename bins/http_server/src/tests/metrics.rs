@@ -0,0 +1,48 @@
+use crate::{dynamic_configs::HttpServerConfigs, middlewares::HttpMetrics};
+use infra::telemetry::DEFAULT_DURATION_BUCKETS;
+use opentelemetry::sdk::{
+    export::metrics::{aggregation::Histogram, AggregatorSelector},
+    metrics::{
+        aggregators::HistogramAggregator,
+        sdk_api::{Descriptor, InstrumentKind, NumberKind},
+    },
+};
+
+fn boundaries(cfg: &HttpServerConfigs, name: &str) -> Vec<f64> {
+    let descriptor = Descriptor::new(
+        name.to_owned(),
+        InstrumentKind::Histogram,
+        NumberKind::F64,
+        None,
+        None,
+    );
+
+    let aggregator = HttpMetrics::aggregator_selector(cfg)
+        .aggregator_for(&descriptor)
+        .unwrap();
+    let histogram = aggregator
+        .as_any()
+        .downcast_ref::<HistogramAggregator>()
+        .unwrap()
+        .histogram()
+        .unwrap();
+
+    histogram.boundaries().clone()
+}
+
+#[test]
+fn duration_buckets_only_apply_to_the_http_server_duration() {
+    let cfg = HttpServerConfigs {
+        http_duration_buckets: vec![1.0, 2.0, 3.0],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        boundaries(&cfg, "http.server.duration"),
+        vec![1.0, 2.0, 3.0]
+    );
+    assert_eq!(
+        boundaries(&cfg, "todo_repository.cache.lookup"),
+        DEFAULT_DURATION_BUCKETS.to_vec()
+    );
+}
@@ -0,0 +1,10 @@
+mod cache;
+mod fakes;
+mod graphql;
+mod http_cache;
+mod metrics;
+mod ownership;
+mod publish;
+mod rate_limit;
+mod stats;
+mod telemetry;
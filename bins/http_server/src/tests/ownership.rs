@@ -0,0 +1,75 @@
+use super::fakes::{FakeApiKeyRepository, FakeBroker, FakeTodoRepository};
+use crate::{controllers, extractors::API_KEY_HEADER};
+use actix_web::{
+    http::StatusCode,
+    test,
+    web::{self, Data},
+    App,
+};
+use opentelemetry::Context;
+use shared::{
+    amqp::{EventPublisher, EventsConfigs},
+    models::todo::Todo,
+    repositories::{ApiKeyRepository, TodoRepository},
+};
+use std::sync::Arc;
+
+/// Completes the todo of `fake-owner` as `subject`, answering the status, whether the todo ended
+/// up completed and how many events were published.
+async fn complete_as(subject: &str, scopes: &[&str]) -> (StatusCode, bool, usize) {
+    let repo = FakeTodoRepository::with_todos(vec![Todo {
+        id: "todo".to_owned(),
+        owner_id: Some("fake-owner".to_owned()),
+        ..Default::default()
+    }]);
+    let broker = FakeBroker::new();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn TodoRepository>>::new(repo.clone()))
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::owned_by(subject, scopes),
+            ))
+            .app_data(Data::<Arc<dyn EventPublisher>>::new(broker.clone()))
+            .app_data(Data::new(EventsConfigs::default()))
+            .service(web::scope("/v1/todos").service(controllers::complete)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/todos/todo/complete")
+        .insert_header((API_KEY_HEADER, "tdk_fake"))
+        .to_request();
+    let status = test::call_service(&app, req).await.status();
+
+    let todo = repo
+        .get_by_id(&Context::new(), "todo")
+        .await
+        .unwrap()
+        .unwrap();
+
+    (
+        status,
+        todo.completed_at.is_some(),
+        broker.event_types().len(),
+    )
+}
+
+#[actix_web::test]
+async fn completing_a_todo_of_another_owner_is_forbidden() {
+    assert_eq!(
+        complete_as("intruder", &["todos:write"]).await,
+        (StatusCode::FORBIDDEN, false, 0)
+    );
+}
+
+#[actix_web::test]
+async fn completing_a_todo_is_allowed_to_its_owner_and_admins() {
+    assert_eq!(
+        complete_as("fake-owner", &["todos:write"]).await,
+        (StatusCode::OK, true, 1)
+    );
+    assert_eq!(
+        complete_as("admin", &["todos:admin"]).await,
+        (StatusCode::OK, true, 1)
+    );
+}
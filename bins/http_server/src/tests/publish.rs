@@ -0,0 +1,133 @@
+use super::fakes::{FailingPublisher, FakeApiKeyRepository, FakeTodoRepository};
+use crate::{controllers, extractors::API_KEY_HEADER};
+use actix_web::{
+    http::StatusCode,
+    test,
+    web::{self, Data},
+    App,
+};
+use serde_json::json;
+use shared::{
+    amqp::{EventPublisher, EventsConfigs, PublishError},
+    models::todo::Todo,
+    repositories::{ApiKeyRepository, TodoRepository},
+};
+use std::{sync::Arc, time::Duration};
+
+fn timeout() -> PublishError {
+    PublishError::ConfirmTimeout(Duration::from_millis(5000))
+}
+
+fn nacked() -> PublishError {
+    PublishError::Nacked
+}
+
+fn unroutable() -> PublishError {
+    PublishError::Unroutable("NO_ROUTE".to_owned())
+}
+
+/// Outcomes of an event the broker did not take, with the status and message they answer.
+const OUTCOMES: [(fn() -> PublishError, StatusCode, &str); 3] = [
+    (
+        timeout,
+        StatusCode::SERVICE_UNAVAILABLE,
+        "but its event was not confirmed",
+    ),
+    (
+        nacked,
+        StatusCode::SERVICE_UNAVAILABLE,
+        "but its event was refused",
+    ),
+    (
+        unroutable,
+        StatusCode::BAD_GATEWAY,
+        "but its event reached no queue",
+    ),
+];
+
+async fn call(error: fn() -> PublishError, req: test::TestRequest) -> (StatusCode, String) {
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn TodoRepository>>::new(
+                FakeTodoRepository::with_todos(vec![Todo {
+                    id: "todo".to_owned(),
+                    owner_id: Some("fake-owner".to_owned()),
+                    ..Default::default()
+                }]),
+            ))
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::new(&["todos:write"]),
+            ))
+            .app_data(Data::<Arc<dyn EventPublisher>>::new(FailingPublisher::new(
+                error,
+            )))
+            .app_data(Data::new(EventsConfigs::default()))
+            .service(
+                web::scope("/v1/todos")
+                    .service(controllers::post)
+                    .service(controllers::delete)
+                    .service(controllers::complete),
+            )
+            .service(
+                web::scope("/v2/todos")
+                    .service(controllers::post_v2)
+                    .service(controllers::delete_v2),
+            ),
+    )
+    .await;
+
+    let res = test::call_service(
+        &app,
+        req.insert_header((API_KEY_HEADER, "tdk_fake")).to_request(),
+    )
+    .await;
+    let status = res.status();
+    let body = test::read_body(res).await;
+
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn requests() -> Vec<(&'static str, test::TestRequest)> {
+    let todo = json!({ "name": "name", "description": "description" });
+
+    vec![
+        (
+            "todo created",
+            test::TestRequest::post()
+                .uri("/v1/todos")
+                .set_json(todo.clone()),
+        ),
+        (
+            "todo deleted",
+            test::TestRequest::delete().uri("/v1/todos/todo"),
+        ),
+        (
+            "todo completed",
+            test::TestRequest::post().uri("/v1/todos/todo/complete"),
+        ),
+        (
+            "todo created",
+            test::TestRequest::post().uri("/v2/todos").set_json(todo),
+        ),
+        (
+            "todo deleted",
+            test::TestRequest::delete().uri("/v2/todos/todo"),
+        ),
+    ]
+}
+
+#[actix_web::test]
+async fn events_the_broker_did_not_take_answer_their_own_status() {
+    for (error, status, message) in OUTCOMES {
+        for (change, req) in requests() {
+            let (got, body) = call(error, req).await;
+
+            assert_eq!(got, status, "{} {}", change, message);
+            assert!(
+                body.contains(&format!("{} {}", change, message)),
+                "{}",
+                body
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use super::fakes::FakeApiKeyRepository;
+use crate::{
+    dynamic_configs::{HttpServerConfigs, RateLimitRule},
+    extractors::API_KEY_HEADER,
+    middlewares::RateLimiter,
+};
+use actix_web::{
+    http::StatusCode,
+    test,
+    web::{self, Data},
+    App, HttpResponse,
+};
+use shared::repositories::ApiKeyRepository;
+use std::{sync::Arc, time::Duration};
+
+fn limiter(requests: u32) -> RateLimiter {
+    RateLimiter::new(&HttpServerConfigs {
+        rate_limit_default: RateLimitRule {
+            requests,
+            period: Duration::from_secs(60),
+        },
+        ..Default::default()
+    })
+}
+
+#[actix_web::test]
+async fn rotating_invalid_api_keys_does_not_bypass_the_limit() {
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::rejecting(),
+            ))
+            .service(
+                web::scope("/limited")
+                    .wrap(limiter(2))
+                    .route("", web::get().to(HttpResponse::Ok)),
+            ),
+    )
+    .await;
+
+    let mut statuses = vec![];
+    for i in 0..3 {
+        let req = test::TestRequest::get()
+            .uri("/limited")
+            .insert_header((API_KEY_HEADER, format!("tdk_random_{}", i)))
+            .to_request();
+        statuses.push(test::call_service(&app, req).await.status());
+    }
+
+    assert_eq!(
+        statuses,
+        vec![
+            StatusCode::OK,
+            StatusCode::OK,
+            StatusCode::TOO_MANY_REQUESTS
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn valid_api_keys_get_their_own_bucket() {
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::new(&["todos:read"]),
+            ))
+            .service(
+                web::scope("/limited")
+                    .wrap(limiter(1))
+                    .route("", web::get().to(HttpResponse::Ok)),
+            ),
+    )
+    .await;
+
+    let anonymous = test::TestRequest::get().uri("/limited").to_request();
+    assert_eq!(
+        test::call_service(&app, anonymous).await.status(),
+        StatusCode::OK
+    );
+
+    let authenticated = test::TestRequest::get()
+        .uri("/limited")
+        .insert_header((API_KEY_HEADER, "tdk_fake"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, authenticated).await.status(),
+        StatusCode::OK
+    );
+}
@@ -0,0 +1,124 @@
+use super::fakes::{FakeApiKeyRepository, FakeBroker, FakeTodoRepository, FakeTodoStatsRepository};
+use crate::{controllers, extractors::API_KEY_HEADER};
+use actix_web::{
+    http::StatusCode,
+    test,
+    web::{self, Data},
+    App,
+};
+use serde_json::{json, Value};
+use shared::{
+    amqp::{EventPublisher, EventsConfigs},
+    events::TODO_COMPLETED_EVENT,
+    models::{todo::Todo, todo_stats::TodoStats},
+    repositories::{ApiKeyRepository, TodoRepository, TodoStatsRepository},
+};
+use std::sync::Arc;
+
+fn stats() -> Vec<TodoStats> {
+    vec![TodoStats {
+        owner_id: "fake-owner".to_owned(),
+        day: "2023-04-20".to_owned(),
+        created: 3,
+        deleted: 1,
+        completed: 2,
+    }]
+}
+
+async fn get_stats(repo: Arc<FakeTodoStatsRepository>, query: &str) -> (StatusCode, String) {
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn TodoStatsRepository>>::new(repo))
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::new(&["todos:read"]),
+            ))
+            .service(web::scope("/v1/todos").service(controllers::stats)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/v1/todos/stats{}", query))
+        .insert_header((API_KEY_HEADER, "tdk_fake"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    let status = res.status();
+    let body = test::read_body(res).await;
+
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[actix_web::test]
+async fn stats_of_the_requested_days_are_returned() {
+    let repo = FakeTodoStatsRepository::with_stats(stats());
+
+    let (status, body) = get_stats(repo.clone(), "?from=2023-04-01&to=2023-04-30").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body).unwrap(),
+        json!([{ "day": "2023-04-20", "created": 3, "deleted": 1, "completed": 2 }])
+    );
+    assert_eq!(
+        repo.ranges(),
+        vec![("2023-04-01".to_owned(), "2023-04-30".to_owned())]
+    );
+}
+
+#[actix_web::test]
+async fn invalid_days_are_rejected_with_the_reason() {
+    for (query, reason) in [
+        (
+            "?from=2023-13-01",
+            "`from` must be a day formatted as YYYY-MM-DD",
+        ),
+        (
+            "?to=yesterday",
+            "`to` must be a day formatted as YYYY-MM-DD",
+        ),
+        (
+            "?from=2023-04-30&to=2023-04-01",
+            "`from` must not be after `to`",
+        ),
+    ] {
+        let repo = FakeTodoStatsRepository::with_stats(stats());
+
+        let (status, body) = get_stats(repo.clone(), query).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+        assert!(body.contains(reason), "{}: {}", query, body);
+        assert!(repo.ranges().is_empty(), "{}", query);
+    }
+}
+
+#[actix_web::test]
+async fn completing_a_todo_publishes_it_once() {
+    let broker = FakeBroker::new();
+    let app = test::init_service(
+        App::new()
+            .app_data(Data::<Arc<dyn TodoRepository>>::new(
+                FakeTodoRepository::with_todos(vec![Todo {
+                    id: "todo".to_owned(),
+                    owner_id: Some("fake-owner".to_owned()),
+                    updated_at: "2023-04-20T12:00:00+00:00".to_owned(),
+                    ..Default::default()
+                }]),
+            ))
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::new(&["todos:write"]),
+            ))
+            .app_data(Data::<Arc<dyn EventPublisher>>::new(broker.clone()))
+            .app_data(Data::new(EventsConfigs::default()))
+            .service(web::scope("/v1/todos").service(controllers::complete)),
+    )
+    .await;
+
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/v1/todos/todo/complete")
+            .insert_header((API_KEY_HEADER, "tdk_fake"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    assert_eq!(broker.event_types(), vec![TODO_COMPLETED_EVENT.to_owned()]);
+}
@@ -0,0 +1,203 @@
+use super::fakes::{FakeApiKeyRepository, FakeBroker, FakeTodoRepository, DATABASE, QUEUE};
+use crate::{controllers, extractors::API_KEY_HEADER, middlewares::HttpMetrics};
+use actix_web::{
+    test,
+    web::{self, Data},
+    App,
+};
+use amqp::publisher::Payload;
+use consumers::{DispatchError, ErrorClass, EventDispatcher, SimpleConsumer};
+use http_components::middlewares::otel::HTTPOTelTracing;
+use infra::telemetry::testing::TelemetryHarness;
+use opentelemetry::{
+    global,
+    sdk::export::trace::SpanData,
+    trace::{SpanKind, Status, TraceContextExt, Tracer},
+    Context,
+};
+use serde_json::json;
+use shared::{
+    amqp::{EnvelopeError, EventPublisher, EventsConfigs, EXCHANGE, TODO_CREATED_ROUTING_KEY},
+    events::{JSON_CONTENT_TYPE, TODO_CREATED_EVENT},
+    models::todo::TodoCreatedMessage,
+    repositories::{ApiKeyRepository, TodoRepository},
+};
+use std::{collections::HashMap, sync::Arc};
+
+fn parent_of(child: &SpanData, parent: &SpanData) -> bool {
+    child.parent_span_id == parent.span_context.span_id()
+}
+
+fn dispatcher() -> EventDispatcher {
+    EventDispatcher::new().register::<TodoCreatedMessage>(QUEUE, SimpleConsumer::new())
+}
+
+#[actix_web::test]
+async fn post_todo_is_traced_from_the_request_to_the_consumer() {
+    let telemetry = TelemetryHarness::install();
+
+    let broker = FakeBroker::new();
+    // wrapped like HTTPServer wraps every app, so the request gets the real Server span
+    let app = test::init_service(
+        App::new()
+            .wrap(HTTPOTelTracing::new())
+            .app_data(Data::<Arc<dyn TodoRepository>>::new(
+                FakeTodoRepository::new(),
+            ))
+            .app_data(Data::<Arc<dyn ApiKeyRepository>>::new(
+                FakeApiKeyRepository::new(&["todos:write"]),
+            ))
+            .app_data(Data::<Arc<dyn EventPublisher>>::new(broker.clone()))
+            .app_data(Data::new(EventsConfigs::default()))
+            .service(
+                web::scope("/v1/todos")
+                    .wrap(HttpMetrics::new())
+                    .service(controllers::post),
+            ),
+    )
+    .await;
+
+    // the calling service, propagating its context through the request headers
+    let tracer = global::tracer("http-client");
+    let client = tracer
+        .span_builder("create todo")
+        .with_kind(SpanKind::Client)
+        .start(&tracer);
+    let client_ctx = Context::current_with_span(client);
+
+    let mut headers = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&client_ctx, &mut headers)
+    });
+
+    let mut req = test::TestRequest::post()
+        .uri("/v1/todos")
+        .insert_header((API_KEY_HEADER, "tdk_fake"))
+        .set_json(json!({ "name": "trace", "description": "one connected trace" }));
+    for header in headers {
+        req = req.insert_header(header);
+    }
+
+    let res = test::call_service(&app, req.to_request()).await;
+    client_ctx.span().end();
+    assert!(res.status().is_success(), "status: {}", res.status());
+
+    assert_eq!(broker.deliver(&dispatcher()).await.ok(), Some(1));
+
+    let span = |name: &str| {
+        telemetry
+            .span(name)
+            .unwrap_or_else(|| panic!("missing span {}", name))
+    };
+    let client = span("create todo");
+    let http = telemetry
+        .spans()
+        .into_iter()
+        .find(|s| s.name == "POST /v1/todos" && s.span_kind == SpanKind::Server)
+        .expect("missing server span POST /v1/todos");
+    let db = span(&format!("INSERT {}.todos", DATABASE));
+    let publish = span("todo-events publish");
+    let process = span("simple-queue process");
+    let handler = span("simple_consumer_handler");
+
+    let trace_id = http.span_context.trace_id();
+    for s in telemetry.spans() {
+        assert_eq!(s.span_context.trace_id(), trace_id, "span {}", s.name);
+    }
+
+    assert!(
+        parent_of(&http, &client),
+        "server span is not a child of the caller through the request headers"
+    );
+    assert!(
+        parent_of(&db, &http),
+        "db span is not a child of the request"
+    );
+    assert!(
+        parent_of(&publish, &http),
+        "publish span is not a child of the request"
+    );
+    assert!(
+        parent_of(&process, &publish),
+        "consume span is not linked to the publish through the message headers"
+    );
+    assert!(
+        parent_of(&handler, &process),
+        "handler span is not a child of the consume span"
+    );
+
+    assert_eq!(db.span_kind, SpanKind::Client);
+    assert_eq!(publish.span_kind, SpanKind::Producer);
+    assert_eq!(process.span_kind, SpanKind::Consumer);
+
+    assert_eq!(telemetry.counter("consumers.messages.processed"), Some(1.0));
+    assert_eq!(telemetry.counter("consumers.messages.failed"), None);
+    assert_eq!(telemetry.histogram_count("http.server.duration"), Some(1));
+}
+
+#[actix_web::test]
+async fn malformed_message_is_counted_as_failed() {
+    let telemetry = TelemetryHarness::install();
+
+    let broker = FakeBroker::new();
+    let event = json!({
+        "specversion": "1.0",
+        "id": "malformed",
+        "source": "/tests",
+        "type": TODO_CREATED_EVENT,
+        "data": { "unexpected": true },
+    });
+    let payload = Payload::new(&event).unwrap();
+    broker
+        .publish(
+            &Context::new(),
+            EXCHANGE,
+            TODO_CREATED_ROUTING_KEY,
+            &payload,
+            JSON_CONTENT_TYPE,
+            None,
+        )
+        .await
+        .unwrap();
+
+    // a message that can never be read is not worth retrying
+    match broker.deliver(&dispatcher()).await {
+        Err(DispatchError::Failed(err)) => assert_eq!(err.class(), ErrorClass::Permanent),
+        other => panic!("expected a failed dispatch, got {:?}", other),
+    }
+
+    assert_eq!(telemetry.counter("consumers.messages.failed"), Some(1.0));
+    assert_eq!(telemetry.counter("consumers.messages.processed"), None);
+
+    let handler = telemetry
+        .span("simple_consumer_handler")
+        .expect("missing handler span");
+    assert!(matches!(handler.status, Status::Error { .. }));
+}
+
+#[actix_web::test]
+async fn unknown_envelope_is_rejected_before_the_handler() {
+    let telemetry = TelemetryHarness::install();
+
+    let broker = FakeBroker::new();
+    let payload = Payload::new(&json!({ "unexpected": true })).unwrap();
+    broker
+        .publish(
+            &Context::new(),
+            EXCHANGE,
+            TODO_CREATED_ROUTING_KEY,
+            &payload,
+            JSON_CONTENT_TYPE,
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        broker.deliver(&dispatcher()).await,
+        Err(DispatchError::Rejected(EnvelopeError::NotACloudEvent(_)))
+    ));
+
+    assert_eq!(telemetry.counter("consumers.messages.failed"), None);
+    assert!(telemetry.span("simple_consumer_handler").is_none());
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use shared::models::api_key::ApiKey;
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub(crate) name: String,
+    /// Subject the key acts on behalf of, defaults to the caller.
+    pub(crate) owner_id: Option<String>,
+    pub(crate) scopes: Vec<String>,
+    /// RFC 3339 expiration date, keys without it never expire.
+    pub(crate) expires_at: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKeyResponse {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    /// Plaintext key, it is only returned once.
+    pub(crate) key: String,
+    pub(crate) owner_id: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) expires_at: Option<String>,
+    pub(crate) created_at: String,
+}
+
+impl CreatedApiKeyResponse {
+    pub fn new(value: &ApiKey, key: String) -> Self {
+        CreatedApiKeyResponse {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            key,
+            owner_id: value.owner_id.clone(),
+            scopes: value.scopes.clone(),
+            expires_at: value.expires_at.clone(),
+            created_at: value.created_at.clone(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyResponse {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) owner_id: String,
+    pub(crate) scopes: Vec<String>,
+    pub(crate) expires_at: Option<String>,
+    pub(crate) last_used_at: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) revoked_at: Option<String>,
+    /// Admin that minted the key.
+    pub(crate) created_by: Option<String>,
+}
+
+impl From<&ApiKey> for ApiKeyResponse {
+    fn from(value: &ApiKey) -> Self {
+        ApiKeyResponse {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            owner_id: value.owner_id.clone(),
+            scopes: value.scopes.clone(),
+            expires_at: value.expires_at.clone(),
+            last_used_at: value.last_used_at.clone(),
+            created_at: value.created_at.clone(),
+            revoked_at: value.revoked_at.clone(),
+            created_by: value.created_by.clone(),
+        }
+    }
+}
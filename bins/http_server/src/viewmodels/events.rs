@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use shared::models::todo::{
+    TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage, TodoRestoredMessage,
+    TodoUpdatedMessage,
+};
+use utoipa::ToSchema;
+
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct TodoEventResponse {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) created_at: String,
+}
+
+impl TodoEventResponse {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl From<&TodoCreatedMessage> for TodoEventResponse {
+    fn from(value: &TodoCreatedMessage) -> Self {
+        TodoEventResponse {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            created_at: value.created_at.clone(),
+        }
+    }
+}
+
+/// Payload of the `updated`, `deleted`, `restored` and `completed` events. Name and description
+/// are only sent when the event carries them.
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct TodoChangedEventResponse {
+    pub(crate) id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    pub(crate) changed_at: String,
+}
+
+impl TodoChangedEventResponse {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl From<&TodoUpdatedMessage> for TodoChangedEventResponse {
+    fn from(value: &TodoUpdatedMessage) -> Self {
+        TodoChangedEventResponse {
+            id: value.id.clone(),
+            name: Some(value.name.clone()),
+            description: Some(value.description.clone()),
+            changed_at: value.updated_at.clone(),
+        }
+    }
+}
+
+impl From<&TodoDeletedMessage> for TodoChangedEventResponse {
+    fn from(value: &TodoDeletedMessage) -> Self {
+        TodoChangedEventResponse {
+            id: value.id.clone(),
+            changed_at: value.deleted_at.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&TodoRestoredMessage> for TodoChangedEventResponse {
+    fn from(value: &TodoRestoredMessage) -> Self {
+        TodoChangedEventResponse {
+            id: value.id.clone(),
+            name: Some(value.name.clone()),
+            description: Some(value.description.clone()),
+            changed_at: value.restored_at.clone(),
+        }
+    }
+}
+
+impl From<&TodoCompletedMessage> for TodoChangedEventResponse {
+    fn from(value: &TodoCompletedMessage) -> Self {
+        TodoChangedEventResponse {
+            id: value.id.clone(),
+            changed_at: value.completed_at.clone(),
+            ..Default::default()
+        }
+    }
+}
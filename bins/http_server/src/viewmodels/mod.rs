@@ -1,3 +1,14 @@
+mod api_keys;
+mod events;
+mod stats;
 mod todos;
+mod todos_v2;
 
+pub use api_keys::{ApiKeyResponse, CreateApiKeyRequest, CreatedApiKeyResponse};
+pub use events::{TodoChangedEventResponse, TodoEventResponse};
+pub use stats::{TodoStatsQuery, TodoStatsResponse};
 pub use todos::{CreateTodoRequest, TodoResponse};
+pub use todos_v2::{
+    CreateTodoV2Request, LinkResponse, TodoLinksResponse, TodoPageLinksResponse,
+    TodoPageV2Response, TodoV2Response, TODOS_V2_PATH,
+};
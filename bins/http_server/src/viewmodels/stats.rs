@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use shared::models::todo_stats::TodoStats;
+use utoipa::{IntoParams, ToSchema};
+
+/// Days to return, both inclusive and formatted as `YYYY-MM-DD`. Defaults to the last 30 days.
+#[derive(Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TodoStatsQuery {
+    pub(crate) from: Option<String>,
+    pub(crate) to: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize, ToSchema)]
+pub struct TodoStatsResponse {
+    pub(crate) day: String,
+    pub(crate) created: i32,
+    pub(crate) deleted: i32,
+    pub(crate) completed: i32,
+}
+
+impl From<&TodoStats> for TodoStatsResponse {
+    fn from(value: &TodoStats) -> Self {
+        TodoStatsResponse {
+            day: value.day.clone(),
+            created: value.created,
+            deleted: value.deleted,
+            completed: value.completed,
+        }
+    }
+}
@@ -13,6 +13,7 @@ impl Into<CreateTodo> for CreateTodoRequest {
         CreateTodo {
             name: self.name,
             description: self.description,
+            owner_id: None,
         }
     }
 }
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::models::todo::{CreateTodo, Todo};
+use utoipa::ToSchema;
+
+pub const TODOS_V2_PATH: &str = "/v2/todos";
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateTodoV2Request {
+    pub(crate) name: String,
+    pub(crate) description: String,
+}
+
+impl From<CreateTodoV2Request> for CreateTodo {
+    fn from(value: CreateTodoV2Request) -> Self {
+        CreateTodo {
+            name: value.name,
+            description: value.description,
+            owner_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LinkResponse {
+    pub(crate) href: String,
+    pub(crate) method: String,
+}
+
+impl LinkResponse {
+    fn new(method: &str, href: String) -> LinkResponse {
+        LinkResponse {
+            href,
+            method: method.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TodoLinksResponse {
+    #[serde(rename = "self")]
+    pub(crate) self_link: LinkResponse,
+    pub(crate) collection: LinkResponse,
+    pub(crate) delete: LinkResponse,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TodoV2Response {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) owner_id: Option<String>,
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) updated_at: DateTime<Utc>,
+    pub(crate) deleted_at: Option<DateTime<Utc>>,
+    #[serde(rename = "_links")]
+    pub(crate) links: TodoLinksResponse,
+}
+
+impl From<&Todo> for TodoV2Response {
+    fn from(value: &Todo) -> Self {
+        let href = format!("{}/{}", TODOS_V2_PATH, value.id);
+
+        TodoV2Response {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            owner_id: value.owner_id.clone(),
+            created_at: timestamp(&value.created_at).unwrap_or_default(),
+            updated_at: timestamp(&value.updated_at).unwrap_or_default(),
+            deleted_at: value.deleted_at.as_deref().and_then(timestamp),
+            links: TodoLinksResponse {
+                self_link: LinkResponse::new("GET", href.clone()),
+                collection: LinkResponse::new("GET", TODOS_V2_PATH.to_owned()),
+                delete: LinkResponse::new("DELETE", href),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TodoPageLinksResponse {
+    #[serde(rename = "self")]
+    pub(crate) self_link: LinkResponse,
+    pub(crate) create: LinkResponse,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TodoPageV2Response {
+    pub(crate) items: Vec<TodoV2Response>,
+    #[serde(rename = "_links")]
+    pub(crate) links: TodoPageLinksResponse,
+}
+
+impl From<&[Todo]> for TodoPageV2Response {
+    fn from(value: &[Todo]) -> Self {
+        TodoPageV2Response {
+            items: value.iter().map(TodoV2Response::from).collect(),
+            links: TodoPageLinksResponse {
+                self_link: LinkResponse::new("GET", TODOS_V2_PATH.to_owned()),
+                create: LinkResponse::new("POST", TODOS_V2_PATH.to_owned()),
+            },
+        }
+    }
+}
+
+/// The repository keeps timestamps as RFC 3339 strings.
+fn timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
@@ -0,0 +1,88 @@
+//! Uploads telemetry written with `TELEMETRY_EXPORTER=file` to an OTLP/HTTP endpoint.
+//!
+//! Every line of the given files is an OTLP/JSON export request, posted as-is to
+//! `<OTLP_REPLAY_ENDPOINT>/v1/traces` or `/v1/metrics`. Rotated files should be given oldest
+//! first, e.g. `telemetry-replay http-server.traces.jsonl.2 http-server.traces.jsonl.1
+//! http-server.traces.jsonl`.
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_json::Value;
+use std::{env, error::Error};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+};
+
+const ENDPOINT_ENV_KEY: &str = "OTLP_REPLAY_ENDPOINT";
+const KEY_ENV_KEY: &str = "OTLP_KEY";
+const API_KEY_HEADER: &str = "api-key";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let files: Vec<String> = env::args().skip(1).collect();
+    if files.is_empty() {
+        return Err("usage: telemetry-replay <file.jsonl>...".into());
+    }
+
+    let endpoint =
+        env::var(ENDPOINT_ENV_KEY).map_err(|_| format!("{} is required", ENDPOINT_ENV_KEY))?;
+    let key = env::var(KEY_ENV_KEY).unwrap_or_default();
+    let client = Client::new();
+
+    for file in files {
+        let sent = replay(&client, endpoint.trim_end_matches('/'), &key, &file).await?;
+        println!("{}: {} requests sent", file, sent);
+    }
+
+    Ok(())
+}
+
+async fn replay(
+    client: &Client,
+    endpoint: &str,
+    key: &str,
+    file: &str,
+) -> Result<usize, Box<dyn Error>> {
+    let mut lines = BufReader::new(File::open(file).await?).lines();
+    let mut sent = 0;
+    let mut line_number = 0;
+
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let body: Value = serde_json::from_str(&line)
+            .map_err(|err| format!("{}:{}: invalid json: {}", file, line_number, err))?;
+
+        let path = match (body.get("resourceSpans"), body.get("resourceMetrics")) {
+            (Some(_), _) => "v1/traces",
+            (_, Some(_)) => "v1/metrics",
+            _ => return Err(format!("{}:{}: unknown signal", file, line_number).into()),
+        };
+
+        let mut request = client
+            .post(format!("{}/{}", endpoint, path))
+            .header(CONTENT_TYPE, "application/json")
+            .body(line);
+        if !key.is_empty() {
+            request = request.header(API_KEY_HEADER, key);
+        }
+
+        let res = request.send().await?;
+        if !res.status().is_success() {
+            return Err(format!(
+                "{}:{}: endpoint responded {}: {}",
+                file,
+                line_number,
+                res.status(),
+                res.text().await.unwrap_or_default()
+            )
+            .into());
+        }
+
+        sent += 1;
+    }
+
+    Ok(sent)
+}
@@ -0,0 +1,170 @@
+use deadpool_postgres::Pool;
+use health_readiness::{HealthReadinessService, HealthReadinessServiceImpl};
+use lapin::Connection;
+use opentelemetry::{global, Context};
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{error, warn};
+
+/// Observable gauges reporting the real state of the service dependencies.
+///
+/// Each dependency gets its own `<prefix>.health.<dependency>` gauge (1 healthy, 0 unhealthy),
+/// and the Postgres pool also reports `<prefix>.postgres.pool.{max_size,size,available,waiting}`.
+/// The health gauges run the same `HealthReadinessServiceImpl` checks as the readiness probe, one
+/// checker per dependency, every `interval` in the background; the gauges read the last result.
+pub struct HealthMeter {
+    meter_name: &'static str,
+    prefix: &'static str,
+    interval: Duration,
+    rabbitmq: Option<Arc<Connection>>,
+    postgres: Option<Arc<Pool>>,
+}
+
+impl HealthMeter {
+    pub fn new(meter_name: &'static str, prefix: &'static str) -> HealthMeter {
+        HealthMeter {
+            meter_name,
+            prefix,
+            interval: Duration::from_secs(15),
+            rabbitmq: None,
+            postgres: None,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn rabbitmq(mut self, conn: Arc<Connection>) -> Self {
+        self.rabbitmq = Some(conn);
+        self
+    }
+
+    pub fn postgres(mut self, pool: Arc<Pool>) -> Self {
+        self.postgres = Some(pool);
+        self
+    }
+
+    pub fn install(self) -> Result<(), Box<dyn Error>> {
+        if let Some(conn) = self.rabbitmq.clone() {
+            self.health_gauge(
+                "rabbitmq",
+                "RabbitMQ Health",
+                Arc::new(HealthReadinessServiceImpl::default().amqp(conn)),
+            )?;
+        }
+
+        if let Some(pool) = self.postgres.clone() {
+            self.health_gauge(
+                "postgres",
+                "Postgres Health",
+                Arc::new(HealthReadinessServiceImpl::default().postgres(pool.clone())),
+            )?;
+            self.pool_gauges(pool)?;
+        }
+
+        Ok(())
+    }
+
+    fn health_gauge(
+        &self,
+        dependency: &'static str,
+        description: &'static str,
+        checker: Arc<dyn HealthReadinessService>,
+    ) -> Result<(), Box<dyn Error>> {
+        let healthy = Arc::new(AtomicBool::new(false));
+
+        let checked = healthy.clone();
+        let interval = self.interval;
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let ok = match checker.validate().await {
+                    Err(err) => {
+                        warn!(
+                            error = err.to_string(),
+                            dependency = dependency,
+                            "health check failed"
+                        );
+                        false
+                    }
+                    Ok(_) => true,
+                };
+                checked.store(ok, Ordering::Relaxed);
+            }
+        });
+
+        let meter = global::meter(self.meter_name);
+        let gauge = meter
+            .u64_observable_gauge(format!("{}.health.{}", self.prefix, dependency))
+            .with_description(description)
+            .init();
+
+        match meter.register_callback(move |ctx: &Context| {
+            gauge.observe(ctx, healthy.load(Ordering::Relaxed) as u64, &[]);
+        }) {
+            Err(err) => {
+                error!(
+                    error = err.to_string(),
+                    dependency = dependency,
+                    "error to register health gauge"
+                );
+                Err(err)
+            }
+            _ => Ok(()),
+        }?;
+
+        Ok(())
+    }
+
+    fn pool_gauges(&self, pool: Arc<Pool>) -> Result<(), Box<dyn Error>> {
+        let meter = global::meter(self.meter_name);
+
+        let max_size = meter
+            .u64_observable_gauge(format!("{}.postgres.pool.max_size", self.prefix))
+            .with_description("Postgres Pool Max Size")
+            .init();
+        let size = meter
+            .u64_observable_gauge(format!("{}.postgres.pool.size", self.prefix))
+            .with_description("Postgres Pool Open Connections")
+            .init();
+        let available = meter
+            .u64_observable_gauge(format!("{}.postgres.pool.available", self.prefix))
+            .with_description("Postgres Pool Idle Connections")
+            .init();
+        let waiting = meter
+            .u64_observable_gauge(format!("{}.postgres.pool.waiting", self.prefix))
+            .with_description("Requests Waiting for a Postgres Connection")
+            .init();
+
+        match meter.register_callback(move |ctx: &Context| {
+            let status = pool.status();
+
+            // deadpool reports the waiters as a negative number of available connections
+            max_size.observe(ctx, status.max_size as u64, &[]);
+            size.observe(ctx, status.size as u64, &[]);
+            available.observe(ctx, status.available.max(0) as u64, &[]);
+            waiting.observe(ctx, (-status.available).max(0) as u64, &[]);
+        }) {
+            Err(err) => {
+                error!(
+                    error = err.to_string(),
+                    "error to register postgres pool gauges"
+                );
+                Err(err)
+            }
+            _ => Ok(()),
+        }?;
+
+        Ok(())
+    }
+}
@@ -1 +1,7 @@
+pub mod health;
+pub mod messaging;
 pub mod repositories;
+pub mod telemetry;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,278 @@
+use amqp::publisher::Payload;
+use async_trait::async_trait;
+use lapin::{
+    options::{BasicPublishOptions, ConfirmSelectOptions},
+    publisher_confirm::Confirmation,
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties, Channel,
+};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    metrics::{Counter, Histogram, Unit},
+    trace::{SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use shared::amqp::{EventPublisher, PublishError};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+
+const CONFIRM_TIMEOUT_ENV_KEY: &str = "AMQP_CONFIRM_TIMEOUT_MS";
+const MANDATORY_ENV_KEY: &str = "AMQP_PUBLISH_MANDATORY";
+const PERSISTENT_DELIVERY_MODE: u8 = 2;
+
+pub struct PublisherConfigs {
+    /// How long to wait for the broker to confirm a message.
+    pub confirm_timeout: Duration,
+    /// Publish with the `mandatory` flag, so unroutable messages are returned instead of dropped.
+    pub mandatory: bool,
+}
+
+impl PublisherConfigs {
+    pub fn from_env() -> PublisherConfigs {
+        PublisherConfigs {
+            confirm_timeout: Duration::from_millis(
+                env::var(CONFIRM_TIMEOUT_ENV_KEY)
+                    .ok()
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(5000),
+            ),
+            mandatory: env::var(MANDATORY_ENV_KEY)
+                .ok()
+                .and_then(|m| m.parse().ok())
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// Publishes persistent messages on a channel in confirm mode, waiting for each confirm.
+///
+/// A message only counts as published once the broker acked it and, with `mandatory`, routed it
+/// to some queue. The time until the confirm is recorded in `messaging.publish.duration` and its
+/// outcome (`ack`, `nack`, `returned`, `timeout` or `error`) counted in
+/// `messaging.publish.confirms`, both by exchange.
+pub struct ConfirmingPublisher {
+    tracer: BoxedTracer,
+    channel: Arc<Channel>,
+    confirm_timeout: Duration,
+    mandatory: bool,
+    metrics: PublishMetrics,
+}
+
+impl ConfirmingPublisher {
+    /// Puts `channel` in confirm mode, every publish on it is confirmed from then on.
+    pub async fn new(
+        channel: Arc<Channel>,
+        cfg: &PublisherConfigs,
+    ) -> Result<Arc<ConfirmingPublisher>, lapin::Error> {
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await?;
+
+        Ok(Arc::new(ConfirmingPublisher {
+            tracer: global::tracer("amqp-publisher"),
+            channel,
+            confirm_timeout: cfg.confirm_timeout,
+            mandatory: cfg.mandatory,
+            metrics: PublishMetrics::new(),
+        }))
+    }
+
+    async fn publish_confirmed(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        payload: &Payload,
+        properties: BasicProperties,
+    ) -> Result<(), PublishError> {
+        let confirm = match self
+            .channel
+            .basic_publish(
+                exchange,
+                routing_key,
+                BasicPublishOptions {
+                    mandatory: self.mandatory,
+                    ..Default::default()
+                },
+                &payload.payload,
+                properties,
+            )
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to publish message");
+                Err(PublishError::Broker(err.to_string()))
+            }
+            Ok(c) => Ok(c),
+        }?;
+
+        let confirmation = tokio::time::timeout(self.confirm_timeout, confirm)
+            .await
+            .ok();
+
+        confirmed(exchange, routing_key, self.confirm_timeout, confirmation)
+    }
+}
+
+/// Result of a publish from the broker confirm, `None` when it did not come within `timeout`.
+pub(crate) fn confirmed(
+    exchange: &str,
+    routing_key: &str,
+    timeout: Duration,
+    confirmation: Option<Result<Confirmation, lapin::Error>>,
+) -> Result<(), PublishError> {
+    match confirmation {
+        None => {
+            error!(
+                exchange = exchange,
+                routing_key = routing_key,
+                "publish confirm timed out"
+            );
+            Err(PublishError::ConfirmTimeout(timeout))
+        }
+        Some(Err(err)) => {
+            error!(error = err.to_string(), "error to confirm message");
+            Err(PublishError::Broker(err.to_string()))
+        }
+        Some(Ok(Confirmation::Ack(Some(returned)))) => {
+            warn!(
+                exchange = exchange,
+                routing_key = routing_key,
+                reply = returned.reply_text.as_str(),
+                "message returned as unroutable"
+            );
+            Err(PublishError::Unroutable(
+                returned.reply_text.as_str().to_owned(),
+            ))
+        }
+        Some(Ok(Confirmation::Nack(_))) => {
+            error!(
+                exchange = exchange,
+                routing_key = routing_key,
+                "message nacked by the broker"
+            );
+            Err(PublishError::Nacked)
+        }
+        Some(Ok(_)) => Ok(()),
+    }
+}
+
+/// Confirm duration and outcome of the messages published by a `ConfirmingPublisher`.
+pub(crate) struct PublishMetrics {
+    duration: Histogram<f64>,
+    confirms: Counter<u64>,
+}
+
+impl PublishMetrics {
+    pub(crate) fn new() -> PublishMetrics {
+        let meter = global::meter("amqp-publisher");
+
+        let duration = meter
+            .f64_histogram("messaging.publish.duration")
+            .with_description("Time Until the Broker Confirms a Message")
+            .with_unit(Unit::new("ms"))
+            .init();
+
+        let confirms = meter
+            .u64_counter("messaging.publish.confirms")
+            .with_description("Publisher Confirms by Outcome")
+            .init();
+
+        PublishMetrics { duration, confirms }
+    }
+
+    pub(crate) fn record(
+        &self,
+        ctx: &Context,
+        exchange: &str,
+        elapsed_ms: f64,
+        result: &Result<(), PublishError>,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ack",
+            Err(PublishError::Nacked) => "nack",
+            Err(PublishError::Unroutable(_)) => "returned",
+            Err(PublishError::ConfirmTimeout(_)) => "timeout",
+            Err(_) => "error",
+        };
+        let attributes = [
+            KeyValue::new("messaging.destination.name", exchange.to_owned()),
+            KeyValue::new("outcome", outcome),
+        ];
+
+        self.duration.record(ctx, elapsed_ms, &attributes);
+        self.confirms.add(ctx, 1, &attributes);
+    }
+}
+
+#[async_trait]
+impl EventPublisher for ConfirmingPublisher {
+    async fn publish(
+        &self,
+        ctx: &Context,
+        exchange: &str,
+        routing_key: &str,
+        payload: &Payload,
+        content_type: &str,
+        headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError> {
+        let span = self
+            .tracer
+            .span_builder(format!("{} publish", exchange))
+            .with_kind(SpanKind::Producer)
+            .with_attributes(vec![
+                KeyValue::new("messaging.system", "rabbitmq"),
+                KeyValue::new("messaging.destination.name", exchange.to_owned()),
+                KeyValue::new(
+                    "messaging.rabbitmq.destination.routing_key",
+                    routing_key.to_owned(),
+                ),
+            ])
+            .start_with_context(&self.tracer, ctx);
+        let ctx = ctx.with_span(span);
+
+        let mut trace_headers: HashMap<String, String> = HashMap::new();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&ctx, &mut trace_headers)
+        });
+
+        let mut table = FieldTable::default();
+        for (key, value) in headers.unwrap_or_default() {
+            table.insert(ShortString::from(key), value);
+        }
+        for (key, value) in trace_headers {
+            table.insert(
+                ShortString::from(key),
+                AMQPValue::LongString(LongString::from(value)),
+            );
+        }
+
+        let properties = BasicProperties::default()
+            .with_content_type(ShortString::from(content_type))
+            .with_delivery_mode(PERSISTENT_DELIVERY_MODE)
+            .with_headers(table);
+
+        let started_at = Instant::now();
+        let result = self
+            .publish_confirmed(exchange, routing_key, payload, properties)
+            .await;
+        let elapsed = started_at.elapsed().as_secs_f64() * 1000.0;
+
+        self.metrics.record(&ctx, exchange, elapsed, &result);
+
+        if let Err(err) = &result {
+            ctx.span().record_error(err);
+            ctx.span().set_status(Status::Error {
+                description: Cow::from("error to publish message"),
+            });
+        }
+        ctx.span().end();
+
+        result
+    }
+}
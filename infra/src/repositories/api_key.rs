@@ -0,0 +1,164 @@
+use super::client::{PostgresClient, SlowQueryConfigs, SqlStatement};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use configs::PostgresConfigs;
+use deadpool_postgres::{tokio_postgres::Row, Pool};
+use opentelemetry::Context;
+use shared::{
+    models::api_key::{ApiKey, CreateApiKey},
+    repositories::ApiKeyRepository,
+};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+const CREATE_API_KEY: SqlStatement = SqlStatement {
+    name: "api_keys.create",
+    operation: "INSERT",
+    table: "api_keys",
+    sql: "INSERT INTO api_keys (name, key_hash, owner_id, scopes, expires_at, created_by) values ($1, $2, $3, $4, $5, $6) RETURNING *",
+};
+
+const LIST_API_KEYS: SqlStatement = SqlStatement {
+    name: "api_keys.list",
+    operation: "SELECT",
+    table: "api_keys",
+    sql: "SELECT * FROM api_keys ORDER BY created_at DESC",
+};
+
+const GET_ACTIVE_API_KEY_BY_HASH: SqlStatement = SqlStatement {
+    name: "api_keys.get_active_by_hash",
+    operation: "SELECT",
+    table: "api_keys",
+    sql: "SELECT * FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())",
+};
+
+const TOUCH_API_KEY: SqlStatement = SqlStatement {
+    name: "api_keys.touch_last_used",
+    operation: "UPDATE",
+    table: "api_keys",
+    sql: "UPDATE api_keys SET last_used_at = NOW() WHERE id = $1",
+};
+
+const REVOKE_API_KEY: SqlStatement = SqlStatement {
+    name: "api_keys.revoke",
+    operation: "UPDATE",
+    table: "api_keys",
+    sql: "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+};
+
+pub struct ApiKeyRepositoryImpl {
+    client: PostgresClient,
+}
+
+impl ApiKeyRepositoryImpl {
+    pub fn new(
+        pool: Arc<Pool>,
+        cfg: &PostgresConfigs,
+        slow_query: &SlowQueryConfigs,
+    ) -> Arc<ApiKeyRepositoryImpl> {
+        let client = PostgresClient::new(pool, cfg, slow_query, "api-key-repository");
+
+        Arc::new(ApiKeyRepositoryImpl { client })
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for ApiKeyRepositoryImpl {
+    async fn create(&self, ctx: &Context, key: &CreateApiKey) -> Result<ApiKey, String> {
+        let expires_at = match &key.expires_at {
+            None => Ok(None),
+            Some(e) => match DateTime::parse_from_rfc3339(e) {
+                Err(err) => {
+                    error!(error = err.to_string(), "invalid expiration date");
+                    Err(String::from("invalid expiration date"))
+                }
+                Ok(d) => Ok(Some(d.with_timezone(&Utc))),
+            },
+        }?;
+
+        let row = self
+            .client
+            .query_one(
+                ctx,
+                &CREATE_API_KEY,
+                &[
+                    &key.name,
+                    &key.key_hash,
+                    &key.owner_id,
+                    &key.scopes,
+                    &expires_at,
+                    &key.created_by,
+                ],
+            )
+            .await?
+            .unwrap();
+
+        Ok(api_key_from_row(&row))
+    }
+
+    async fn list(&self, ctx: &Context) -> Result<Vec<ApiKey>, String> {
+        let rows = self.client.query(ctx, &LIST_API_KEYS, &[]).await?;
+
+        Ok(rows.iter().map(api_key_from_row).collect::<Vec<ApiKey>>())
+    }
+
+    async fn get_active_by_hash(
+        &self,
+        ctx: &Context,
+        hash: &str,
+    ) -> Result<Option<ApiKey>, String> {
+        match self
+            .client
+            .query_one(ctx, &GET_ACTIVE_API_KEY_BY_HASH, &[&hash])
+            .await?
+        {
+            None => Ok(None),
+            Some(row) => Ok(Some(api_key_from_row(&row))),
+        }
+    }
+
+    async fn touch_last_used(&self, ctx: &Context, id: &str) -> Result<(), String> {
+        let uid = parse_uuid(id)?;
+        self.client.execute(ctx, &TOUCH_API_KEY, &[&uid]).await?;
+
+        Ok(())
+    }
+
+    async fn revoke(&self, ctx: &Context, id: &str) -> Result<bool, String> {
+        let uid = parse_uuid(id)?;
+        let revoked = self.client.execute(ctx, &REVOKE_API_KEY, &[&uid]).await?;
+
+        Ok(revoked > 0)
+    }
+}
+
+fn parse_uuid(id: &str) -> Result<Uuid, String> {
+    match Uuid::parse_str(id) {
+        Err(err) => {
+            error!(error = err.to_string(), "invalid uuid");
+            Err(String::from("invalid uuid"))
+        }
+        Ok(u) => Ok(u),
+    }
+}
+
+fn api_key_from_row(row: &Row) -> ApiKey {
+    ApiKey {
+        id: row.get::<usize, Uuid>(0).to_string(),
+        name: row.get(1),
+        owner_id: row.get(3),
+        scopes: row.get(4),
+        expires_at: row
+            .get::<usize, Option<DateTime<Utc>>>(5)
+            .map(|d| d.to_rfc3339()),
+        last_used_at: row
+            .get::<usize, Option<DateTime<Utc>>>(6)
+            .map(|d| d.to_rfc3339()),
+        created_at: row.get::<usize, DateTime<Utc>>(7).to_rfc3339(),
+        revoked_at: row
+            .get::<usize, Option<DateTime<Utc>>>(8)
+            .map(|d| d.to_rfc3339()),
+        created_by: row.get(9),
+    }
+}
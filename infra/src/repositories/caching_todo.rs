@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use lru::LruCache;
+use opentelemetry::{global, metrics::Counter, Context, KeyValue};
+use shared::{
+    models::todo::{CreateTodo, Todo},
+    repositories::TodoRepository,
+};
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+struct CacheEntry {
+    todo: Todo,
+    cached_at: Instant,
+}
+
+/// Read-through cache in front of any `TodoRepository`.
+///
+/// Only `get_by_id` is cached. Entries expire after `ttl`, the least recently used ones are
+/// evicted once `capacity` is reached, and `invalidate` drops an entry when another replica
+/// changed the ToDo.
+pub struct CachingTodoRepository {
+    inner: Arc<dyn TodoRepository>,
+    cache: Arc<Mutex<LruCache<String, CacheEntry>>>,
+    ttl: Duration,
+    hits: Counter<u64>,
+    misses: Counter<u64>,
+    evictions: Counter<u64>,
+}
+
+impl CachingTodoRepository {
+    pub fn new(
+        inner: Arc<dyn TodoRepository>,
+        capacity: usize,
+        ttl: Duration,
+    ) -> Arc<CachingTodoRepository> {
+        let meter = global::meter("todo-repository-cache");
+
+        let hits = meter
+            .u64_counter("todo_repository.cache.hits")
+            .with_description("ToDo Repository Cache Hits")
+            .init();
+
+        let misses = meter
+            .u64_counter("todo_repository.cache.misses")
+            .with_description("ToDo Repository Cache Misses")
+            .init();
+
+        let evictions = meter
+            .u64_counter("todo_repository.cache.evictions")
+            .with_description("ToDo Repository Cache Evictions")
+            .init();
+
+        let cache = Arc::new(Mutex::new(LruCache::new(
+            NonZeroUsize::new(capacity.max(1)).unwrap(),
+        )));
+
+        let size = meter
+            .u64_observable_gauge("todo_repository.cache.size")
+            .with_description("ToDo Repository Cache Entries")
+            .init();
+
+        let observed = cache.clone();
+        if let Err(err) = meter.register_callback(move |ctx: &Context| {
+            let len = observed.lock().unwrap().len();
+            size.observe(ctx, len as u64, &[]);
+        }) {
+            error!(
+                error = err.to_string(),
+                "error to register cache size gauge"
+            );
+        }
+
+        Arc::new(CachingTodoRepository {
+            inner,
+            cache,
+            ttl,
+            hits,
+            misses,
+            evictions,
+        })
+    }
+
+    pub fn invalidate(&self, id: &str) {
+        self.cache.lock().unwrap().pop(id);
+    }
+
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn cached(&self, ctx: &Context, id: &str) -> Option<Todo> {
+        let mut cache = self.cache.lock().unwrap();
+
+        match cache.get(id) {
+            None => return None,
+            Some(entry) if entry.cached_at.elapsed() < self.ttl => return Some(entry.todo.clone()),
+            Some(_) => {}
+        };
+
+        cache.pop(id);
+        self.evictions
+            .add(ctx, 1, &[KeyValue::new("reason", "expired")]);
+
+        None
+    }
+
+    fn store(&self, ctx: &Context, todo: &Todo) {
+        let evicted = self.cache.lock().unwrap().push(
+            todo.id.clone(),
+            CacheEntry {
+                todo: todo.clone(),
+                cached_at: Instant::now(),
+            },
+        );
+
+        // push also hands back the previous entry of the same key, that is not an eviction
+        if matches!(evicted, Some((key, _)) if key != todo.id) {
+            self.evictions
+                .add(ctx, 1, &[KeyValue::new("reason", "capacity")]);
+        }
+    }
+}
+
+#[async_trait]
+impl TodoRepository for CachingTodoRepository {
+    async fn create(&self, ctx: &Context, todo: &CreateTodo) -> Result<Todo, String> {
+        self.inner.create(ctx, todo).await
+    }
+
+    async fn get_by_id(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        if let Some(todo) = self.cached(ctx, id) {
+            self.hits.add(ctx, 1, &[]);
+            return Ok(Some(todo));
+        }
+
+        self.misses.add(ctx, 1, &[]);
+
+        let todo = self.inner.get_by_id(ctx, id).await?;
+        if let Some(t) = &todo {
+            self.store(ctx, t);
+        }
+
+        Ok(todo)
+    }
+
+    async fn list_paginated(
+        &self,
+        ctx: &Context,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<Todo>, String> {
+        self.inner.list_paginated(ctx, limit, offset).await
+    }
+
+    async fn delete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let result = self.inner.delete(ctx, id).await;
+        self.invalidate(id);
+
+        result
+    }
+
+    async fn complete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let result = self.inner.complete(ctx, id).await;
+        self.invalidate(id);
+
+        result
+    }
+}
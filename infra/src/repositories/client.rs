@@ -0,0 +1,521 @@
+use bytes::{Bytes, BytesMut};
+use configs::PostgresConfigs;
+use deadpool_postgres::{
+    tokio_postgres::{
+        types::{to_sql_checked, IsNull, ToSql, Type},
+        Row,
+    },
+    Object, Pool, Transaction,
+};
+use opentelemetry::{
+    global::{self, BoxedTracer},
+    metrics::{Histogram, Unit},
+    trace::{SpanKind, Status, TraceContextExt, Tracer},
+    Context, KeyValue,
+};
+use postgres::Statement;
+use std::{
+    borrow::Cow,
+    env,
+    error::Error,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+
+const DB_SYSTEM: &str = "postgresql";
+const SLOW_QUERY_THRESHOLD_ENV_KEY: &str = "DB_SLOW_QUERY_THRESHOLD_MS";
+const SLOW_QUERY_EXPLAIN_ENV_KEY: &str = "DB_SLOW_QUERY_EXPLAIN";
+
+/// A SQL statement together with what OpenTelemetry needs to describe it.
+pub(crate) struct SqlStatement {
+    /// Stable name used to tell statements apart in metrics and logs, e.g. `todos.get_by_id`.
+    pub(crate) name: &'static str,
+    /// `db.operation`, e.g. `SELECT`.
+    pub(crate) operation: &'static str,
+    /// `db.sql.table`.
+    pub(crate) table: &'static str,
+    pub(crate) sql: &'static str,
+}
+
+/// Connection attributes shared by every span, following the database semantic conventions.
+struct ConnectionAttributes {
+    db_name: String,
+    peer_name: String,
+    peer_port: i64,
+}
+
+impl ConnectionAttributes {
+    fn new(cfg: &PostgresConfigs) -> ConnectionAttributes {
+        ConnectionAttributes {
+            db_name: cfg.db.clone(),
+            peer_name: cfg.host.clone(),
+            peer_port: cfg.port as i64,
+        }
+    }
+}
+
+/// Statements slower than `threshold` are logged, and explained off the request path when
+/// `explain` is enabled.
+#[derive(Debug, Clone)]
+pub struct SlowQueryConfigs {
+    pub threshold: Duration,
+    pub explain: bool,
+}
+
+impl SlowQueryConfigs {
+    pub fn from_env() -> SlowQueryConfigs {
+        SlowQueryConfigs {
+            threshold: Duration::from_millis(
+                env::var(SLOW_QUERY_THRESHOLD_ENV_KEY)
+                    .ok()
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(500),
+            ),
+            explain: env::var(SLOW_QUERY_EXPLAIN_ENV_KEY)
+                .ok()
+                .and_then(|e| e.parse().ok())
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Traced access to the connection pool shared by every repository.
+///
+/// Each statement gets a Client span named `<operation> <db>.<table>`, with child spans timing
+/// the connection checkout and the statement preparation, and its execution time is recorded
+/// in the `db.client.duration` histogram by statement name.
+pub(crate) struct PostgresClient {
+    tracer_name: &'static str,
+    tracer: BoxedTracer,
+    pool: Arc<Pool>,
+    connection: ConnectionAttributes,
+    slow_query: SlowQueryConfigs,
+    duration: Histogram<f64>,
+}
+
+impl PostgresClient {
+    pub(crate) fn new(
+        pool: Arc<Pool>,
+        cfg: &PostgresConfigs,
+        slow_query: &SlowQueryConfigs,
+        tracer_name: &'static str,
+    ) -> PostgresClient {
+        let tracer = global::tracer(tracer_name);
+
+        let duration = global::meter(tracer_name)
+            .f64_histogram("db.client.duration")
+            .with_description("Database Statement Execution Duration")
+            .with_unit(Unit::new("ms"))
+            .init();
+
+        PostgresClient {
+            tracer_name,
+            tracer,
+            pool,
+            connection: ConnectionAttributes::new(cfg),
+            slow_query: slow_query.clone(),
+            duration,
+        }
+    }
+
+    pub(crate) async fn query_one(
+        &self,
+        ctx: &Context,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, String> {
+        let ctx = self.span(ctx, stmt);
+
+        let conn = self.get_conn(&ctx).await?;
+        let statement = self.statement(&ctx, &conn, stmt).await?;
+
+        let started_at = Instant::now();
+        let result = match conn.query_opt(&statement, params).await {
+            Err(err) => Err(self.failed(&ctx, &err)),
+            Ok(r) => {
+                self.rows(&ctx, r.is_some() as i64);
+                Ok(r)
+            }
+        };
+
+        self.observe(
+            &ctx,
+            &statement,
+            stmt,
+            params,
+            started_at.elapsed(),
+            result.is_ok(),
+        );
+        ctx.span().end();
+        result
+    }
+
+    pub(crate) async fn query(
+        &self,
+        ctx: &Context,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, String> {
+        let ctx = self.span(ctx, stmt);
+
+        let conn = self.get_conn(&ctx).await?;
+        let statement = self.statement(&ctx, &conn, stmt).await?;
+
+        let started_at = Instant::now();
+        let result = match conn.query(&statement, params).await {
+            Err(err) => Err(self.failed(&ctx, &err)),
+            Ok(r) => {
+                self.rows(&ctx, r.len() as i64);
+                Ok(r)
+            }
+        };
+
+        self.observe(
+            &ctx,
+            &statement,
+            stmt,
+            params,
+            started_at.elapsed(),
+            result.is_ok(),
+        );
+        ctx.span().end();
+        result
+    }
+
+    pub(crate) async fn execute(
+        &self,
+        ctx: &Context,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, String> {
+        let ctx = self.span(ctx, stmt);
+
+        let conn = self.get_conn(&ctx).await?;
+        let statement = self.statement(&ctx, &conn, stmt).await?;
+
+        let started_at = Instant::now();
+        let result = match conn.execute(&statement, params).await {
+            Err(err) => Err(self.failed(&ctx, &err)),
+            Ok(affected) => {
+                self.rows(&ctx, affected as i64);
+                Ok(affected)
+            }
+        };
+
+        self.observe(
+            &ctx,
+            &statement,
+            stmt,
+            params,
+            started_at.elapsed(),
+            result.is_ok(),
+        );
+        ctx.span().end();
+        result
+    }
+
+    /// Same as `execute`, inside a transaction owned by the caller. Slow statements are logged
+    /// but never explained, since that would run them again inside the caller's transaction.
+    pub(crate) async fn execute_in(
+        &self,
+        ctx: &Context,
+        tx: &Transaction<'_>,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, String> {
+        let ctx = self.span(ctx, stmt);
+
+        let statement = match tx.prepare_cached(stmt.sql).await {
+            Err(err) => {
+                let err = self.failed(&ctx, &err);
+                ctx.span().end();
+                return Err(err);
+            }
+            Ok(s) => s,
+        };
+
+        let started_at = Instant::now();
+        let result = match tx.execute(&statement, params).await {
+            Err(err) => Err(self.failed(&ctx, &err)),
+            Ok(affected) => {
+                self.rows(&ctx, affected as i64);
+                Ok(affected)
+            }
+        };
+
+        self.record(&ctx, stmt, started_at.elapsed());
+        ctx.span().end();
+        result
+    }
+
+    /// Starts the statement span and returns a context carrying it.
+    fn span(&self, ctx: &Context, stmt: &SqlStatement) -> Context {
+        let span = self
+            .tracer
+            .span_builder(format!(
+                "{} {}.{}",
+                stmt.operation, self.connection.db_name, stmt.table
+            ))
+            .with_kind(SpanKind::Client)
+            .with_attributes(vec![
+                KeyValue::new("db.system", DB_SYSTEM),
+                KeyValue::new("db.name", self.connection.db_name.clone()),
+                KeyValue::new("db.operation", stmt.operation),
+                KeyValue::new("db.sql.table", stmt.table),
+                KeyValue::new("db.statement", stmt.sql),
+                KeyValue::new("net.peer.name", self.connection.peer_name.clone()),
+                KeyValue::new("net.peer.port", self.connection.peer_port),
+            ])
+            .start_with_context(&self.tracer, ctx);
+
+        ctx.with_span(span)
+    }
+
+    /// Records the execution time and logs slow statements. The plan of slow statements that
+    /// succeeded is captured in the background when enabled, so nothing beyond the histogram runs
+    /// on the request path.
+    fn observe(
+        &self,
+        ctx: &Context,
+        statement: &Statement,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+        elapsed: Duration,
+        succeeded: bool,
+    ) {
+        if self.record(ctx, stmt, elapsed) && succeeded && self.slow_query.explain {
+            self.explain(ctx, statement, stmt, params);
+        }
+    }
+
+    /// Records the execution time and logs slow statements, returning whether it was slow.
+    fn record(&self, ctx: &Context, stmt: &SqlStatement, elapsed: Duration) -> bool {
+        let millis = elapsed.as_secs_f64() * 1000.0;
+
+        self.duration.record(
+            ctx,
+            millis,
+            &[
+                KeyValue::new("db.system", DB_SYSTEM),
+                KeyValue::new("db.operation", stmt.operation),
+                KeyValue::new("db.sql.table", stmt.table),
+                KeyValue::new("db.query.name", stmt.name),
+            ],
+        );
+
+        if elapsed < self.slow_query.threshold {
+            return false;
+        }
+
+        warn!(
+            query = stmt.name,
+            duration_ms = millis,
+            trace_id = ctx.span().span_context().trace_id().to_string(),
+            "slow query"
+        );
+
+        true
+    }
+
+    /// Spawns the `EXPLAIN (ANALYZE, BUFFERS)` of a slow statement on its own connection. The
+    /// params are encoded up front, so the task does not borrow from the caller, and the plan is
+    /// attached to an `EXPLAIN <name>` span under the statement span.
+    fn explain(
+        &self,
+        ctx: &Context,
+        statement: &Statement,
+        stmt: &SqlStatement,
+        params: &[&(dyn ToSql + Sync)],
+    ) {
+        let mut encoded = Vec::with_capacity(params.len());
+        for (param, ty) in params.iter().zip(statement.params()) {
+            let mut buf = BytesMut::new();
+            match param.to_sql_checked(ty, &mut buf) {
+                Err(err) => {
+                    warn!(error = err.to_string(), "error to explain slow query");
+                    return;
+                }
+                Ok(IsNull::Yes) => encoded.push(EncodedParam(None)),
+                Ok(IsNull::No) => encoded.push(EncodedParam(Some(buf.freeze()))),
+            }
+        }
+
+        let tracer = global::tracer(self.tracer_name);
+        let span = tracer
+            .span_builder(format!("EXPLAIN {}", stmt.name))
+            .with_kind(SpanKind::Client)
+            .start_with_context(&tracer, ctx);
+        let ctx = ctx.with_span(span);
+
+        let pool = self.pool.clone();
+        let name = stmt.name;
+        let sql = stmt.sql;
+
+        tokio::spawn(async move {
+            explain_plan(&ctx, &pool, name, sql, &encoded).await;
+            ctx.span().end();
+        });
+    }
+
+    fn rows(&self, ctx: &Context, rows: i64) {
+        ctx.span()
+            .set_attribute(KeyValue::new("db.rows_affected", rows));
+    }
+
+    fn failed(&self, ctx: &Context, err: &postgres::Error) -> String {
+        let span = ctx.span();
+        span.record_error(err);
+        span.set_status(Status::Error {
+            description: Cow::from("error to execute query"),
+        });
+
+        error!(error = err.to_string(), "error to execute query");
+        String::from("error to execute query")
+    }
+
+    async fn get_conn(&self, ctx: &Context) -> Result<Object, String> {
+        let checkout = self
+            .tracer
+            .span_builder("connection checkout")
+            .with_kind(SpanKind::Internal)
+            .start_with_context(&self.tracer, ctx);
+        let checkout = ctx.with_span(checkout);
+
+        let result = match self.pool.get().await {
+            Err(err) => {
+                for span in [checkout.span(), ctx.span()] {
+                    span.record_error(&err);
+                    span.set_status(Status::Error {
+                        description: Cow::from("error to get connection from poll"),
+                    });
+                }
+                ctx.span().end();
+
+                error!(error = err.to_string(), "error to get connection from poll");
+                Err(String::from("error to get connection from poll"))
+            }
+            Ok(c) => Ok(c),
+        };
+
+        checkout.span().end();
+        result
+    }
+
+    async fn statement(
+        &self,
+        ctx: &Context,
+        conn: &Object,
+        stmt: &SqlStatement,
+    ) -> Result<Statement, String> {
+        let prepare = self
+            .tracer
+            .span_builder("prepare")
+            .with_kind(SpanKind::Internal)
+            .start_with_context(&self.tracer, ctx);
+        let prepare = ctx.with_span(prepare);
+
+        let result = match conn.prepare_cached(stmt.sql).await {
+            Err(err) => {
+                for span in [prepare.span(), ctx.span()] {
+                    span.record_error(&err);
+                    span.set_status(Status::Error {
+                        description: Cow::from("error to prepare statement"),
+                    });
+                }
+                ctx.span().end();
+
+                error!(error = err.to_string(), "error to prepare statement");
+                Err(String::from("error to prepare statement"))
+            }
+            Ok(s) => Ok(s),
+        };
+
+        prepare.span().end();
+        result
+    }
+}
+
+/// A statement param already encoded in the binary format of the type it was bound to.
+#[derive(Debug)]
+struct EncodedParam(Option<Bytes>);
+
+impl ToSql for EncodedParam {
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        match &self.0 {
+            None => Ok(IsNull::Yes),
+            Some(bytes) => {
+                out.extend_from_slice(bytes);
+                Ok(IsNull::No)
+            }
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+/// Runs `EXPLAIN (ANALYZE, BUFFERS)` inside a transaction that is always rolled back, since
+/// ANALYZE executes the statement again.
+async fn explain_plan(
+    ctx: &Context,
+    pool: &Pool,
+    name: &'static str,
+    sql: &'static str,
+    params: &[EncodedParam],
+) {
+    let mut conn = match pool.get().await {
+        Err(err) => {
+            warn!(error = err.to_string(), "error to explain slow query");
+            return;
+        }
+        Ok(c) => c,
+    };
+
+    let tx = match conn.transaction().await {
+        Err(err) => {
+            warn!(error = err.to_string(), "error to explain slow query");
+            return;
+        }
+        Ok(tx) => tx,
+    };
+
+    let params = params
+        .iter()
+        .map(|p| p as &(dyn ToSql + Sync))
+        .collect::<Vec<&(dyn ToSql + Sync)>>();
+
+    let explain = format!("EXPLAIN (ANALYZE, BUFFERS) {}", sql);
+    match tx.query(explain.as_str(), &params).await {
+        Err(err) => warn!(error = err.to_string(), "error to explain slow query"),
+        Ok(rows) => {
+            let plan = rows
+                .iter()
+                .map(|r| r.get::<usize, String>(0))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            ctx.span().add_event(
+                "db.explain",
+                vec![
+                    KeyValue::new("db.query.name", name),
+                    KeyValue::new("db.explain.plan", plan),
+                ],
+            );
+        }
+    }
+
+    if let Err(err) = tx.rollback().await {
+        warn!(
+            error = err.to_string(),
+            "error to rollback slow query explain"
+        );
+    }
+}
@@ -1,3 +1,13 @@
+mod api_key;
+mod caching_todo;
+mod client;
+mod processed_message;
 mod todo;
+mod todo_stats;
 
+pub use api_key::ApiKeyRepositoryImpl;
+pub use caching_todo::CachingTodoRepository;
+pub use client::SlowQueryConfigs;
+pub use processed_message::ProcessedMessageRepository;
 pub use todo::TodoRepositoryImpl;
+pub use todo_stats::TodoStatsRepositoryImpl;
@@ -0,0 +1,71 @@
+use super::client::{PostgresClient, SlowQueryConfigs, SqlStatement};
+use configs::PostgresConfigs;
+use deadpool_postgres::{Pool, Transaction};
+use opentelemetry::Context;
+use std::{sync::Arc, time::Duration};
+
+const MARK_PROCESSED: SqlStatement = SqlStatement {
+    name: "processed_messages.mark",
+    operation: "INSERT",
+    table: "processed_messages",
+    sql: "INSERT INTO processed_messages (consumer, source, message_id, expires_at) \
+          VALUES ($1, $2, $3, NOW() + make_interval(secs => $4)) \
+          ON CONFLICT (consumer, source, message_id) DO UPDATE \
+          SET processed_at = NOW(), expires_at = EXCLUDED.expires_at \
+          WHERE processed_messages.expires_at < NOW()",
+};
+
+const PURGE_EXPIRED: SqlStatement = SqlStatement {
+    name: "processed_messages.purge_expired",
+    operation: "DELETE",
+    table: "processed_messages",
+    sql: "DELETE FROM processed_messages WHERE expires_at < NOW()",
+};
+
+/// Messages already handled by each consumer, identified by their CloudEvents `source` and `id`,
+/// remembered for a TTL.
+pub struct ProcessedMessageRepository {
+    client: PostgresClient,
+}
+
+impl ProcessedMessageRepository {
+    pub fn new(
+        pool: Arc<Pool>,
+        cfg: &PostgresConfigs,
+        slow_query: &SlowQueryConfigs,
+    ) -> Arc<ProcessedMessageRepository> {
+        let client = PostgresClient::new(pool, cfg, slow_query, "processed-message-repository");
+
+        Arc::new(ProcessedMessageRepository { client })
+    }
+
+    /// Records the message as processed by `consumer` inside `tx`, returning false when it
+    /// already was and the record has not expired yet. A concurrent delivery of the same message
+    /// waits on the row lock until `tx` finishes.
+    pub async fn mark(
+        &self,
+        ctx: &Context,
+        tx: &Transaction<'_>,
+        consumer: &str,
+        source: &str,
+        message_id: &str,
+        ttl: Duration,
+    ) -> Result<bool, String> {
+        let affected = self
+            .client
+            .execute_in(
+                ctx,
+                tx,
+                &MARK_PROCESSED,
+                &[&consumer, &source, &message_id, &ttl.as_secs_f64()],
+            )
+            .await?;
+
+        Ok(affected > 0)
+    }
+
+    /// Forgets every message whose TTL is over, returning how many.
+    pub async fn purge_expired(&self, ctx: &Context) -> Result<u64, String> {
+        self.client.execute(ctx, &PURGE_EXPIRED, &[]).await
+    }
+}
@@ -1,77 +1,90 @@
+use super::client::{PostgresClient, SlowQueryConfigs, SqlStatement};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use deadpool_postgres::{
-    tokio_postgres::{types::ToSql, Row},
-    Object, Pool,
-};
-use opentelemetry::{
-    global::{self, BoxedSpan, BoxedTracer},
-    trace::{Span, Status, Tracer},
-    Context, KeyValue,
-};
-use postgres::Statement;
+use configs::PostgresConfigs;
+use deadpool_postgres::{tokio_postgres::Row, Pool};
+use opentelemetry::Context;
 use shared::{
     models::todo::{CreateTodo, Todo},
     repositories::TodoRepository,
 };
-use std::{borrow::Cow, sync::Arc};
+use std::sync::Arc;
 use tracing::error;
 use uuid::Uuid;
 
+const CREATE_TODO: SqlStatement = SqlStatement {
+    name: "todos.create",
+    operation: "INSERT",
+    table: "todos",
+    sql: "INSERT INTO todos (name, description, owner_id) values ($1, $2, $3) RETURNING *",
+};
+
+const GET_TODO_BY_ID: SqlStatement = SqlStatement {
+    name: "todos.get_by_id",
+    operation: "SELECT",
+    table: "todos",
+    sql: "SELECT * FROM todos WHERE id = $1 AND deleted_at IS NULL",
+};
+
+const LIST_TODOS: SqlStatement = SqlStatement {
+    name: "todos.list_paginated",
+    operation: "SELECT",
+    table: "todos",
+    sql: "SELECT * FROM todos WHERE deleted_at IS NULL ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+};
+
+const DELETE_TODO: SqlStatement = SqlStatement {
+    name: "todos.delete",
+    operation: "UPDATE",
+    table: "todos",
+    sql: "UPDATE todos SET deleted_at = NOW(), updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL RETURNING *",
+};
+
+const COMPLETE_TODO: SqlStatement = SqlStatement {
+    name: "todos.complete",
+    operation: "UPDATE",
+    table: "todos",
+    sql: "UPDATE todos SET completed_at = NOW(), updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL AND completed_at IS NULL RETURNING *",
+};
+
 pub struct TodoRepositoryImpl {
-    tracer: BoxedTracer,
-    pool: Arc<Pool>,
+    client: PostgresClient,
 }
 
 impl TodoRepositoryImpl {
-    pub fn new(pool: Arc<Pool>) -> Arc<TodoRepositoryImpl> {
-        let tracer = global::tracer("todo-repository");
-
-        Arc::new(TodoRepositoryImpl { tracer, pool })
+    pub fn new(
+        pool: Arc<Pool>,
+        cfg: &PostgresConfigs,
+        slow_query: &SlowQueryConfigs,
+    ) -> Arc<TodoRepositoryImpl> {
+        let client = PostgresClient::new(pool, cfg, slow_query, "todo-repository");
+
+        Arc::new(TodoRepositoryImpl { client })
     }
 }
 
 #[async_trait]
 impl TodoRepository for TodoRepositoryImpl {
     async fn create(&self, ctx: &Context, todo: &CreateTodo) -> Result<Todo, String> {
-        let query = "INSERT INTO todos (name, description) values ($1, $2) RETURNING *";
-
         let row = self
-            .query_one(ctx, query.to_owned(), &[&todo.name, &todo.description])
+            .client
+            .query_one(
+                ctx,
+                &CREATE_TODO,
+                &[&todo.name, &todo.description, &todo.owner_id],
+            )
             .await?
             .unwrap();
 
-        Ok(Todo {
-            id: row.get::<usize, Uuid>(0).to_string(),
-            name: row.get(1),
-            description: row.get(2),
-            created_at: row.get::<usize, DateTime<Utc>>(3).to_rfc3339(),
-            updated_at: row.get::<usize, DateTime<Utc>>(4).to_rfc3339(),
-            deleted_at: None,
-        })
+        Ok(todo_from_row(&row))
     }
 
     async fn get_by_id(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
-        let query = "SELECT * FROM todos WHERE id = $1 AND deleted_at IS NULL";
+        let uid = parse_uuid(id)?;
 
-        let uid: Uuid = match Uuid::parse_str(id) {
-            Err(err) => {
-                error!(error = err.to_string(), "invalid uuid");
-                Err(String::from("invalid uuid"))
-            }
-            Ok(u) => Ok(u),
-        }?;
-
-        match self.query_one(ctx, query.to_owned(), &[&uid]).await? {
+        match self.client.query_one(ctx, &GET_TODO_BY_ID, &[&uid]).await? {
             None => Ok(None),
-            Some(row) => Ok(Some(Todo {
-                id: row.get::<usize, Uuid>(0).to_string(),
-                name: row.get(1),
-                description: row.get(2),
-                created_at: row.get::<usize, DateTime<Utc>>(3).to_rfc3339(),
-                updated_at: row.get::<usize, DateTime<Utc>>(4).to_rfc3339(),
-                deleted_at: None,
-            })),
+            Some(row) => Ok(Some(todo_from_row(&row))),
         }
     }
 
@@ -81,151 +94,57 @@ impl TodoRepository for TodoRepositoryImpl {
         limit: i32,
         offset: i32,
     ) -> Result<Vec<Todo>, String> {
-        let query = "SELECT * FROM todos WHERE deleted_at IS NOT NULL OFFSET = $1 LIMIT = $2";
-
+        // LIMIT and OFFSET are BIGINT parameters
         let rows = self
-            .query(&ctx, query.to_owned(), &[&offset, &limit])
+            .client
+            .query(ctx, &LIST_TODOS, &[&(limit as i64), &(offset as i64)])
             .await?;
 
-        Ok(rows
-            .iter()
-            .map(|row| Todo {
-                id: row.get::<usize, Uuid>(0).to_string(),
-                name: row.get(1),
-                description: row.get(2),
-                created_at: row.get::<usize, DateTime<Utc>>(3).to_rfc3339(),
-                updated_at: row.get::<usize, DateTime<Utc>>(4).to_rfc3339(),
-                deleted_at: None,
-            })
-            .collect::<Vec<Todo>>())
+        Ok(rows.iter().map(todo_from_row).collect::<Vec<Todo>>())
     }
 
-    async fn delete(&self, ctx: &Context, id: &str) -> Result<(), String> {
-        let query = "UPDATE todos SET deleted_at = $1 WHERE id = $2";
-
-        let mut span = self.tracer.start_with_context("query_one", ctx);
-        span.set_attributes(vec![KeyValue::new("sql.query", query.clone())]);
-
-        let uid: Uuid = match Uuid::parse_str(id) {
-            Err(err) => {
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("invalid uuid"),
-                });
-
-                error!(error = err.to_string(), "invalid uuid");
-                Err(String::from("invalid uuid"))
-            }
-            Ok(u) => Ok(u),
-        }?;
-
-        let conn = self.get_conn(&mut span).await?;
-        let statement = self.statement(&conn, &query, &mut span).await?;
-
-        match conn.query(&statement, &[&"", &uid]).await {
-            Err(err) => {
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("error to execute query"),
-                });
-
-                error!(error = err.to_string(), "error to execute query");
-                Err(String::from("error to execute query"))
-            }
-            _ => Ok(()),
-        }
-    }
-}
+    async fn delete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let uid = parse_uuid(id)?;
 
-impl TodoRepositoryImpl {
-    async fn query_one(
-        &self,
-        ctx: &Context,
-        query: String,
-        params: &[&(dyn ToSql + Sync)],
-    ) -> Result<Option<Row>, String> {
-        let mut span = self.tracer.start_with_context("query_one", ctx);
-        span.set_attributes(vec![KeyValue::new("sql.query", query.clone())]);
-
-        let conn = self.get_conn(&mut span).await?;
-        let statement = self.statement(&conn, &query, &mut span).await?;
-
-        match conn.query_one(&statement, params).await {
-            Err(err) => {
-                if err.code().is_none() {
-                    return Ok(None);
-                }
-
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("error to execute query"),
-                });
-
-                error!(error = err.to_string(), "error to execute query");
-                Err(String::from("error to execute query"))
-            }
-            Ok(r) => Ok(Some(r)),
+        match self.client.query_one(ctx, &DELETE_TODO, &[&uid]).await? {
+            None => Ok(None),
+            Some(row) => Ok(Some(todo_from_row(&row))),
         }
     }
 
-    async fn query(
-        &self,
-        ctx: &Context,
-        query: String,
-        params: &[&(dyn ToSql + Sync)],
-    ) -> Result<Vec<Row>, String> {
-        let mut span = self.tracer.start_with_context("query", ctx);
-        span.set_attributes(vec![KeyValue::new("sql.query", query.clone())]);
-
-        let conn = self.get_conn(&mut span).await?;
-        let statement = self.statement(&conn, &query, &mut span).await?;
-
-        match conn.query(&statement, params).await {
-            Err(err) => {
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("error to execute query"),
-                });
-
-                error!(error = err.to_string(), "error to execute query");
-                Err(String::from("error to execute query"))
-            }
-            Ok(r) => Ok(r),
+    async fn complete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String> {
+        let uid = parse_uuid(id)?;
+
+        match self.client.query_one(ctx, &COMPLETE_TODO, &[&uid]).await? {
+            None => Ok(None),
+            Some(row) => Ok(Some(todo_from_row(&row))),
         }
     }
+}
 
-    async fn get_conn(&self, span: &mut BoxedSpan) -> Result<Object, String> {
-        match self.pool.get().await {
-            Err(err) => {
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("error to get connection from poll"),
-                });
-
-                error!(error = err.to_string(), "error to get connection from poll");
-                Err(String::from("error to get connection from poll"))
-            }
-            Ok(c) => Ok(c),
+fn parse_uuid(id: &str) -> Result<Uuid, String> {
+    match Uuid::parse_str(id) {
+        Err(err) => {
+            error!(error = err.to_string(), "invalid uuid");
+            Err(String::from("invalid uuid"))
         }
+        Ok(u) => Ok(u),
     }
+}
 
-    async fn statement(
-        &self,
-        conn: &Object,
-        query: &str,
-        span: &mut BoxedSpan,
-    ) -> Result<Statement, String> {
-        match conn.prepare(query).await {
-            Err(err) => {
-                span.record_error(&err);
-                span.set_status(Status::Error {
-                    description: Cow::from("error to prepare statement"),
-                });
-
-                error!(error = err.to_string(), "error to prepare statement");
-                Err(String::from("error to prepare statement"))
-            }
-            Ok(s) => Ok(s),
-        }
+fn todo_from_row(row: &Row) -> Todo {
+    Todo {
+        id: row.get::<usize, Uuid>(0).to_string(),
+        name: row.get(1),
+        description: row.get(2),
+        created_at: row.get::<usize, DateTime<Utc>>(3).to_rfc3339(),
+        updated_at: row.get::<usize, DateTime<Utc>>(4).to_rfc3339(),
+        deleted_at: row
+            .get::<usize, Option<DateTime<Utc>>>(5)
+            .map(|d| d.to_rfc3339()),
+        owner_id: row.get(6),
+        completed_at: row
+            .get::<usize, Option<DateTime<Utc>>>(7)
+            .map(|d| d.to_rfc3339()),
     }
 }
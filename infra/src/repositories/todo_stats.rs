@@ -0,0 +1,252 @@
+use super::client::{PostgresClient, SlowQueryConfigs, SqlStatement};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use configs::PostgresConfigs;
+use deadpool_postgres::{
+    tokio_postgres::{IsolationLevel, Row},
+    Pool, Transaction,
+};
+use opentelemetry::Context;
+use shared::{
+    models::todo_stats::{TodoChange, TodoStats},
+    repositories::TodoStatsRepository,
+};
+use std::sync::Arc;
+use tracing::error;
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+const RECORD_TODO_STATS: SqlStatement = SqlStatement {
+    name: "todo_stats.record",
+    operation: "INSERT",
+    table: "todo_stats",
+    sql: "INSERT INTO todo_stats (owner_id, day, created, deleted, completed) VALUES ($1, $2, $3, $4, $5) \
+          ON CONFLICT (owner_id, day) DO UPDATE \
+          SET created = todo_stats.created + EXCLUDED.created, \
+          deleted = todo_stats.deleted + EXCLUDED.deleted, \
+          completed = todo_stats.completed + EXCLUDED.completed",
+};
+
+const LIST_TODO_STATS_BY_OWNER: SqlStatement = SqlStatement {
+    name: "todo_stats.list_by_owner",
+    operation: "SELECT",
+    table: "todo_stats",
+    sql: "SELECT owner_id, day, created, deleted, completed FROM todo_stats \
+          WHERE owner_id = $1 AND day BETWEEN $2 AND $3 ORDER BY day",
+};
+
+const RECORD_TODO_STATS_CHANGE: SqlStatement = SqlStatement {
+    name: "todo_stats_changes.record",
+    operation: "INSERT",
+    table: "todo_stats_changes",
+    sql: "INSERT INTO todo_stats_changes (todo_id, change) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+};
+
+const LOCK_TODO_STATS: SqlStatement = SqlStatement {
+    name: "todo_stats.lock",
+    operation: "LOCK",
+    table: "todo_stats",
+    sql: "LOCK TABLE todo_stats, todo_stats_changes IN EXCLUSIVE MODE",
+};
+
+const RESET_TODO_STATS_CHANGES: SqlStatement = SqlStatement {
+    name: "todo_stats_changes.reset",
+    operation: "DELETE",
+    table: "todo_stats_changes",
+    sql: "DELETE FROM todo_stats_changes",
+};
+
+const RESET_TODO_STATS: SqlStatement = SqlStatement {
+    name: "todo_stats.reset",
+    operation: "DELETE",
+    table: "todo_stats",
+    sql: "DELETE FROM todo_stats",
+};
+
+const REBUILD_TODO_STATS_CHANGES: SqlStatement = SqlStatement {
+    name: "todo_stats_changes.rebuild",
+    operation: "INSERT",
+    table: "todo_stats_changes",
+    sql: "INSERT INTO todo_stats_changes (todo_id, change) \
+          SELECT id::text, 'created' FROM todos WHERE owner_id IS NOT NULL \
+          UNION ALL \
+          SELECT id::text, 'deleted' FROM todos WHERE owner_id IS NOT NULL AND deleted_at IS NOT NULL \
+          UNION ALL \
+          SELECT id::text, 'completed' FROM todos WHERE owner_id IS NOT NULL AND completed_at IS NOT NULL",
+};
+
+const REBUILD_TODO_STATS: SqlStatement = SqlStatement {
+    name: "todo_stats.rebuild",
+    operation: "INSERT",
+    table: "todo_stats",
+    sql: "INSERT INTO todo_stats (owner_id, day, created, deleted, completed) \
+          SELECT owner_id, day, SUM(created), SUM(deleted), SUM(completed) FROM ( \
+            SELECT owner_id, (created_at AT TIME ZONE 'UTC')::date AS day, 1 AS created, 0 AS deleted, 0 AS completed \
+            FROM todos WHERE owner_id IS NOT NULL \
+            UNION ALL \
+            SELECT owner_id, (deleted_at AT TIME ZONE 'UTC')::date AS day, 0 AS created, 1 AS deleted, 0 AS completed \
+            FROM todos WHERE owner_id IS NOT NULL AND deleted_at IS NOT NULL \
+            UNION ALL \
+            SELECT owner_id, (completed_at AT TIME ZONE 'UTC')::date AS day, 0 AS created, 0 AS deleted, 1 AS completed \
+            FROM todos WHERE owner_id IS NOT NULL AND completed_at IS NOT NULL \
+          ) changes GROUP BY owner_id, day",
+};
+
+/// Read model of `todo_stats`, kept up to date by the projection consumer.
+pub struct TodoStatsRepositoryImpl {
+    pool: Arc<Pool>,
+    client: PostgresClient,
+}
+
+impl TodoStatsRepositoryImpl {
+    pub fn new(
+        pool: Arc<Pool>,
+        cfg: &PostgresConfigs,
+        slow_query: &SlowQueryConfigs,
+    ) -> Arc<TodoStatsRepositoryImpl> {
+        let client = PostgresClient::new(pool.clone(), cfg, slow_query, "todo-stats-repository");
+
+        Arc::new(TodoStatsRepositoryImpl { pool, client })
+    }
+
+    /// Adds the counts of `delta` to its owner and day inside `tx`, unless `change` of the ToDo
+    /// was already counted, returning whether it was added.
+    pub async fn record(
+        &self,
+        ctx: &Context,
+        tx: &Transaction<'_>,
+        todo_id: &str,
+        change: TodoChange,
+        delta: &TodoStats,
+    ) -> Result<bool, String> {
+        let day = parse_day(&delta.day)?;
+
+        let recorded = self
+            .client
+            .execute_in(
+                ctx,
+                tx,
+                &RECORD_TODO_STATS_CHANGE,
+                &[&todo_id, &change.as_str()],
+            )
+            .await?;
+        if recorded == 0 {
+            return Ok(false);
+        }
+
+        self.client
+            .execute_in(
+                ctx,
+                tx,
+                &RECORD_TODO_STATS,
+                &[
+                    &delta.owner_id,
+                    &day,
+                    &delta.created,
+                    &delta.deleted,
+                    &delta.completed,
+                ],
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Recomputes `todo_stats` from `todos`, returning how many days were written. `todos` only
+    /// keeps the current deletion, so restored ToDo's are no longer counted as deleted.
+    ///
+    /// The changes counted are recorded in `todo_stats_changes` from the same snapshot, so the
+    /// projection consumer can keep running: events whose change the rebuild already counted are
+    /// skipped and the others are counted once they are consumed.
+    pub async fn rebuild(&self, ctx: &Context) -> Result<u64, String> {
+        let mut conn = match self.pool.get().await {
+            Err(err) => {
+                error!(error = err.to_string(), "error to get connection from poll");
+                Err(String::from("error to get connection from poll"))
+            }
+            Ok(c) => Ok(c),
+        }?;
+
+        // the snapshot is taken by the first query, after the lock waits for the projection
+        let tx = match conn
+            .build_transaction()
+            .isolation_level(IsolationLevel::RepeatableRead)
+            .start()
+            .await
+        {
+            Err(err) => {
+                error!(error = err.to_string(), "error to begin transaction");
+                Err(String::from("error to begin transaction"))
+            }
+            Ok(tx) => Ok(tx),
+        }?;
+
+        for statement in [
+            &LOCK_TODO_STATS,
+            &RESET_TODO_STATS_CHANGES,
+            &RESET_TODO_STATS,
+            &REBUILD_TODO_STATS_CHANGES,
+        ] {
+            self.client.execute_in(ctx, &tx, statement, &[]).await?;
+        }
+        let days = self
+            .client
+            .execute_in(ctx, &tx, &REBUILD_TODO_STATS, &[])
+            .await?;
+
+        match tx.commit().await {
+            Err(err) => {
+                error!(
+                    error = err.to_string(),
+                    "error to commit todo stats rebuild"
+                );
+                Err(String::from("error to commit todo stats rebuild"))
+            }
+            Ok(_) => Ok(days),
+        }
+    }
+}
+
+#[async_trait]
+impl TodoStatsRepository for TodoStatsRepositoryImpl {
+    async fn list_by_owner(
+        &self,
+        ctx: &Context,
+        owner_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<TodoStats>, String> {
+        let from = parse_day(from)?;
+        let to = parse_day(to)?;
+
+        let rows = self
+            .client
+            .query(ctx, &LIST_TODO_STATS_BY_OWNER, &[&owner_id, &from, &to])
+            .await?;
+
+        Ok(rows.iter().map(todo_stats_from_row).collect())
+    }
+}
+
+fn parse_day(day: &str) -> Result<NaiveDate, String> {
+    match NaiveDate::parse_from_str(day, DAY_FORMAT) {
+        Err(err) => {
+            error!(error = err.to_string(), "invalid day");
+            Err(String::from("invalid day"))
+        }
+        Ok(d) => Ok(d),
+    }
+}
+
+fn todo_stats_from_row(row: &Row) -> TodoStats {
+    TodoStats {
+        owner_id: row.get(0),
+        day: row
+            .get::<usize, NaiveDate>(1)
+            .format(DAY_FORMAT)
+            .to_string(),
+        created: row.get(2),
+        deleted: row.get(3),
+        completed: row.get(4),
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use tracing::error;
+
+/// Append-only file rotated by size: once `max_bytes` would be exceeded the current file is
+/// renamed to `<path>.1`, the older ones shift to `<path>.2`.. and anything past `max_files` is
+/// removed.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    /// Writes a whole line, rotating before it so a line never spans two files.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let size = line.len() as u64 + 1;
+        if self.written > 0 && self.written + size > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.file.flush()?;
+        self.written += size;
+
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+            self.written = 0;
+            return Ok(());
+        }
+
+        let _ = fs::remove_file(self.rotated(self.max_files));
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+enum Command {
+    Line(String),
+    Flush(Sender<()>),
+}
+
+/// Hands lines over to a dedicated thread owning the `RotatingFile`, so exporters running on the
+/// tokio runtime never wait on the disk. Lines are written in the order they were handed over.
+#[derive(Debug, Clone)]
+pub struct FileWriter {
+    sender: Sender<Command>,
+}
+
+impl FileWriter {
+    pub fn spawn(file: RotatingFile) -> io::Result<FileWriter> {
+        let (sender, receiver) = mpsc::channel();
+
+        let name = format!(
+            "telemetry-{}",
+            file.path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        thread::Builder::new()
+            .name(name)
+            .spawn(move || write_lines(file, receiver))?;
+
+        Ok(FileWriter { sender })
+    }
+
+    /// Queues a whole line, only failing when the writer thread is gone.
+    pub fn write_line(&self, line: String) -> io::Result<()> {
+        self.sender
+            .send(Command::Line(line))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "telemetry writer stopped"))
+    }
+
+    /// Blocks until every line queued so far is written.
+    pub fn flush(&self) {
+        let (ack, done) = mpsc::channel();
+        if self.sender.send(Command::Flush(ack)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+}
+
+fn write_lines(mut file: RotatingFile, receiver: Receiver<Command>) {
+    for command in receiver {
+        match command {
+            Command::Line(line) => {
+                if let Err(err) = file.write_line(&line) {
+                    error!(error = err.to_string(), "error to write telemetry file");
+                }
+            }
+            Command::Flush(ack) => {
+                let _ = ack.send(());
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::{
+    file::FileWriter,
+    otlp_json::{attributes, resource, scope, unix_nano},
+};
+use opentelemetry::{
+    metrics::{MetricsError, Result},
+    sdk::{
+        export::metrics::{
+            aggregation::{
+                cumulative_temporality_selector, AggregationKind, Count, Histogram, LastValue, Sum,
+                Temporality, TemporalitySelector,
+            },
+            InstrumentationLibraryReader, MetricsExporter, Record,
+        },
+        metrics::{
+            aggregators::{HistogramAggregator, LastValueAggregator, SumAggregator},
+            sdk_api::{Descriptor, InstrumentKind, Number, NumberKind},
+        },
+        Resource,
+    },
+    Context,
+};
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+
+/// OTLP `AGGREGATION_TEMPORALITY_CUMULATIVE`.
+const CUMULATIVE: u8 = 2;
+
+/// Writes every collection as one OTLP/JSON `ExportMetricsServiceRequest` per line.
+#[derive(Debug)]
+pub struct JsonLinesMetricsExporter {
+    file: FileWriter,
+}
+
+impl JsonLinesMetricsExporter {
+    pub fn new(file: FileWriter) -> JsonLinesMetricsExporter {
+        JsonLinesMetricsExporter { file }
+    }
+}
+
+impl TemporalitySelector for JsonLinesMetricsExporter {
+    fn temporality_for(&self, descriptor: &Descriptor, kind: &AggregationKind) -> Temporality {
+        cumulative_temporality_selector().temporality_for(descriptor, kind)
+    }
+}
+
+/// Data points of one metric, keyed by its name.
+struct MetricPoints {
+    description: String,
+    unit: String,
+    kind: &'static str,
+    monotonic: bool,
+    points: Vec<Json>,
+}
+
+impl MetricsExporter for JsonLinesMetricsExporter {
+    fn export(
+        &self,
+        _cx: &Context,
+        res: &Resource,
+        reader: &dyn InstrumentationLibraryReader,
+    ) -> Result<()> {
+        let mut scope_metrics = vec![];
+
+        reader.try_for_each(&mut |library, reader| {
+            let mut metrics: BTreeMap<String, MetricPoints> = BTreeMap::new();
+
+            reader.try_for_each(self, &mut |record| {
+                let desc = record.descriptor();
+                let Some((kind, point)) = data_point(record)? else {
+                    return Ok(());
+                };
+
+                metrics
+                    .entry(desc.name().to_owned())
+                    .or_insert_with(|| MetricPoints {
+                        description: desc.description().cloned().unwrap_or_default(),
+                        unit: desc.unit().unwrap_or_default().to_owned(),
+                        kind,
+                        monotonic: matches!(
+                            desc.instrument_kind(),
+                            InstrumentKind::Counter | InstrumentKind::CounterObserver
+                        ),
+                        points: vec![],
+                    })
+                    .points
+                    .push(point);
+
+                Ok(())
+            })?;
+
+            scope_metrics.push(json!({
+                "scope": scope(library),
+                "metrics": metrics.into_iter().map(|(name, m)| encode_metric(name, m)).collect::<Vec<Json>>(),
+            }));
+
+            Ok(())
+        })?;
+
+        let line = json!({
+            "resourceMetrics": [{
+                "resource": resource(res),
+                "scopeMetrics": scope_metrics,
+            }]
+        });
+
+        self.file
+            .write_line(line.to_string())
+            .map_err(|err| MetricsError::Other(err.to_string()))
+    }
+}
+
+fn encode_metric(name: String, m: MetricPoints) -> Json {
+    let data = match m.kind {
+        "sum" => json!({
+            "dataPoints": m.points,
+            "aggregationTemporality": CUMULATIVE,
+            "isMonotonic": m.monotonic,
+        }),
+        "histogram" => json!({
+            "dataPoints": m.points,
+            "aggregationTemporality": CUMULATIVE,
+        }),
+        _ => json!({ "dataPoints": m.points }),
+    };
+
+    json!({
+        "name": name,
+        "description": m.description,
+        "unit": m.unit,
+        m.kind: data,
+    })
+}
+
+/// Encodes the record according to its aggregator, returning the OTLP metric type with it.
+fn data_point(record: &Record<'_>) -> Result<Option<(&'static str, Json)>> {
+    let Some(agg) = record.aggregator() else {
+        return Ok(None);
+    };
+    let number_kind = record.descriptor().number_kind();
+
+    let mut point = json!({
+        "attributes": attributes(record.attributes().iter()),
+        "startTimeUnixNano": unix_nano(record.start_time()),
+        "timeUnixNano": unix_nano(record.end_time()),
+    });
+
+    let kind = if let Some(sum) = agg.as_any().downcast_ref::<SumAggregator>() {
+        set_number(&mut point, &sum.sum()?, number_kind);
+        "sum"
+    } else if let Some(last) = agg.as_any().downcast_ref::<LastValueAggregator>() {
+        let (value, _) = last.last_value()?;
+        set_number(&mut point, &value, number_kind);
+        "gauge"
+    } else if let Some(histogram) = agg.as_any().downcast_ref::<HistogramAggregator>() {
+        let buckets = histogram.histogram()?;
+        point["count"] = json!(histogram.count()?.to_string());
+        point["sum"] = json!(histogram.sum()?.to_f64(number_kind));
+        point["explicitBounds"] = json!(buckets.boundaries());
+        point["bucketCounts"] = json!(buckets
+            .counts()
+            .iter()
+            .map(|c| (*c as u64).to_string())
+            .collect::<Vec<String>>());
+        "histogram"
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some((kind, point)))
+}
+
+fn set_number(point: &mut Json, value: &Number, kind: &NumberKind) {
+    match kind {
+        NumberKind::F64 => point["asDouble"] = json!(value.to_f64(kind)),
+        _ => point["asInt"] = json!(value.to_i64(kind).to_string()),
+    }
+}
@@ -0,0 +1,274 @@
+//! Exporters used when there is no OTLP collector to talk to, e.g. running locally or in
+//! air-gapped environments. `TELEMETRY_EXPORTER` selects between:
+//!
+//! - `otlp` (default): the regular OTLP pipelines, traces configured by the binaries themselves
+//!   and metrics exported to `OTLP_HOST` unless `ENABLE_METRICS` is false;
+//! - `stdout`: traces and metrics pretty printed to stdout;
+//! - `file`: OTLP/JSON lines written to rotating files under `TELEMETRY_FILE_DIR`, which can
+//!   later be uploaded with `telemetry_replay`;
+//! - `none`: nothing is exported.
+mod file;
+mod metrics;
+mod otlp_json;
+mod traces;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use file::{FileWriter, RotatingFile};
+pub use metrics::JsonLinesMetricsExporter;
+pub use traces::JsonLinesSpanExporter;
+
+use opentelemetry::{
+    global, runtime,
+    sdk::{
+        export::{
+            metrics::{aggregation::cumulative_temporality_selector, stdout, AggregatorSelector},
+            trace::stdout::Exporter as StdoutSpanExporter,
+        },
+        metrics::{
+            controllers::{self, BasicController},
+            processors, selectors,
+        },
+        propagation::TraceContextPropagator,
+        trace::{self, TracerProvider},
+        Resource,
+    },
+    Context, KeyValue,
+};
+use opentelemetry_otlp::{Protocol, TonicExporterBuilder, WithExportConfig};
+use std::{env, error::Error, io, path::PathBuf, str::FromStr, sync::Mutex, time::Duration};
+use tonic::metadata::MetadataMap;
+use tracing::error;
+
+const EXPORTER_ENV_KEY: &str = "TELEMETRY_EXPORTER";
+const FILE_DIR_ENV_KEY: &str = "TELEMETRY_FILE_DIR";
+const FILE_MAX_BYTES_ENV_KEY: &str = "TELEMETRY_FILE_MAX_BYTES";
+const FILE_MAX_FILES_ENV_KEY: &str = "TELEMETRY_FILE_MAX_FILES";
+const ENABLE_METRICS_ENV_KEY: &str = "ENABLE_METRICS";
+const OTLP_HOST_ENV_KEY: &str = "OTLP_HOST";
+const OTLP_KEY_ENV_KEY: &str = "OTLP_KEY";
+const OTLP_EXPORT_TIMEOUT_ENV_KEY: &str = "OTLP_EXPORT_TIMEOUT";
+const METRICS_COLLECT_PERIOD: Duration = Duration::from_secs(10);
+
+/// Meter controller installed by `setup`, kept to export its last metrics on `shutdown`.
+static METER_CONTROLLER: Mutex<Option<BasicController>> = Mutex::new(None);
+
+/// File writers opened by `setup`, flushed on `shutdown` so the lines still queued are written.
+static FILE_WRITERS: Mutex<Vec<FileWriter>> = Mutex::new(Vec::new());
+
+/// Histogram buckets, in milliseconds, for binaries without a selector of their own.
+pub const DEFAULT_DURATION_BUCKETS: [f64; 11] = [
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelemetryExporter {
+    None,
+    Otlp,
+    Stdout,
+    File,
+}
+
+impl FromStr for TelemetryExporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(TelemetryExporter::None),
+            "otlp" => Ok(TelemetryExporter::Otlp),
+            "stdout" => Ok(TelemetryExporter::Stdout),
+            "file" => Ok(TelemetryExporter::File),
+            other => Err(format!("unknown telemetry exporter: {}", other)),
+        }
+    }
+}
+
+impl TelemetryExporter {
+    pub fn from_env() -> Result<TelemetryExporter, String> {
+        match env::var(EXPORTER_ENV_KEY) {
+            Err(_) => Ok(TelemetryExporter::Otlp),
+            Ok(e) if e.is_empty() => Ok(TelemetryExporter::Otlp),
+            Ok(e) => e.parse(),
+        }
+    }
+}
+
+struct FileConfigs {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl FileConfigs {
+    fn from_env() -> FileConfigs {
+        FileConfigs {
+            dir: PathBuf::from(env::var(FILE_DIR_ENV_KEY).unwrap_or_else(|_| "telemetry".into())),
+            max_bytes: env::var(FILE_MAX_BYTES_ENV_KEY)
+                .ok()
+                .and_then(|b| b.parse().ok())
+                .unwrap_or(10 * 1024 * 1024),
+            max_files: env::var(FILE_MAX_FILES_ENV_KEY)
+                .ok()
+                .and_then(|f| f.parse().ok())
+                .unwrap_or(5),
+        }
+    }
+
+    fn open(&self, service_name: &str, signal: &str) -> io::Result<FileWriter> {
+        let writer = FileWriter::spawn(RotatingFile::open(
+            self.dir.join(format!("{}.{}.jsonl", service_name, signal)),
+            self.max_bytes,
+            self.max_files,
+        )?)?;
+
+        if let Ok(mut writers) = FILE_WRITERS.lock() {
+            writers.push(writer.clone());
+        }
+
+        Ok(writer)
+    }
+}
+
+/// Installs the global tracer and meter providers. With `TelemetryExporter::Otlp` only the meter
+/// provider is installed, the tracer is left to the binaries' own OTLP setup.
+pub fn setup(
+    exporter: TelemetryExporter,
+    service_name: &str,
+    selector: impl AggregatorSelector + Send + Sync + 'static,
+) -> Result<(), Box<dyn Error>> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let resource = Resource::new(vec![KeyValue::new("service.name", service_name.to_owned())]);
+    let trace_config = trace::config().with_resource(resource.clone());
+
+    let (tracer_provider, controller) = match exporter {
+        TelemetryExporter::None => return Ok(()),
+        TelemetryExporter::Otlp => {
+            if env::var(ENABLE_METRICS_ENV_KEY).as_deref() == Ok("false") {
+                return Ok(());
+            }
+
+            // built here rather than by the ruskit setup, which hands its controller over to the
+            // global provider where it can no longer be stopped on shutdown
+            let controller = opentelemetry_otlp::new_pipeline()
+                .metrics(selector, cumulative_temporality_selector(), runtime::Tokio)
+                .with_exporter(otlp_exporter()?)
+                .with_resource(resource)
+                .with_period(METRICS_COLLECT_PERIOD)
+                .build()?;
+
+            if let Ok(mut installed) = METER_CONTROLLER.lock() {
+                *installed = Some(controller);
+            }
+
+            return Ok(());
+        }
+        TelemetryExporter::Stdout => {
+            let metrics_exporter = stdout().build()?;
+
+            (
+                TracerProvider::builder()
+                    .with_config(trace_config)
+                    .with_simple_exporter(StdoutSpanExporter::new(io::stdout(), true))
+                    .build(),
+                controllers::basic(processors::factory(
+                    selector,
+                    metrics_exporter.temporality_selector(),
+                ))
+                .with_exporter(metrics_exporter),
+            )
+        }
+        TelemetryExporter::File => {
+            let files = FileConfigs::from_env();
+
+            (
+                TracerProvider::builder()
+                    .with_config(trace_config)
+                    .with_batch_exporter(
+                        JsonLinesSpanExporter::new(files.open(service_name, "traces")?),
+                        runtime::Tokio,
+                    )
+                    .build(),
+                controllers::basic(processors::factory(
+                    selector,
+                    cumulative_temporality_selector(),
+                ))
+                .with_exporter(JsonLinesMetricsExporter::new(
+                    files.open(service_name, "metrics")?,
+                )),
+            )
+        }
+    };
+
+    global::set_tracer_provider(tracer_provider);
+
+    let controller = controller
+        .with_resource(resource)
+        .with_collect_period(METRICS_COLLECT_PERIOD)
+        .build();
+    controller.start(&Context::current(), runtime::Tokio)?;
+    global::set_meter_provider(controller.clone());
+
+    if let Ok(mut installed) = METER_CONTROLLER.lock() {
+        *installed = Some(controller);
+    }
+
+    Ok(())
+}
+
+/// OTLP/gRPC exporter to `OTLP_HOST`, authenticated with the `OTLP_KEY` api-key like the ruskit
+/// pipelines.
+fn otlp_exporter() -> Result<TonicExporterBuilder, Box<dyn Error>> {
+    let mut metadata = MetadataMap::new();
+    metadata.insert(
+        "api-key",
+        env::var(OTLP_KEY_ENV_KEY).unwrap_or_default().parse()?,
+    );
+
+    Ok(opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(env::var(OTLP_HOST_ENV_KEY).unwrap_or_default())
+        .with_protocol(Protocol::Grpc)
+        .with_timeout(Duration::from_secs(
+            env::var(OTLP_EXPORT_TIMEOUT_ENV_KEY)
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .unwrap_or(10),
+        ))
+        .with_metadata(metadata))
+}
+
+/// Exports the spans and metrics still buffered and shuts the providers down, right before the
+/// binary exits.
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+
+    let controller = match METER_CONTROLLER.lock() {
+        Ok(mut installed) => installed.take(),
+        Err(_) => None,
+    };
+
+    if let Some(controller) = controller {
+        if let Err(err) = controller.stop(&Context::current()) {
+            error!(
+                error = err.to_string(),
+                "error to stop the meter controller"
+            );
+        }
+    }
+
+    let writers: Vec<FileWriter> = match FILE_WRITERS.lock() {
+        Ok(mut writers) => writers.drain(..).collect(),
+        Err(_) => vec![],
+    };
+
+    for writer in writers {
+        writer.flush();
+    }
+}
+
+/// Aggregator selector using `DEFAULT_DURATION_BUCKETS` for every histogram.
+pub fn default_selector() -> impl AggregatorSelector + Send + Sync + 'static {
+    selectors::simple::histogram(DEFAULT_DURATION_BUCKETS)
+}
@@ -0,0 +1,57 @@
+//! Helpers encoding SDK data with the OTLP/JSON field names, so the files written here can be
+//! posted as-is to any OTLP/HTTP endpoint.
+use opentelemetry::{sdk::Resource, Array, InstrumentationLibrary, Key, Value};
+use serde_json::{json, Value as Json};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) fn any_value(value: &Value) -> Json {
+    match value {
+        Value::Bool(b) => json!({ "boolValue": b }),
+        // OTLP/JSON encodes 64 bits integers as strings
+        Value::I64(i) => json!({ "intValue": i.to_string() }),
+        Value::F64(f) => json!({ "doubleValue": f }),
+        Value::String(s) => json!({ "stringValue": s.as_str() }),
+        Value::Array(array) => {
+            let values: Vec<Json> = match array {
+                Array::Bool(v) => v.iter().map(|b| json!({ "boolValue": b })).collect(),
+                Array::I64(v) => v
+                    .iter()
+                    .map(|i| json!({ "intValue": i.to_string() }))
+                    .collect(),
+                Array::F64(v) => v.iter().map(|f| json!({ "doubleValue": f })).collect(),
+                Array::String(v) => v
+                    .iter()
+                    .map(|s| json!({ "stringValue": s.as_str() }))
+                    .collect(),
+            };
+            json!({ "arrayValue": { "values": values } })
+        }
+    }
+}
+
+pub(crate) fn attributes<'a>(attributes: impl IntoIterator<Item = (&'a Key, &'a Value)>) -> Json {
+    Json::Array(
+        attributes
+            .into_iter()
+            .map(|(k, v)| json!({ "key": k.as_str(), "value": any_value(v) }))
+            .collect(),
+    )
+}
+
+pub(crate) fn resource(resource: &Resource) -> Json {
+    json!({ "attributes": attributes(resource.iter()) })
+}
+
+pub(crate) fn scope(library: &InstrumentationLibrary) -> Json {
+    json!({
+        "name": library.name.as_ref(),
+        "version": library.version.as_deref().unwrap_or_default(),
+    })
+}
+
+pub(crate) fn unix_nano(time: &SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
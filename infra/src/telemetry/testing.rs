@@ -0,0 +1,183 @@
+//! In-memory telemetry for tests: spans are kept as soon as they end and metrics are collected on
+//! demand, so tests can assert trace topology and instrument values without any collector.
+use super::default_selector;
+use opentelemetry::{
+    global,
+    metrics::Result as MetricsResult,
+    sdk::{
+        export::{
+            metrics::{
+                aggregation::{cumulative_temporality_selector, Count, Sum},
+                InstrumentationLibraryReader, Record,
+            },
+            trace::SpanData,
+        },
+        metrics::{
+            aggregators::{HistogramAggregator, SumAggregator},
+            controllers::{self, BasicController},
+            processors,
+        },
+        propagation::TraceContextPropagator,
+        trace::{Span, SpanProcessor, TracerProvider},
+    },
+    trace::TraceResult,
+    Context,
+};
+use std::{
+    any::Any,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// Telemetry providers are process wide, so tests using the harness run one at a time.
+static INSTALLED: Mutex<()> = Mutex::new(());
+
+/// Keeps every ended span, synchronously, unlike the SDK simple processor which exports from a
+/// background thread.
+#[derive(Debug, Clone, Default)]
+struct InMemorySpanProcessor {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+}
+
+impl SpanProcessor for InMemorySpanProcessor {
+    fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+    fn on_end(&self, span: SpanData) {
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.push(span);
+        }
+    }
+
+    fn force_flush(&self) -> TraceResult<()> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> TraceResult<()> {
+        Ok(())
+    }
+}
+
+/// Installs in-memory tracer and meter providers, along with the TraceContext propagator, as
+/// the global ones. Components must be built after `install` since they take their tracers and
+/// meters from the globals when created.
+pub struct TelemetryHarness {
+    spans: Arc<Mutex<Vec<SpanData>>>,
+    controller: BasicController,
+    _installed: MutexGuard<'static, ()>,
+}
+
+impl TelemetryHarness {
+    pub fn install() -> TelemetryHarness {
+        let installed = INSTALLED.lock().unwrap_or_else(|err| err.into_inner());
+
+        let processor = InMemorySpanProcessor::default();
+        let spans = processor.spans.clone();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        global::set_tracer_provider(
+            TracerProvider::builder()
+                .with_span_processor(processor)
+                .build(),
+        );
+
+        let controller = controllers::basic(processors::factory(
+            default_selector(),
+            cumulative_temporality_selector(),
+        ))
+        .with_collect_period(Duration::ZERO)
+        .build();
+        global::set_meter_provider(controller.clone());
+
+        TelemetryHarness {
+            spans,
+            controller,
+            _installed: installed,
+        }
+    }
+
+    /// Every span ended so far, in the order they ended.
+    pub fn spans(&self) -> Vec<SpanData> {
+        self.spans
+            .lock()
+            .map(|spans| spans.clone())
+            .unwrap_or_default()
+    }
+
+    /// The first ended span with the given name.
+    pub fn span(&self, name: &str) -> Option<SpanData> {
+        self.spans().into_iter().find(|s| s.name == name)
+    }
+
+    /// Sum of a counter across all its attribute sets, `None` if it was never recorded.
+    pub fn counter(&self, name: &str) -> Option<f64> {
+        let mut total = None;
+
+        self.read(name, &mut |record, agg| {
+            if let Some(sum) = agg.downcast_ref::<SumAggregator>() {
+                *total.get_or_insert(0.0) += sum.sum()?.to_f64(record.descriptor().number_kind());
+            }
+            Ok(())
+        });
+
+        total
+    }
+
+    /// Sum of a counter across the attribute sets where `key` is `value`, `None` if it was never
+    /// recorded with them.
+    pub fn counter_where(&self, name: &str, key: &str, value: &str) -> Option<f64> {
+        let mut total = None;
+
+        self.read(name, &mut |record, agg| {
+            let matches = record
+                .attributes()
+                .iter()
+                .any(|(k, v)| k.as_str() == key && v.as_str() == value);
+
+            match agg.downcast_ref::<SumAggregator>() {
+                Some(sum) if matches => {
+                    *total.get_or_insert(0.0) +=
+                        sum.sum()?.to_f64(record.descriptor().number_kind());
+                }
+                _ => {}
+            }
+            Ok(())
+        });
+
+        total
+    }
+
+    /// Number of values recorded by a histogram across all its attribute sets.
+    pub fn histogram_count(&self, name: &str) -> Option<u64> {
+        let mut total = None;
+
+        self.read(name, &mut |_, agg| {
+            if let Some(histogram) = agg.downcast_ref::<HistogramAggregator>() {
+                *total.get_or_insert(0) += histogram.count()?;
+            }
+            Ok(())
+        });
+
+        total
+    }
+
+    fn read(&self, name: &str, f: &mut dyn FnMut(&Record<'_>, &dyn Any) -> MetricsResult<()>) {
+        let cx = Context::current();
+        if let Err(err) = self.controller.collect(&cx) {
+            panic!("error to collect metrics: {}", err);
+        }
+
+        let result = self.controller.try_for_each(&mut |_, reader| {
+            reader.try_for_each(
+                &cumulative_temporality_selector(),
+                &mut |record| match record.aggregator() {
+                    Some(agg) if record.descriptor().name() == name => f(record, agg.as_any()),
+                    _ => Ok(()),
+                },
+            )
+        });
+
+        if let Err(err) = result {
+            panic!("error to read metrics: {}", err);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::{
+    file::FileWriter,
+    otlp_json::{attributes, resource, scope, unix_nano},
+};
+use futures_util::future::BoxFuture;
+use opentelemetry::{
+    sdk::export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::{SpanId, SpanKind, Status, TraceError},
+};
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+
+/// Writes every exported batch as one OTLP/JSON `ExportTraceServiceRequest` per line.
+#[derive(Debug)]
+pub struct JsonLinesSpanExporter {
+    file: FileWriter,
+}
+
+impl JsonLinesSpanExporter {
+    pub fn new(file: FileWriter) -> JsonLinesSpanExporter {
+        JsonLinesSpanExporter { file }
+    }
+}
+
+impl SpanExporter for JsonLinesSpanExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = match batch.first() {
+            None => Ok(()),
+            Some(first) => {
+                let line = json!({
+                    "resourceSpans": [{
+                        "resource": resource(&first.resource),
+                        "scopeSpans": scope_spans(&batch),
+                    }]
+                });
+
+                self.file
+                    .write_line(line.to_string())
+                    .map_err(|err| TraceError::from(err.to_string()))
+            }
+        };
+
+        Box::pin(std::future::ready(result))
+    }
+
+    fn shutdown(&mut self) {
+        self.file.flush();
+    }
+}
+
+/// Spans of a process share the same resource, so they are only grouped by scope.
+fn scope_spans(batch: &[SpanData]) -> Json {
+    let mut scopes: BTreeMap<&str, (Json, Vec<Json>)> = BTreeMap::new();
+
+    for span in batch {
+        scopes
+            .entry(span.instrumentation_lib.name.as_ref())
+            .or_insert_with(|| (scope(&span.instrumentation_lib), vec![]))
+            .1
+            .push(encode_span(span));
+    }
+
+    Json::Array(
+        scopes
+            .into_values()
+            .map(|(scope, spans)| json!({ "scope": scope, "spans": spans }))
+            .collect(),
+    )
+}
+
+fn encode_span(span: &SpanData) -> Json {
+    let (code, message) = match &span.status {
+        Status::Unset => (0, String::new()),
+        Status::Ok => (1, String::new()),
+        Status::Error { description } => (2, description.to_string()),
+    };
+
+    let parent = match span.parent_span_id {
+        SpanId::INVALID => String::new(),
+        id => format!("{:016x}", id),
+    };
+
+    json!({
+        "traceId": format!("{:032x}", span.span_context.trace_id()),
+        "spanId": format!("{:016x}", span.span_context.span_id()),
+        "parentSpanId": parent,
+        "name": span.name.as_ref(),
+        "kind": kind(&span.span_kind),
+        "startTimeUnixNano": unix_nano(&span.start_time),
+        "endTimeUnixNano": unix_nano(&span.end_time),
+        "attributes": attributes(&span.attributes),
+        "events": span.events.iter().map(|e| json!({
+            "timeUnixNano": unix_nano(&e.timestamp),
+            "name": e.name.as_ref(),
+            "attributes": attributes(e.attributes.iter().map(|kv| (&kv.key, &kv.value))),
+        })).collect::<Vec<Json>>(),
+        "links": span.links.iter().map(|l| json!({
+            "traceId": format!("{:032x}", l.span_context.trace_id()),
+            "spanId": format!("{:016x}", l.span_context.span_id()),
+            "attributes": attributes(l.attributes.iter().map(|kv| (&kv.key, &kv.value))),
+        })).collect::<Vec<Json>>(),
+        "status": { "code": code, "message": message },
+    })
+}
+
+fn kind(kind: &SpanKind) -> u8 {
+    match kind {
+        SpanKind::Internal => 1,
+        SpanKind::Server => 2,
+        SpanKind::Client => 3,
+        SpanKind::Producer => 4,
+        SpanKind::Consumer => 5,
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{
+    messaging::{confirmed, PublishMetrics},
+    telemetry::testing::TelemetryHarness,
+};
+use lapin::{
+    acker::Acker,
+    message::{BasicReturnMessage, Delivery},
+    publisher_confirm::Confirmation,
+    types::ShortString,
+    BasicProperties,
+};
+use opentelemetry::Context;
+use shared::amqp::PublishError;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(50);
+
+fn returned(reply: &str) -> Confirmation {
+    Confirmation::Ack(Some(Box::new(BasicReturnMessage {
+        delivery: Delivery {
+            delivery_tag: 0,
+            exchange: ShortString::from("todo-events"),
+            routing_key: ShortString::from("todo.nowhere"),
+            redelivered: false,
+            properties: BasicProperties::default(),
+            data: vec![],
+            acker: Acker::default(),
+        },
+        reply_code: 312,
+        reply_text: ShortString::from(reply),
+    })))
+}
+
+fn confirm(confirmation: Option<Confirmation>) -> Result<(), PublishError> {
+    confirmed("todo-events", "todo.created", TIMEOUT, confirmation.map(Ok))
+}
+
+#[test]
+fn confirms_tell_each_outcome_apart() {
+    assert!(confirm(Some(Confirmation::Ack(None))).is_ok());
+    assert!(confirm(Some(Confirmation::NotRequested)).is_ok());
+    assert!(matches!(
+        confirm(Some(Confirmation::Nack(None))),
+        Err(PublishError::Nacked)
+    ));
+    assert!(matches!(
+        confirm(Some(returned("NO_ROUTE"))),
+        Err(PublishError::Unroutable(reply)) if reply == "NO_ROUTE"
+    ));
+    assert!(matches!(
+        confirm(None),
+        Err(PublishError::ConfirmTimeout(timeout)) if timeout == TIMEOUT
+    ));
+    assert!(matches!(
+        confirmed(
+            "todo-events",
+            "todo.created",
+            TIMEOUT,
+            Some(Err(lapin::Error::ChannelsLimitReached))
+        ),
+        Err(PublishError::Broker(_))
+    ));
+}
+
+#[test]
+fn confirms_are_counted_by_outcome() {
+    let telemetry = TelemetryHarness::install();
+    let metrics = PublishMetrics::new();
+    let ctx = Context::new();
+
+    for confirmation in [
+        Some(Confirmation::Ack(None)),
+        Some(Confirmation::Ack(None)),
+        Some(Confirmation::Nack(None)),
+        Some(returned("NO_ROUTE")),
+        None,
+    ] {
+        metrics.record(&ctx, "todo-events", 1.0, &confirm(confirmation));
+    }
+
+    let confirms =
+        |outcome: &str| telemetry.counter_where("messaging.publish.confirms", "outcome", outcome);
+    assert_eq!(confirms("ack"), Some(2.0));
+    assert_eq!(confirms("nack"), Some(1.0));
+    assert_eq!(confirms("returned"), Some(1.0));
+    assert_eq!(confirms("timeout"), Some(1.0));
+    assert_eq!(confirms("error"), None);
+    assert_eq!(
+        telemetry.histogram_count("messaging.publish.duration"),
+        Some(5)
+    );
+}
@@ -0,0 +1,142 @@
+//! Runs against the postgres of the local environment, with the `todos` table of
+//! `.docker/migration.sql`:
+//!
+//! `RUST_ENV=local cargo test -p infra --test todo_repository -- --ignored`
+use chrono::{DateTime, Utc};
+use configs::Empty;
+use configs_builder::ConfigBuilder;
+use deadpool_postgres::Pool;
+use infra::repositories::{SlowQueryConfigs, TodoRepositoryImpl};
+use opentelemetry::Context;
+use shared::{
+    models::todo::{CreateTodo, Todo},
+    repositories::TodoRepository,
+};
+use sql_pool::postgres::conn_pool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+struct Todos {
+    pool: Arc<Pool>,
+    repository: Arc<TodoRepositoryImpl>,
+}
+
+impl Todos {
+    async fn new() -> Todos {
+        let cfg = ConfigBuilder::new()
+            .postgres()
+            .build::<Empty>()
+            .await
+            .unwrap();
+        let pool = Arc::new(conn_pool(&cfg.postgres).unwrap());
+
+        Todos {
+            repository: TodoRepositoryImpl::new(
+                pool.clone(),
+                &cfg.postgres,
+                &SlowQueryConfigs::from_env(),
+            ),
+            pool,
+        }
+    }
+
+    async fn create(&self) -> Todo {
+        self.repository
+            .create(
+                &Context::new(),
+                &CreateTodo {
+                    name: "name".to_owned(),
+                    description: "description".to_owned(),
+                    owner_id: Some(format!("todo-repository-test-{}", Uuid::new_v4())),
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn list(&self, limit: i32, offset: i32) -> Vec<String> {
+        self.repository
+            .list_paginated(&Context::new(), limit, offset)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|todo| todo.id)
+            .collect()
+    }
+
+    async fn deleted_at(&self, todo: &Todo) -> Option<DateTime<Utc>> {
+        let conn = self.pool.get().await.unwrap();
+
+        conn.query_one(
+            "SELECT deleted_at FROM todos WHERE id = $1",
+            &[&Uuid::parse_str(&todo.id).unwrap()],
+        )
+        .await
+        .unwrap()
+        .get(0)
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn only_todos_not_deleted_are_listed() {
+    let todos = Todos::new().await;
+    let kept = todos.create().await;
+    let deleted = todos.create().await;
+    todos
+        .repository
+        .delete(&Context::new(), &deleted.id)
+        .await
+        .unwrap();
+
+    // newest first, so both are on the first page
+    let listed = todos.list(10, 0).await;
+
+    assert!(listed.contains(&kept.id));
+    assert!(!listed.contains(&deleted.id));
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn pages_follow_the_limit_and_offset() {
+    let todos = Todos::new().await;
+    for _ in 0..3 {
+        todos.create().await;
+    }
+
+    let first = todos.list(2, 0).await;
+    let second = todos.list(1, 1).await;
+
+    assert_eq!(first.len(), 2);
+    assert_eq!(second, first[1..].to_vec());
+}
+
+#[tokio::test]
+#[ignore = "needs the local postgres"]
+async fn deleting_stamps_the_deletion_time() {
+    let todos = Todos::new().await;
+    let todo = todos.create().await;
+
+    todos
+        .repository
+        .delete(&Context::new(), &todo.id)
+        .await
+        .unwrap();
+
+    assert!(todos.deleted_at(&todo).await.is_some());
+    assert_eq!(
+        todos
+            .repository
+            .get_by_id(&Context::new(), &todo.id)
+            .await
+            .unwrap()
+            .map(|t| t.id),
+        None
+    );
+    // deleting it again changes nothing
+    todos
+        .repository
+        .delete(&Context::new(), &todo.id)
+        .await
+        .unwrap();
+}
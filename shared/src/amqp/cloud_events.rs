@@ -0,0 +1,450 @@
+//! CloudEvents AMQP protocol binding.
+//!
+//! In structured mode the whole envelope is the message body. In binary mode the body is only
+//! the event data and every other attribute travels as a `cloudEvents:`-prefixed application
+//! property (AMQP header).
+//!
+//! Either way the body is encoded with the codec configured for the routing key. Its MIME type is
+//! the `datacontenttype` of the event and, in binary mode, the AMQP `content-type`. Structured
+//! mode messages are sent as the matching `application/cloudevents+*` type instead, as the AMQP
+//! binding requires.
+use super::publisher::{EventPublisher, PublishError};
+use crate::{
+    codecs::{codec_for, Codec, CodecError, EventCodecs},
+    events::{downcast, upcast, CloudEvent, Event, SchemaVersions, SPEC_VERSION},
+};
+use amqp::publisher::Payload;
+use lapin::{
+    types::{AMQPValue, FieldTable, LongString},
+    BasicProperties,
+};
+use opentelemetry::{global, propagation::Extractor, trace::TraceContextExt, Context};
+use serde_json::Value;
+use std::{collections::HashMap, env, fmt, str::FromStr};
+use tracing::warn;
+
+const CONTENT_MODE_ENV_KEY: &str = "EVENTS_CONTENT_MODE";
+const HEADER_PREFIX: &str = "cloudEvents:";
+const SPECVERSION_HEADER: &str = "cloudEvents:specversion";
+const ID_HEADER: &str = "cloudEvents:id";
+const SOURCE_HEADER: &str = "cloudEvents:source";
+const TYPE_HEADER: &str = "cloudEvents:type";
+const TIME_HEADER: &str = "cloudEvents:time";
+const SUBJECT_HEADER: &str = "cloudEvents:subject";
+const DATACONTENTTYPE_HEADER: &str = "cloudEvents:datacontenttype";
+const TRACEPARENT_HEADER: &str = "cloudEvents:traceparent";
+const DATAVERSION_HEADER: &str = "cloudEvents:dataversion";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentMode {
+    Structured,
+    Binary,
+}
+
+impl FromStr for ContentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "structured" => Ok(ContentMode::Structured),
+            "binary" => Ok(ContentMode::Binary),
+            other => Err(format!("unknown cloudevents content mode: {}", other)),
+        }
+    }
+}
+
+impl ContentMode {
+    /// Reads `EVENTS_CONTENT_MODE`, structured by default.
+    pub fn from_env() -> Result<ContentMode, String> {
+        match env::var(CONTENT_MODE_ENV_KEY) {
+            Err(_) => Ok(ContentMode::Structured),
+            Ok(m) if m.is_empty() => Ok(ContentMode::Structured),
+            Ok(m) => m.parse(),
+        }
+    }
+}
+
+/// How events are published, read once when the binary starts.
+#[derive(Debug, Clone)]
+pub struct EventsConfigs {
+    pub content_mode: ContentMode,
+    pub schema_versions: SchemaVersions,
+    pub codecs: EventCodecs,
+}
+
+impl Default for EventsConfigs {
+    fn default() -> Self {
+        EventsConfigs {
+            content_mode: ContentMode::Structured,
+            schema_versions: SchemaVersions::default(),
+            codecs: EventCodecs::default(),
+        }
+    }
+}
+
+impl EventsConfigs {
+    /// Reads `EVENTS_CONTENT_MODE`, `EVENTS_SCHEMA_VERSIONS` and the `EVENTS_CODEC` keys,
+    /// failing on an unknown content mode or codec.
+    pub fn from_env() -> Result<EventsConfigs, String> {
+        Ok(EventsConfigs {
+            content_mode: ContentMode::from_env()?,
+            schema_versions: SchemaVersions::from_env(),
+            codecs: EventCodecs::from_env()?,
+        })
+    }
+}
+
+/// Why a delivery could not be read as a CloudEvent. The `Display` output is what gets recorded
+/// as the dead-letter reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvelopeError {
+    NotACloudEvent(String),
+    UnsupportedSpecVersion(String),
+    MissingAttribute(&'static str),
+    UnsupportedContentType(String),
+    InvalidData(String),
+    UnknownType(String),
+    UnsupportedSchemaVersion(String, u32),
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::NotACloudEvent(err) => write!(f, "not a cloudevents envelope: {}", err),
+            EnvelopeError::UnsupportedSpecVersion(v) => {
+                write!(f, "unsupported cloudevents specversion: {}", v)
+            }
+            EnvelopeError::MissingAttribute(a) => {
+                write!(f, "missing required cloudevents attribute: {}", a)
+            }
+            EnvelopeError::UnsupportedContentType(c) => {
+                write!(f, "unsupported datacontenttype: {}", c)
+            }
+            EnvelopeError::InvalidData(err) => write!(f, "invalid event data: {}", err),
+            EnvelopeError::UnknownType(t) => write!(f, "no handler for event type: {}", t),
+            EnvelopeError::UnsupportedSchemaVersion(t, v) => {
+                write!(f, "no upcaster for {} data version {}", t, v)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EnvelopeError {}
+
+/// Wraps `data` in a CloudEvent carrying the trace in `ctx` and publishes it using the content
+/// mode, schema version and codec configured in `cfg` for the routing key.
+pub async fn publish_event<E: Event + Send + Sync>(
+    publisher: &dyn EventPublisher,
+    cfg: &EventsConfigs,
+    ctx: &Context,
+    exchange: &str,
+    routing_key: &str,
+    data: E,
+) -> Result<(), PublishError> {
+    let codec = cfg.codecs.for_routing_key(routing_key).codec();
+    let mut event = CloudEvent::new(data).with_trace(ctx);
+    event.datacontenttype = Some(codec.content_type().to_owned());
+
+    let data =
+        serde_json::to_value(&event.data).map_err(|err| CodecError::Encode(err.to_string()))?;
+    let mut event = event.map(|_| data);
+
+    let version = cfg.schema_versions.producer_version::<E>();
+    if version != E::VERSION {
+        match downcast(E::TYPE, E::VERSION, version, event.data.clone()) {
+            None => warn!(
+                event_type = E::TYPE,
+                version = version,
+                "error to downcast event, publishing the current version"
+            ),
+            Some(data) => {
+                event.data = data;
+                event.dataversion = Some(version);
+            }
+        }
+    }
+
+    send(
+        publisher,
+        cfg.content_mode,
+        codec,
+        ctx,
+        exchange,
+        routing_key,
+        &event,
+    )
+    .await
+}
+
+async fn send(
+    publisher: &dyn EventPublisher,
+    content_mode: ContentMode,
+    codec: &dyn Codec,
+    ctx: &Context,
+    exchange: &str,
+    routing_key: &str,
+    event: &CloudEvent<Value>,
+) -> Result<(), PublishError> {
+    let (body, content_type, headers) = match content_mode {
+        ContentMode::Structured => (
+            codec.encode_event(event)?,
+            codec.structured_content_type(),
+            None,
+        ),
+        ContentMode::Binary => (
+            codec.encode_data(&event.ty, &event.data)?,
+            codec.content_type(),
+            Some(binary_headers(event)),
+        ),
+    };
+
+    let payload = Payload {
+        payload: body,
+        typ: event.to_string(),
+    };
+
+    publisher
+        .publish(ctx, exchange, routing_key, &payload, content_type, headers)
+        .await
+}
+
+fn binary_headers<T>(event: &CloudEvent<T>) -> HashMap<&'static str, AMQPValue> {
+    let mut headers = HashMap::from([
+        (SPECVERSION_HEADER, long_string(&event.specversion)),
+        (ID_HEADER, long_string(&event.id)),
+        (SOURCE_HEADER, long_string(&event.source)),
+        (TYPE_HEADER, long_string(&event.ty)),
+    ]);
+
+    let optional = [
+        (TIME_HEADER, &event.time),
+        (SUBJECT_HEADER, &event.subject),
+        (DATACONTENTTYPE_HEADER, &event.datacontenttype),
+        (TRACEPARENT_HEADER, &event.traceparent),
+    ];
+    for (header, value) in optional {
+        if let Some(v) = value {
+            headers.insert(header, long_string(v));
+        }
+    }
+
+    if let Some(version) = event.dataversion {
+        headers.insert(DATAVERSION_HEADER, long_string(&version.to_string()));
+    }
+
+    headers
+}
+
+fn long_string(value: &str) -> AMQPValue {
+    AMQPValue::LongString(LongString::from(value))
+}
+
+fn header<'a>(headers: Option<&'a FieldTable>, name: &str) -> Option<&'a str> {
+    let value = headers?.inner().get(name)?;
+
+    match value {
+        AMQPValue::LongString(s) => std::str::from_utf8(s.as_bytes()).ok(),
+        AMQPValue::ShortString(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+/// Reads a delivery in either content mode. Binary mode is recognized by the
+/// `cloudEvents:specversion` header, anything else must be a structured envelope. The body is
+/// decoded with the codec of its content type, the `application/cloudevents+*` type of the codec
+/// in structured mode, JSON when it has none.
+pub fn decode_event(
+    body: &[u8],
+    properties: &BasicProperties,
+) -> Result<CloudEvent<Value>, EnvelopeError> {
+    let headers = properties.headers().as_ref();
+    let content_type = properties.content_type().as_ref().map(|c| c.as_str());
+
+    let event = match header(headers, SPECVERSION_HEADER) {
+        Some(specversion) => {
+            let required = |name: &'static str| {
+                header(headers, name)
+                    .map(str::to_owned)
+                    .ok_or(EnvelopeError::MissingAttribute(
+                        name.trim_start_matches(HEADER_PREFIX),
+                    ))
+            };
+            let optional = |name: &str| header(headers, name).map(str::to_owned);
+
+            let datacontenttype =
+                optional(DATACONTENTTYPE_HEADER).or_else(|| content_type.map(str::to_owned));
+            let codec = codec_for(datacontenttype.as_deref()).ok_or_else(|| {
+                EnvelopeError::UnsupportedContentType(datacontenttype.clone().unwrap_or_default())
+            })?;
+
+            let ty = required(TYPE_HEADER)?;
+            let data = codec
+                .decode_data(&ty, body)
+                .map_err(|err| EnvelopeError::InvalidData(err.to_string()))?;
+
+            CloudEvent {
+                specversion: specversion.to_owned(),
+                id: required(ID_HEADER)?,
+                source: required(SOURCE_HEADER)?,
+                ty,
+                time: optional(TIME_HEADER),
+                subject: optional(SUBJECT_HEADER),
+                datacontenttype,
+                traceparent: optional(TRACEPARENT_HEADER),
+                dataversion: match optional(DATAVERSION_HEADER) {
+                    None => None,
+                    Some(v) => Some(v.parse().map_err(|_| {
+                        EnvelopeError::InvalidData(format!("invalid dataversion: {}", v))
+                    })?),
+                },
+                data,
+            }
+        }
+        None => {
+            let codec = codec_for(content_type).ok_or_else(|| {
+                EnvelopeError::UnsupportedContentType(content_type.unwrap_or_default().to_owned())
+            })?;
+
+            codec
+                .decode_event(body)
+                .map_err(|err| EnvelopeError::NotACloudEvent(err.to_string()))?
+        }
+    };
+
+    if event.specversion != SPEC_VERSION {
+        return Err(EnvelopeError::UnsupportedSpecVersion(event.specversion));
+    }
+
+    for (name, value) in [
+        ("id", &event.id),
+        ("source", &event.source),
+        ("type", &event.ty),
+    ] {
+        if value.is_empty() {
+            return Err(EnvelopeError::MissingAttribute(name));
+        }
+    }
+
+    Ok(event)
+}
+
+/// Codec of a delivery body and, in binary mode, the event type its data is encoded for.
+fn body_codec(
+    properties: &BasicProperties,
+) -> Result<(&'static dyn Codec, Option<String>), EnvelopeError> {
+    let headers = properties.headers().as_ref();
+    let content_type = properties.content_type().as_ref().map(|c| c.as_str());
+
+    let (content_type, ty) = match header(headers, SPECVERSION_HEADER) {
+        None => (content_type, None),
+        Some(_) => {
+            let ty = header(headers, TYPE_HEADER).ok_or(EnvelopeError::MissingAttribute("type"))?;
+            (
+                header(headers, DATACONTENTTYPE_HEADER).or(content_type),
+                Some(ty.to_owned()),
+            )
+        }
+    };
+
+    let codec = codec_for(content_type).ok_or_else(|| {
+        EnvelopeError::UnsupportedContentType(content_type.unwrap_or_default().to_owned())
+    })?;
+
+    Ok((codec, ty))
+}
+
+/// Decodes a delivery body as is, the envelope in structured mode and the event data in binary
+/// mode, without validating or upcasting it.
+pub fn decode_body(body: &[u8], properties: &BasicProperties) -> Result<Value, EnvelopeError> {
+    match body_codec(properties)? {
+        (codec, Some(ty)) => codec
+            .decode_data(&ty, body)
+            .map_err(|err| EnvelopeError::InvalidData(err.to_string())),
+        (codec, None) => {
+            let event = codec
+                .decode_event(body)
+                .map_err(|err| EnvelopeError::NotACloudEvent(err.to_string()))?;
+
+            serde_json::to_value(event).map_err(|err| EnvelopeError::InvalidData(err.to_string()))
+        }
+    }
+}
+
+/// Encodes a body read with `decode_body` back into a delivery with the same properties.
+pub fn encode_body(body: &Value, properties: &BasicProperties) -> Result<Vec<u8>, EnvelopeError> {
+    match body_codec(properties)? {
+        (codec, Some(ty)) => codec
+            .encode_data(&ty, body)
+            .map_err(|err| EnvelopeError::InvalidData(err.to_string())),
+        (codec, None) => {
+            let event = serde_json::from_value::<CloudEvent<Value>>(body.clone())
+                .map_err(|err| EnvelopeError::NotACloudEvent(err.to_string()))?;
+
+            codec
+                .encode_event(&event)
+                .map_err(|err| EnvelopeError::InvalidData(err.to_string()))
+        }
+    }
+}
+
+impl CloudEvent<Value> {
+    /// Upcasts the data to version `current` of the event type.
+    pub fn upcast(mut self, current: u32) -> Result<CloudEvent<Value>, EnvelopeError> {
+        let version = self.dataversion.unwrap_or(1);
+        if version == current {
+            return Ok(self);
+        }
+
+        self.data = upcast(&self.ty, version, current, self.data)
+            .ok_or_else(|| EnvelopeError::UnsupportedSchemaVersion(self.ty.clone(), version))?;
+        self.dataversion = Some(current);
+
+        Ok(self)
+    }
+
+    /// Deserializes the data into the message type registered for the event type, upcasting it
+    /// from whatever version it was produced with.
+    pub fn data_as<E: Event>(&self) -> Result<E, EnvelopeError> {
+        let event = self.clone().upcast(E::VERSION)?;
+
+        serde_json::from_value(event.data)
+            .map_err(|err| EnvelopeError::InvalidData(err.to_string()))
+    }
+}
+
+/// Reads W3C trace context from AMQP headers.
+pub struct HeaderExtractor<'a>(Option<&'a FieldTable>);
+
+impl<'a> HeaderExtractor<'a> {
+    pub fn new(properties: &'a BasicProperties) -> HeaderExtractor<'a> {
+        HeaderExtractor(properties.headers().as_ref())
+    }
+}
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        header(self.0, key)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .map(|h| h.inner().keys().map(|k| k.as_str()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// The publisher context, from the `traceparent` header set by the AMQP publisher or else from
+/// the envelope `traceparent` extension.
+pub fn event_context(properties: &BasicProperties, event: &CloudEvent<Value>) -> Context {
+    let ctx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor::new(properties))
+    });
+
+    match &event.traceparent {
+        Some(traceparent) if !ctx.span().span_context().is_valid() => {
+            let carrier = HashMap::from([("traceparent".to_owned(), traceparent.clone())]);
+            global::get_text_map_propagator(|propagator| propagator.extract(&carrier))
+        }
+        _ => ctx,
+    }
+}
@@ -1,2 +1,25 @@
-pub const EXCHANGE: &str = "simple-exchange";
-pub const ROUTING_KEY: &str = "simple-exchange-key";
+mod cloud_events;
+mod publisher;
+
+pub use cloud_events::{
+    decode_body, decode_event, encode_body, event_context, publish_event, ContentMode,
+    EnvelopeError, EventsConfigs, HeaderExtractor,
+};
+pub use publisher::{EventPublisher, PublishError, UnpublishedEvent, UnpublishedKind};
+
+/// Topic exchange every todo event is published to, routed by `todo.<event>`.
+pub const EXCHANGE: &str = "todo-events";
+
+pub const TODO_CREATED_ROUTING_KEY: &str = "todo.created";
+pub const TODO_UPDATED_ROUTING_KEY: &str = "todo.updated";
+pub const TODO_DELETED_ROUTING_KEY: &str = "todo.deleted";
+pub const TODO_RESTORED_ROUTING_KEY: &str = "todo.restored";
+pub const TODO_COMPLETED_ROUTING_KEY: &str = "todo.completed";
+
+/// Binding key matching every todo event.
+pub const TODO_EVENTS_BINDING_KEY: &str = "todo.*";
+
+/// Dead-letter queue declared by `QueueDefinition::with_dlq` for `queue`.
+pub fn dlq_name(queue: &str) -> String {
+    format!("{}-dlq", queue)
+}
@@ -0,0 +1,181 @@
+use crate::{
+    codecs::CodecError,
+    events::{CLOUDEVENTS_JSON_CONTENT_TYPE, JSON_CONTENT_TYPE},
+};
+use amqp::{
+    errors::AmqpError,
+    publisher::{Payload, Publisher},
+};
+use async_trait::async_trait;
+use lapin::types::AMQPValue;
+use opentelemetry::Context;
+use std::{collections::HashMap, fmt, time::Duration};
+use tracing::error;
+
+/// Why an event was not published.
+#[derive(Debug)]
+pub enum PublishError {
+    Amqp(AmqpError),
+    Codec(CodecError),
+    /// The channel failed before the broker answered.
+    Broker(String),
+    /// The broker did not confirm the message in time, it may or may not have been persisted.
+    ConfirmTimeout(Duration),
+    /// The broker refused to take responsibility for the message.
+    Nacked,
+    /// The message matched no queue and was returned by the broker, with its reply text.
+    Unroutable(String),
+    /// The publisher can not send payloads of this content type.
+    UnsupportedContentType(String),
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::Amqp(err) => write!(f, "{}", err),
+            PublishError::Codec(err) => write!(f, "{}", err),
+            PublishError::Broker(err) => write!(f, "broker error: {}", err),
+            PublishError::ConfirmTimeout(timeout) => {
+                write!(f, "publish not confirmed within {}ms", timeout.as_millis())
+            }
+            PublishError::Nacked => write!(f, "publish nacked by the broker"),
+            PublishError::Unroutable(reply) => {
+                write!(f, "message returned as unroutable: {}", reply)
+            }
+            PublishError::UnsupportedContentType(content_type) => {
+                write!(f, "publisher can not send {} payloads", content_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PublishError {}
+
+/// How the event of a committed change failed to reach the broker, which each server maps to its
+/// own status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnpublishedKind {
+    /// Not confirmed in time or nacked, publishing it later may work.
+    Unavailable,
+    /// Returned as unroutable, no queue would receive it.
+    Unroutable,
+    Failed,
+}
+
+/// Why the event of a committed change was not published, told the same way by every server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnpublishedEvent {
+    pub kind: UnpublishedKind,
+    /// Short answer to the caller, e.g. `todo created but its event was refused`.
+    pub message: String,
+    pub details: String,
+}
+
+impl PublishError {
+    /// Logs and classifies the error of publishing the event of `change`, e.g. `todo created`,
+    /// once the change itself was committed.
+    pub fn unpublished(&self, change: &str) -> UnpublishedEvent {
+        match self {
+            PublishError::ConfirmTimeout(timeout) => {
+                error!(
+                    timeout_ms = timeout.as_millis() as u64,
+                    "{} event not confirmed in time", change
+                );
+                UnpublishedEvent {
+                    kind: UnpublishedKind::Unavailable,
+                    message: format!("{} but its event was not confirmed", change),
+                    details: format!(
+                        "the broker did not confirm the {} event within {}ms, the event may not have been published",
+                        change,
+                        timeout.as_millis()
+                    ),
+                }
+            }
+            PublishError::Nacked => {
+                error!("{} event nacked by the broker", change);
+                UnpublishedEvent {
+                    kind: UnpublishedKind::Unavailable,
+                    message: format!("{} but its event was refused", change),
+                    details: format!(
+                        "the broker nacked the {} event, the event was not published",
+                        change
+                    ),
+                }
+            }
+            PublishError::Unroutable(reply) => {
+                error!(
+                    reply = reply.as_str(),
+                    "{} event returned as unroutable", change
+                );
+                UnpublishedEvent {
+                    kind: UnpublishedKind::Unroutable,
+                    message: format!("{} but its event reached no queue", change),
+                    details: format!(
+                        "the broker returned the {} event as unroutable ({}), no consumer will receive it",
+                        change, reply
+                    ),
+                }
+            }
+            err => {
+                error!(error = err.to_string(), "error to publish {} event", change);
+                UnpublishedEvent {
+                    kind: UnpublishedKind::Failed,
+                    message: format!("{} but its event was not published", change),
+                    details: format!("error to publish the {} event", change),
+                }
+            }
+        }
+    }
+}
+
+impl From<AmqpError> for PublishError {
+    fn from(value: AmqpError) -> Self {
+        PublishError::Amqp(value)
+    }
+}
+
+impl From<CodecError> for PublishError {
+    fn from(value: CodecError) -> Self {
+        PublishError::Codec(value)
+    }
+}
+
+/// A publisher whose errors tell a timed out confirm or an unroutable message apart, sending
+/// payloads of any content type. Every `Publisher` is one that only sends JSON, in either content
+/// mode, and otherwise only fails with `PublishError::Amqp`. It can not set the AMQP
+/// `content-type`, so its messages keep the one it always sends.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    async fn publish(
+        &self,
+        ctx: &Context,
+        exchange: &str,
+        routing_key: &str,
+        payload: &Payload,
+        content_type: &str,
+        headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError>;
+}
+
+#[async_trait]
+impl<T: Publisher + Send + Sync> EventPublisher for T {
+    async fn publish(
+        &self,
+        ctx: &Context,
+        exchange: &str,
+        routing_key: &str,
+        payload: &Payload,
+        content_type: &str,
+        headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError> {
+        if content_type != JSON_CONTENT_TYPE && content_type != CLOUDEVENTS_JSON_CONTENT_TYPE {
+            return Err(PublishError::UnsupportedContentType(
+                content_type.to_owned(),
+            ));
+        }
+
+        Publisher::publish(self, ctx, exchange, routing_key, payload, headers)
+            .await
+            .map_err(PublishError::Amqp)
+    }
+}
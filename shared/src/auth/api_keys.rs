@@ -0,0 +1,33 @@
+use super::{AuthMethod, Principal};
+use crate::{models::api_key::hash_api_key, repositories::ApiKeyRepository};
+use chrono::Utc;
+use opentelemetry::Context;
+use tracing::error;
+
+/// Resolves an API key to the principal of the active key matching its hash, refreshing when the
+/// key was last used. `None` when no active key matches or the lookup failed.
+pub async fn authenticate_api_key(
+    ctx: &Context,
+    repo: &dyn ApiKeyRepository,
+    key: &str,
+) -> Option<Principal> {
+    let api_key = match repo.get_active_by_hash(ctx, &hash_api_key(key)).await {
+        Err(err) => {
+            error!(error = err.to_string(), "error to get api key");
+            None
+        }
+        Ok(k) => k,
+    }?;
+
+    if api_key.last_use_outdated(Utc::now()) {
+        if let Err(err) = repo.touch_last_used(ctx, &api_key.id).await {
+            error!(error = err.to_string(), "error to update api key last use");
+        }
+    }
+
+    Some(Principal {
+        subject: api_key.owner_id,
+        scopes: api_key.scopes,
+        method: AuthMethod::ApiKey,
+    })
+}
@@ -0,0 +1,33 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+
+/// Auth0 claims read straight from the payload of a bearer token.
+///
+/// Decoding here does NOT validate the token, callers must only use the claims once the token
+/// was verified.
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenClaims {
+    #[serde(default)]
+    pub sub: String,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl TokenClaims {
+    pub fn from_token(token: &str) -> Option<TokenClaims> {
+        let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+
+        serde_json::from_slice(&payload).ok()
+    }
+
+    /// Scopes granted either through the `scope` claim or Auth0 RBAC `permissions`.
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope
+            .split_whitespace()
+            .map(str::to_owned)
+            .chain(self.permissions.iter().cloned())
+            .collect()
+    }
+}
@@ -0,0 +1,52 @@
+//! Credentials the HTTP and gRPC servers accept, a bearer JWT or an API key, and the principal
+//! both resolve them to.
+mod api_keys;
+mod claims;
+
+pub use api_keys::authenticate_api_key;
+pub use claims::TokenClaims;
+
+use crate::models::todo::Todo;
+
+pub const TODOS_READ_SCOPE: &str = "todos:read";
+pub const TODOS_WRITE_SCOPE: &str = "todos:write";
+/// Grants every other todo scope.
+pub const TODOS_ADMIN_SCOPE: &str = "todos:admin";
+
+/// Caller whose credentials were validated, either a verified JWT or an active API key.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub scopes: Vec<String>,
+    pub method: AuthMethod,
+}
+
+impl Principal {
+    /// Whether the principal holds `scope`, which `todos:admin` always grants.
+    pub fn grants(&self, scope: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s == scope || s == TODOS_ADMIN_SCOPE)
+    }
+
+    /// Whether the principal may change `todo`, which only its owner or a `todos:admin` holder
+    /// may.
+    pub fn can_change(&self, todo: &Todo) -> bool {
+        todo.owner_id.as_deref() == Some(self.subject.as_str()) || self.grants(TODOS_ADMIN_SCOPE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Jwt,
+    ApiKey,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthMethod::Jwt => "jwt",
+            AuthMethod::ApiKey => "api_key",
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use super::{Codec, CodecError};
+use crate::events::{CloudEvent, CLOUDEVENTS_JSON_CONTENT_TYPE, JSON_CONTENT_TYPE};
+use serde_json::Value;
+
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn structured_content_type(&self) -> &'static str {
+        CLOUDEVENTS_JSON_CONTENT_TYPE
+    }
+
+    fn encode_event(&self, event: &CloudEvent<Value>) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(event).map_err(|err| CodecError::Encode(err.to_string()))
+    }
+
+    fn decode_event(&self, bytes: &[u8]) -> Result<CloudEvent<Value>, CodecError> {
+        serde_json::from_slice(bytes).map_err(|err| CodecError::Decode(err.to_string()))
+    }
+
+    fn encode_data(&self, _event_type: &str, data: &Value) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(data).map_err(|err| CodecError::Encode(err.to_string()))
+    }
+
+    fn decode_data(&self, _event_type: &str, bytes: &[u8]) -> Result<Value, CodecError> {
+        serde_json::from_slice(bytes).map_err(|err| CodecError::Decode(err.to_string()))
+    }
+}
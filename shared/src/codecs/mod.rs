@@ -0,0 +1,196 @@
+//! Payload codecs.
+//!
+//! Every codec maps bytes to and from a `serde_json::Value`, the representation envelopes and
+//! upcasters already work with, so a queue can move to another format without touching its
+//! handlers. Data is always encoded knowing its event type, which lets schema-bound formats like
+//! protobuf pick the message of each event. Consumers pick the codec from the AMQP `content-type`
+//! of each message, producers from `EventCodecs`, which lets both formats coexist on a queue
+//! while producers switch.
+mod json;
+mod msgpack;
+mod protobuf;
+
+pub use json::JsonCodec;
+pub use msgpack::{MessagePackCodec, CLOUDEVENTS_MSGPACK_CONTENT_TYPE, MSGPACK_CONTENT_TYPE};
+pub use protobuf::{ProtobufCodec, CLOUDEVENTS_PROTOBUF_CONTENT_TYPE, PROTOBUF_CONTENT_TYPE};
+
+use crate::events::{CloudEvent, Event, CLOUDEVENTS_JSON_CONTENT_TYPE, JSON_CONTENT_TYPE};
+use serde_json::Value;
+use std::{collections::HashMap, env, fmt, str::FromStr};
+
+const CODEC_ENV_KEY: &str = "EVENTS_CODEC";
+const ROUTING_KEY_CODEC_ENV_SUFFIX: &str = "_EVENTS_CODEC";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    Encode(String),
+    Decode(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Encode(err) => write!(f, "error to encode payload: {}", err),
+            CodecError::Decode(err) => write!(f, "error to decode payload: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+pub trait Codec: Send + Sync {
+    /// MIME type of the data encoded with the codec, the `datacontenttype` of its events and the
+    /// AMQP `content-type` of binary mode messages.
+    fn content_type(&self) -> &'static str;
+
+    /// AMQP `content-type` of structured mode messages, whose body is the whole envelope, e.g.
+    /// `application/cloudevents+json`.
+    fn structured_content_type(&self) -> &'static str;
+
+    /// Encodes a whole envelope, the body of a structured mode message.
+    fn encode_event(&self, event: &CloudEvent<Value>) -> Result<Vec<u8>, CodecError>;
+
+    fn decode_event(&self, bytes: &[u8]) -> Result<CloudEvent<Value>, CodecError>;
+
+    /// Encodes the data of an event of type `event_type`, the body of a binary mode message and
+    /// what handlers are given.
+    fn encode_data(&self, event_type: &str, data: &Value) -> Result<Vec<u8>, CodecError>;
+
+    fn decode_data(&self, event_type: &str, bytes: &[u8]) -> Result<Value, CodecError>;
+}
+
+impl dyn Codec + '_ {
+    pub fn encode_as<E: Event>(&self, data: &E) -> Result<Vec<u8>, CodecError> {
+        let value =
+            serde_json::to_value(data).map_err(|err| CodecError::Encode(err.to_string()))?;
+        self.encode_data(E::TYPE, &value)
+    }
+
+    pub fn decode_as<E: Event>(&self, bytes: &[u8]) -> Result<E, CodecError> {
+        serde_json::from_value(self.decode_data(E::TYPE, bytes)?)
+            .map_err(|err| CodecError::Decode(err.to_string()))
+    }
+}
+
+/// Codec of a `content-type`, ignoring its parameters, either the data type of the codec or its
+/// structured mode `application/cloudevents+*` type. Messages without one are JSON.
+pub fn codec_for(content_type: Option<&str>) -> Option<&'static dyn Codec> {
+    let media_type = match content_type {
+        None => return Some(&JsonCodec),
+        Some(c) => c
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase(),
+    };
+
+    match media_type.as_str() {
+        JSON_CONTENT_TYPE | CLOUDEVENTS_JSON_CONTENT_TYPE | "text/json" => Some(&JsonCodec),
+        MSGPACK_CONTENT_TYPE
+        | CLOUDEVENTS_MSGPACK_CONTENT_TYPE
+        | "application/x-msgpack"
+        | "application/vnd.msgpack" => Some(&MessagePackCodec),
+        PROTOBUF_CONTENT_TYPE | CLOUDEVENTS_PROTOBUF_CONTENT_TYPE | "application/x-protobuf" => {
+            Some(&ProtobufCodec)
+        }
+        _ => None,
+    }
+}
+
+/// Codec producers encode events with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CodecKind {
+    #[default]
+    Json,
+    MessagePack,
+    Protobuf,
+}
+
+impl FromStr for CodecKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(CodecKind::Json),
+            "msgpack" | "messagepack" => Ok(CodecKind::MessagePack),
+            "protobuf" => Ok(CodecKind::Protobuf),
+            other => Err(format!("unknown codec: {}", other)),
+        }
+    }
+}
+
+impl CodecKind {
+    pub fn codec(self) -> &'static dyn Codec {
+        match self {
+            CodecKind::Json => &JsonCodec,
+            CodecKind::MessagePack => &MessagePackCodec,
+            CodecKind::Protobuf => &ProtobufCodec,
+        }
+    }
+}
+
+/// Codec of the events published with each routing key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventCodecs {
+    default: CodecKind,
+    /// Keyed by the env prefix of the routing key, e.g. `TODO_CREATED`.
+    routing_keys: HashMap<String, CodecKind>,
+}
+
+impl EventCodecs {
+    /// Every event encoded with `kind`.
+    pub fn new(kind: CodecKind) -> EventCodecs {
+        EventCodecs {
+            default: kind,
+            routing_keys: HashMap::new(),
+        }
+    }
+
+    /// Events published with `routing_key` encoded with `kind` instead.
+    pub fn with(mut self, routing_key: &str, kind: CodecKind) -> Self {
+        self.routing_keys.insert(env_prefix(routing_key), kind);
+        self
+    }
+
+    /// Reads `EVENTS_CODEC`, overridable for a routing key by prefixing it with the key, e.g.
+    /// `TODO_CREATED_EVENTS_CODEC` for `todo.created`. JSON by default, failing on an unknown
+    /// codec.
+    pub fn from_env() -> Result<EventCodecs, String> {
+        let mut codecs = EventCodecs::default();
+
+        for (key, value) in env::vars() {
+            let prefix = match key.strip_suffix(ROUTING_KEY_CODEC_ENV_SUFFIX) {
+                Some(prefix) => Some(prefix.to_owned()),
+                None if key == CODEC_ENV_KEY => None,
+                None => continue,
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            let kind = value
+                .parse::<CodecKind>()
+                .map_err(|err| format!("{}: {}", key, err))?;
+            match prefix {
+                None => codecs.default = kind,
+                Some(prefix) => {
+                    codecs.routing_keys.insert(prefix, kind);
+                }
+            }
+        }
+
+        Ok(codecs)
+    }
+
+    pub fn for_routing_key(&self, routing_key: &str) -> CodecKind {
+        self.routing_keys
+            .get(&env_prefix(routing_key))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+fn env_prefix(routing_key: &str) -> String {
+    routing_key.to_uppercase().replace(['-', '.'], "_")
+}
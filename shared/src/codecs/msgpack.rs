@@ -0,0 +1,36 @@
+use super::{Codec, CodecError};
+use crate::events::CloudEvent;
+use serde_json::Value;
+
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+pub const CLOUDEVENTS_MSGPACK_CONTENT_TYPE: &str = "application/cloudevents+msgpack";
+
+/// MessagePack with objects encoded as maps keyed by field name, so the payload stays readable
+/// without the struct that produced it.
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn content_type(&self) -> &'static str {
+        MSGPACK_CONTENT_TYPE
+    }
+
+    fn structured_content_type(&self) -> &'static str {
+        CLOUDEVENTS_MSGPACK_CONTENT_TYPE
+    }
+
+    fn encode_event(&self, event: &CloudEvent<Value>) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(event).map_err(|err| CodecError::Encode(err.to_string()))
+    }
+
+    fn decode_event(&self, bytes: &[u8]) -> Result<CloudEvent<Value>, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(|err| CodecError::Decode(err.to_string()))
+    }
+
+    fn encode_data(&self, _event_type: &str, data: &Value) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(data).map_err(|err| CodecError::Encode(err.to_string()))
+    }
+
+    fn decode_data(&self, _event_type: &str, bytes: &[u8]) -> Result<Value, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(|err| CodecError::Decode(err.to_string()))
+    }
+}
@@ -0,0 +1,270 @@
+use super::{Codec, CodecError};
+use crate::events::{
+    CloudEvent, TODO_COMPLETED_EVENT, TODO_CREATED_EVENT, TODO_DELETED_EVENT, TODO_RESTORED_EVENT,
+    TODO_UPDATED_EVENT,
+};
+use prost::{Message, Oneof};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/protobuf";
+pub const CLOUDEVENTS_PROTOBUF_CONTENT_TYPE: &str = "application/cloudevents+protobuf";
+
+/// Protobuf with a message per event type, so numbers keep their type and consumers in other
+/// languages read the data with the `.proto` of the event:
+///
+/// ```proto
+/// message TodoCreated   { string id = 1; string name = 2; string description = 3;
+///                         string created_at = 4; optional string owner_id = 5; }
+/// message TodoUpdated   { string id = 1; string name = 2; string description = 3;
+///                         string updated_at = 4; optional string owner_id = 5; }
+/// message TodoDeleted   { string id = 1; string deleted_at = 2; optional string owner_id = 3; }
+/// message TodoRestored  { string id = 1; string name = 2; string description = 3;
+///                         string restored_at = 4; optional string owner_id = 5; }
+/// message TodoCompleted { string id = 1; string completed_at = 2; optional string owner_id = 3; }
+/// ```
+///
+/// Fields added by a new schema version are optional, so every version of an event shares its
+/// message. Data with a field the message lacks fails to encode rather than losing it.
+///
+/// Structured envelopes are the `io.cloudevents.v1.CloudEvent` message of the CloudEvents
+/// protobuf format, with the data in `binary_data` and `dataversion` as a `ce_integer`.
+pub struct ProtobufCodec;
+
+impl Codec for ProtobufCodec {
+    fn content_type(&self) -> &'static str {
+        PROTOBUF_CONTENT_TYPE
+    }
+
+    fn structured_content_type(&self) -> &'static str {
+        CLOUDEVENTS_PROTOBUF_CONTENT_TYPE
+    }
+
+    fn encode_event(&self, event: &CloudEvent<Value>) -> Result<Vec<u8>, CodecError> {
+        let mut attributes = HashMap::new();
+        let strings = [
+            ("time", &event.time),
+            ("subject", &event.subject),
+            ("datacontenttype", &event.datacontenttype),
+            ("traceparent", &event.traceparent),
+        ];
+        for (name, value) in strings {
+            if let Some(v) = value {
+                attributes.insert(name.to_owned(), Attribute::string(v));
+            }
+        }
+        if let Some(version) = event.dataversion {
+            let version = i32::try_from(version)
+                .map_err(|_| CodecError::Encode(format!("invalid dataversion: {}", version)))?;
+            attributes.insert("dataversion".to_owned(), Attribute::integer(version));
+        }
+
+        let message = CloudEventMessage {
+            id: event.id.clone(),
+            source: event.source.clone(),
+            spec_version: event.specversion.clone(),
+            ty: event.ty.clone(),
+            attributes,
+            binary_data: self.encode_data(&event.ty, &event.data)?,
+        };
+
+        Ok(message.encode_to_vec())
+    }
+
+    fn decode_event(&self, bytes: &[u8]) -> Result<CloudEvent<Value>, CodecError> {
+        let message =
+            CloudEventMessage::decode(bytes).map_err(|err| CodecError::Decode(err.to_string()))?;
+        let string = |name: &str| match message.attributes.get(name).map(|a| &a.attr) {
+            Some(Some(Attr::CeString(s))) => Some(s.clone()),
+            _ => None,
+        };
+
+        let dataversion = match message.attributes.get("dataversion").map(|a| &a.attr) {
+            None => None,
+            Some(Some(Attr::CeInteger(v))) if *v >= 0 => Some(*v as u32),
+            Some(other) => {
+                return Err(CodecError::Decode(format!(
+                    "invalid dataversion: {:?}",
+                    other
+                )))
+            }
+        };
+
+        Ok(CloudEvent {
+            specversion: message.spec_version.clone(),
+            id: message.id.clone(),
+            source: message.source.clone(),
+            ty: message.ty.clone(),
+            time: string("time"),
+            subject: string("subject"),
+            datacontenttype: string("datacontenttype"),
+            traceparent: string("traceparent"),
+            dataversion,
+            data: self.decode_data(&message.ty, &message.binary_data)?,
+        })
+    }
+
+    fn encode_data(&self, event_type: &str, data: &Value) -> Result<Vec<u8>, CodecError> {
+        match event_type {
+            TODO_CREATED_EVENT => encode::<TodoCreated>(data),
+            TODO_UPDATED_EVENT => encode::<TodoUpdated>(data),
+            TODO_DELETED_EVENT => encode::<TodoDeleted>(data),
+            TODO_RESTORED_EVENT => encode::<TodoRestored>(data),
+            TODO_COMPLETED_EVENT => encode::<TodoCompleted>(data),
+            other => Err(CodecError::Encode(format!(
+                "no protobuf message for event type: {}",
+                other
+            ))),
+        }
+    }
+
+    fn decode_data(&self, event_type: &str, bytes: &[u8]) -> Result<Value, CodecError> {
+        match event_type {
+            TODO_CREATED_EVENT => decode::<TodoCreated>(bytes),
+            TODO_UPDATED_EVENT => decode::<TodoUpdated>(bytes),
+            TODO_DELETED_EVENT => decode::<TodoDeleted>(bytes),
+            TODO_RESTORED_EVENT => decode::<TodoRestored>(bytes),
+            TODO_COMPLETED_EVENT => decode::<TodoCompleted>(bytes),
+            other => Err(CodecError::Decode(format!(
+                "no protobuf message for event type: {}",
+                other
+            ))),
+        }
+    }
+}
+
+fn encode<M: Message + DeserializeOwned>(data: &Value) -> Result<Vec<u8>, CodecError> {
+    match serde_json::from_value::<M>(data.clone()) {
+        Err(err) => Err(CodecError::Encode(err.to_string())),
+        Ok(message) => Ok(message.encode_to_vec()),
+    }
+}
+
+fn decode<M: Message + Default + Serialize>(bytes: &[u8]) -> Result<Value, CodecError> {
+    match M::decode(bytes) {
+        Err(err) => Err(CodecError::Decode(err.to_string())),
+        Ok(message) => {
+            serde_json::to_value(message).map_err(|err| CodecError::Decode(err.to_string()))
+        }
+    }
+}
+
+/// `io.cloudevents.v1.CloudEvent`, `binary_data` being the only data representation used.
+#[derive(Clone, PartialEq, Message)]
+struct CloudEventMessage {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    source: String,
+    #[prost(string, tag = "3")]
+    spec_version: String,
+    #[prost(string, tag = "4")]
+    ty: String,
+    #[prost(map = "string, message", tag = "5")]
+    attributes: HashMap<String, Attribute>,
+    #[prost(bytes = "vec", tag = "6")]
+    binary_data: Vec<u8>,
+}
+
+/// `io.cloudevents.v1.CloudEvent.CloudEventAttributeValue`, the kinds the envelope uses.
+#[derive(Clone, PartialEq, Message)]
+struct Attribute {
+    #[prost(oneof = "Attr", tags = "2, 3")]
+    attr: Option<Attr>,
+}
+
+impl Attribute {
+    fn string(value: &str) -> Attribute {
+        Attribute {
+            attr: Some(Attr::CeString(value.to_owned())),
+        }
+    }
+
+    fn integer(value: i32) -> Attribute {
+        Attribute {
+            attr: Some(Attr::CeInteger(value)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Oneof)]
+enum Attr {
+    #[prost(int32, tag = "2")]
+    CeInteger(i32),
+    #[prost(string, tag = "3")]
+    CeString(String),
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoCreated {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    name: String,
+    #[prost(string, tag = "3")]
+    description: String,
+    #[prost(string, tag = "4")]
+    created_at: String,
+    /// Added by v2.
+    #[prost(string, optional, tag = "5")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoUpdated {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    name: String,
+    #[prost(string, tag = "3")]
+    description: String,
+    #[prost(string, tag = "4")]
+    updated_at: String,
+    #[prost(string, optional, tag = "5")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoDeleted {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    deleted_at: String,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoRestored {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    name: String,
+    #[prost(string, tag = "3")]
+    description: String,
+    #[prost(string, tag = "4")]
+    restored_at: String,
+    #[prost(string, optional, tag = "5")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoCompleted {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(string, tag = "2")]
+    completed_at: String,
+    #[prost(string, optional, tag = "3")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
@@ -0,0 +1,114 @@
+use chrono::Utc;
+use opentelemetry::{trace::TraceContextExt, Context};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{env, fmt::Display};
+use uuid::Uuid;
+
+pub const SPEC_VERSION: &str = "1.0";
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+/// AMQP `content-type` of a structured mode message whose envelope is JSON.
+pub const CLOUDEVENTS_JSON_CONTENT_TYPE: &str = "application/cloudevents+json";
+
+const APP_NAME_ENV_KEY: &str = "APP_NAME";
+
+/// A message that can travel as the `data` of a CloudEvent.
+pub trait Event: Serialize + DeserializeOwned + Display {
+    /// CloudEvents `type`, e.g. `todos.todo.created`.
+    const TYPE: &'static str;
+
+    /// Schema version of the struct. Bumping it needs an `Upcaster` from the previous version.
+    const VERSION: u32 = 1;
+
+    /// CloudEvents `subject`, usually the id of the entity the event is about.
+    fn subject(&self) -> Option<String> {
+        None
+    }
+}
+
+/// CloudEvents 1.0 envelope, with the distributed tracing extension.
+///
+/// The struct is the structured content mode representation, attribute names are the JSON
+/// member names defined by the spec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudEvent<T> {
+    pub specversion: String,
+    pub id: String,
+    pub source: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datacontenttype: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+    /// Schema version of `data`, envelopes without it carry version 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataversion: Option<u32>,
+    pub data: T,
+}
+
+impl<T: Event> CloudEvent<T> {
+    /// Wraps the message with a new id, the current time and `/<APP_NAME>` as source.
+    pub fn new(data: T) -> CloudEvent<T> {
+        CloudEvent {
+            specversion: SPEC_VERSION.to_owned(),
+            id: Uuid::new_v4().to_string(),
+            source: format!(
+                "/{}",
+                env::var(APP_NAME_ENV_KEY).unwrap_or_else(|_| "todos".to_owned())
+            ),
+            ty: T::TYPE.to_owned(),
+            time: Some(Utc::now().to_rfc3339()),
+            subject: data.subject(),
+            datacontenttype: Some(JSON_CONTENT_TYPE.to_owned()),
+            traceparent: None,
+            dataversion: Some(T::VERSION),
+            data,
+        }
+    }
+}
+
+impl<T> CloudEvent<T> {
+    /// Sets the `traceparent` extension from the span in `ctx`, if any.
+    pub fn with_trace(mut self, ctx: &Context) -> CloudEvent<T> {
+        let span = ctx.span();
+        let span_ctx = span.span_context();
+
+        if span_ctx.is_valid() {
+            self.traceparent = Some(format!(
+                "00-{:032x}-{:016x}-{:02x}",
+                span_ctx.trace_id(),
+                span_ctx.span_id(),
+                span_ctx.trace_flags().to_u8()
+            ));
+        }
+
+        self
+    }
+
+    /// Same envelope carrying another representation of the data.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> CloudEvent<U> {
+        CloudEvent {
+            specversion: self.specversion,
+            id: self.id,
+            source: self.source,
+            ty: self.ty,
+            time: self.time,
+            subject: self.subject,
+            datacontenttype: self.datacontenttype,
+            traceparent: self.traceparent,
+            dataversion: self.dataversion,
+            data: f(self.data),
+        }
+    }
+}
+
+/// The event type, which is also what the AMQP publisher sets as the message `type` property.
+impl<T> Display for CloudEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ty)
+    }
+}
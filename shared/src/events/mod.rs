@@ -0,0 +1,13 @@
+mod cloud_event;
+mod schema;
+
+pub use cloud_event::{
+    CloudEvent, Event, CLOUDEVENTS_JSON_CONTENT_TYPE, JSON_CONTENT_TYPE, SPEC_VERSION,
+};
+pub use schema::{downcast, upcast, SchemaVersions, Upcaster, UPCASTERS};
+
+pub const TODO_CREATED_EVENT: &str = "todos.todo.created";
+pub const TODO_UPDATED_EVENT: &str = "todos.todo.updated";
+pub const TODO_DELETED_EVENT: &str = "todos.todo.deleted";
+pub const TODO_RESTORED_EVENT: &str = "todos.todo.restored";
+pub const TODO_COMPLETED_EVENT: &str = "todos.todo.completed";
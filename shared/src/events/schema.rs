@@ -0,0 +1,136 @@
+//! Schema versions of event data.
+//!
+//! Every change to an event struct bumps its `Event::VERSION` and registers an `Upcaster` that
+//! turns the previous version into the new one, and back. Consumers upcast whatever version they
+//! receive to the struct they were built with, producers downcast to the version configured in
+//! `EVENTS_SCHEMA_VERSIONS` until every consumer is deployed with the new struct.
+use super::{Event, TODO_CREATED_EVENT};
+use serde_json::Value;
+use std::{collections::HashMap, env};
+use tracing::warn;
+
+const SCHEMA_VERSIONS_ENV_KEY: &str = "EVENTS_SCHEMA_VERSIONS";
+
+/// Migrates the data of `event_type` between version `from` and `from + 1`.
+pub struct Upcaster {
+    pub event_type: &'static str,
+    pub from: u32,
+    pub upcast: fn(Value) -> Value,
+    pub downcast: fn(Value) -> Value,
+}
+
+pub const UPCASTERS: &[Upcaster] = &[
+    // v2 added the owner of the ToDo
+    Upcaster {
+        event_type: TODO_CREATED_EVENT,
+        from: 1,
+        upcast: |data| with_field(data, "owner_id", Value::Null),
+        downcast: |data| without_field(data, "owner_id"),
+    },
+];
+
+fn with_field(data: Value, name: &str, default: Value) -> Value {
+    match data {
+        Value::Object(mut fields) => {
+            fields.entry(name).or_insert(default);
+            Value::Object(fields)
+        }
+        other => other,
+    }
+}
+
+fn without_field(data: Value, name: &str) -> Value {
+    match data {
+        Value::Object(mut fields) => {
+            fields.remove(name);
+            Value::Object(fields)
+        }
+        other => other,
+    }
+}
+
+fn upcaster(event_type: &str, from: u32) -> Option<&'static Upcaster> {
+    UPCASTERS
+        .iter()
+        .find(|u| u.event_type == event_type && u.from == from)
+}
+
+/// Runs the chain of upcasters from `version` to `current`, `None` when some step is missing,
+/// e.g. a message from a producer newer than this consumer.
+pub fn upcast(event_type: &str, version: u32, current: u32, data: Value) -> Option<Value> {
+    if version == 0 || version > current {
+        return None;
+    }
+
+    (version..current).try_fold(data, |data, from| {
+        upcaster(event_type, from).map(|u| (u.upcast)(data))
+    })
+}
+
+/// Runs the chain of downcasters from `current` to `target`, `None` when some step is missing.
+pub fn downcast(event_type: &str, current: u32, target: u32, data: Value) -> Option<Value> {
+    if target == 0 || target > current {
+        return None;
+    }
+
+    (target..current).rev().try_fold(data, |data, from| {
+        upcaster(event_type, from).map(|u| (u.downcast)(data))
+    })
+}
+
+/// Version each event type is published with, from `EVENTS_SCHEMA_VERSIONS`, e.g.
+/// `todos.todo.created=1`. Types not listed are published with their current version.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaVersions {
+    versions: HashMap<String, u32>,
+}
+
+impl SchemaVersions {
+    pub fn from_env() -> SchemaVersions {
+        SchemaVersions::parse(&env::var(SCHEMA_VERSIONS_ENV_KEY).unwrap_or_default())
+    }
+
+    pub fn parse(value: &str) -> SchemaVersions {
+        let mut versions: HashMap<String, u32> = HashMap::new();
+
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry
+                .split_once('=')
+                .map(|(t, v)| (t.trim(), v.trim().parse()))
+            {
+                Some((event_type, Ok(version))) => {
+                    versions.insert(event_type.to_owned(), version);
+                }
+                _ => warn!(entry = entry, "ignoring invalid schema version"),
+            }
+        }
+
+        SchemaVersions { versions }
+    }
+
+    /// The configured version for `E`, or its current one when the configured version can not
+    /// be produced.
+    pub fn producer_version<E: Event>(&self) -> u32 {
+        match self.versions.get(E::TYPE) {
+            None => E::VERSION,
+            Some(&version) if version == E::VERSION => version,
+            Some(&version) => {
+                let reachable = version > 0
+                    && version < E::VERSION
+                    && (version..E::VERSION).all(|from| upcaster(E::TYPE, from).is_some());
+
+                if reachable {
+                    version
+                } else {
+                    warn!(
+                        event_type = E::TYPE,
+                        version = version,
+                        current = E::VERSION,
+                        "unsupported producer schema version, using the current one"
+                    );
+                    E::VERSION
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod amqp;
+pub mod auth;
+pub mod codecs;
+pub mod events;
 pub mod models;
 pub mod repositories;
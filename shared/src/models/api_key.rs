@@ -0,0 +1,63 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+pub const API_KEY_PREFIX: &str = "tdk_";
+const API_KEY_SECRET_LEN: usize = 40;
+
+pub struct CreateApiKey {
+    pub name: String,
+    pub key_hash: String,
+    pub owner_id: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<String>,
+    /// Subject of the admin minting the key.
+    pub created_by: String,
+}
+
+#[derive(Default, Clone)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub owner_id: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+    pub created_by: Option<String>,
+}
+
+impl ApiKey {
+    /// Whether `last_used_at` is due for a refresh. It is only written once per minute, so
+    /// authenticating with a key does not cost a write on every request.
+    pub fn last_use_outdated(&self, now: DateTime<Utc>) -> bool {
+        match self
+            .last_used_at
+            .as_deref()
+            .map(DateTime::parse_from_rfc3339)
+        {
+            Some(Ok(last_used_at)) => {
+                now - last_used_at.with_timezone(&Utc) >= Duration::minutes(1)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Generates a new plaintext API key. Only its hash is ever stored.
+pub fn generate_api_key() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_SECRET_LEN)
+        .map(char::from)
+        .collect();
+
+    format!("{}{}", API_KEY_PREFIX, secret)
+}
+
+/// Keys are random and long enough that a plain SHA-256 is sufficient, which also lets us look
+/// them up by hash.
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
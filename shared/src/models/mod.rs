@@ -1 +1,3 @@
+pub mod api_key;
 pub mod todo;
+pub mod todo_stats;
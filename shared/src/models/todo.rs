@@ -1,14 +1,17 @@
-use amqp::errors::AmqpError;
+use crate::events::{
+    Event, TODO_COMPLETED_EVENT, TODO_CREATED_EVENT, TODO_DELETED_EVENT, TODO_RESTORED_EVENT,
+    TODO_UPDATED_EVENT,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
-use tracing::error;
 
 pub struct CreateTodo {
     pub name: String,
     pub description: String,
+    pub owner_id: Option<String>,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Todo {
     pub id: String,
     pub name: String,
@@ -16,6 +19,8 @@ pub struct Todo {
     pub created_at: String,
     pub updated_at: String,
     pub deleted_at: Option<String>,
+    pub owner_id: Option<String>,
+    pub completed_at: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -24,11 +29,21 @@ pub struct TodoCreatedMessage {
     pub name: String,
     pub description: String,
     pub created_at: String,
+    pub owner_id: Option<String>,
 }
 
 impl Display for TodoCreatedMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "SimpleAmqpMessage")
+        write!(f, "{}", Self::TYPE)
+    }
+}
+
+impl Event for TodoCreatedMessage {
+    const TYPE: &'static str = TODO_CREATED_EVENT;
+    const VERSION: u32 = 2;
+
+    fn subject(&self) -> Option<String> {
+        Some(self.id.clone())
     }
 }
 
@@ -39,24 +54,149 @@ impl From<&Todo> for TodoCreatedMessage {
             name: value.name.clone(),
             description: value.description.clone(),
             created_at: value.created_at.clone(),
+            owner_id: value.owner_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TodoUpdatedMessage {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub updated_at: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+}
+
+impl Display for TodoUpdatedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Self::TYPE)
+    }
+}
+
+impl Event for TodoUpdatedMessage {
+    const TYPE: &'static str = TODO_UPDATED_EVENT;
+
+    fn subject(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+impl From<&Todo> for TodoUpdatedMessage {
+    fn from(value: &Todo) -> Self {
+        TodoUpdatedMessage {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            updated_at: value.updated_at.clone(),
+            owner_id: value.owner_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TodoDeletedMessage {
+    pub id: String,
+    pub deleted_at: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+}
+
+impl Display for TodoDeletedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Self::TYPE)
+    }
+}
+
+impl Event for TodoDeletedMessage {
+    const TYPE: &'static str = TODO_DELETED_EVENT;
+
+    fn subject(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+impl From<&Todo> for TodoDeletedMessage {
+    fn from(value: &Todo) -> Self {
+        TodoDeletedMessage {
+            id: value.id.clone(),
+            deleted_at: value
+                .deleted_at
+                .clone()
+                .unwrap_or_else(|| value.updated_at.clone()),
+            owner_id: value.owner_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TodoRestoredMessage {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub restored_at: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+}
+
+impl Display for TodoRestoredMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Self::TYPE)
+    }
+}
+
+impl Event for TodoRestoredMessage {
+    const TYPE: &'static str = TODO_RESTORED_EVENT;
+
+    fn subject(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+impl From<&Todo> for TodoRestoredMessage {
+    fn from(value: &Todo) -> Self {
+        TodoRestoredMessage {
+            id: value.id.clone(),
+            name: value.name.clone(),
+            description: value.description.clone(),
+            restored_at: value.updated_at.clone(),
+            owner_id: value.owner_id.clone(),
         }
     }
 }
 
-impl TryFrom<&[u8]> for TodoCreatedMessage {
-    type Error = AmqpError;
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TodoCompletedMessage {
+    pub id: String,
+    pub completed_at: String,
+    #[serde(default)]
+    pub owner_id: Option<String>,
+}
+
+impl Display for TodoCompletedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Self::TYPE)
+    }
+}
+
+impl Event for TodoCompletedMessage {
+    const TYPE: &'static str = TODO_COMPLETED_EVENT;
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        match serde_json::from_slice::<TodoCreatedMessage>(value) {
-            Ok(v) => Ok(v),
-            Err(err) => {
-                error!(
-                    error = err.to_string(),
-                    payload = format!("{:?}", value),
-                    "parsing error"
-                );
-                Err(AmqpError::AckMessageDeserializationError(err.to_string()))
-            }
+    fn subject(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
+impl From<&Todo> for TodoCompletedMessage {
+    fn from(value: &Todo) -> Self {
+        TodoCompletedMessage {
+            id: value.id.clone(),
+            completed_at: value
+                .completed_at
+                .clone()
+                .unwrap_or_else(|| value.updated_at.clone()),
+            owner_id: value.owner_id.clone(),
         }
     }
 }
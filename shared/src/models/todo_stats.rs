@@ -0,0 +1,44 @@
+/// ToDo's created, deleted and completed by an owner in a UTC day, formatted as `YYYY-MM-DD`.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct TodoStats {
+    pub owner_id: String,
+    pub day: String,
+    pub created: i32,
+    pub deleted: i32,
+    pub completed: i32,
+}
+
+/// Change of a ToDo counted in `TodoStats`, each counted at most once per ToDo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoChange {
+    Created,
+    Deleted,
+    Completed,
+}
+
+impl TodoChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoChange::Created => "created",
+            TodoChange::Deleted => "deleted",
+            TodoChange::Completed => "completed",
+        }
+    }
+
+    /// Counts of a single change on `day`.
+    pub fn delta(&self, owner_id: String, day: String) -> TodoStats {
+        let mut delta = TodoStats {
+            owner_id,
+            day,
+            ..Default::default()
+        };
+
+        match self {
+            TodoChange::Created => delta.created = 1,
+            TodoChange::Deleted => delta.deleted = 1,
+            TodoChange::Completed => delta.completed = 1,
+        }
+
+        delta
+    }
+}
@@ -0,0 +1,15 @@
+use crate::models::api_key::{ApiKey, CreateApiKey};
+use async_trait::async_trait;
+use opentelemetry::Context;
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync + 'static {
+    async fn create(&self, ctx: &Context, key: &CreateApiKey) -> Result<ApiKey, String>;
+    async fn list(&self, ctx: &Context) -> Result<Vec<ApiKey>, String>;
+    /// Returns the key matching the hash only while it is neither revoked nor expired.
+    async fn get_active_by_hash(&self, ctx: &Context, hash: &str)
+        -> Result<Option<ApiKey>, String>;
+    async fn touch_last_used(&self, ctx: &Context, id: &str) -> Result<(), String>;
+    /// Revokes an active key, returning whether there was one with this ID.
+    async fn revoke(&self, ctx: &Context, id: &str) -> Result<bool, String>;
+}
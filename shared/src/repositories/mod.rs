@@ -1,3 +1,7 @@
+mod api_key;
 mod todo;
+mod todo_stats;
 
+pub use api_key::ApiKeyRepository;
 pub use todo::TodoRepository;
+pub use todo_stats::TodoStatsRepository;
@@ -12,5 +12,9 @@ pub trait TodoRepository: Send + Sync + 'static {
         limit: i32,
         offset: i32,
     ) -> Result<Vec<Todo>, String>;
-    async fn delete(&self, ctx: &Context, id: &str) -> Result<(), String>;
+    /// Soft deletes the ToDo, returning it unless it did not exist or was already deleted.
+    async fn delete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String>;
+    /// Marks the ToDo as completed, returning it unless it did not exist, was deleted or was
+    /// already completed.
+    async fn complete(&self, ctx: &Context, id: &str) -> Result<Option<Todo>, String>;
 }
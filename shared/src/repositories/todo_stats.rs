@@ -0,0 +1,16 @@
+use crate::models::todo_stats::TodoStats;
+use async_trait::async_trait;
+use opentelemetry::Context;
+
+#[async_trait]
+pub trait TodoStatsRepository: Send + Sync + 'static {
+    /// Days of the owner between `from` and `to`, both inclusive and formatted as `YYYY-MM-DD`,
+    /// ordered by day. Days without activity are not returned.
+    async fn list_by_owner(
+        &self,
+        ctx: &Context,
+        owner_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<TodoStats>, String>;
+}
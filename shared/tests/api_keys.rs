@@ -0,0 +1,19 @@
+use chrono::{Duration, Utc};
+use shared::models::api_key::ApiKey;
+
+fn used_at(last_used_at: Option<String>) -> ApiKey {
+    ApiKey {
+        last_used_at,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn last_use_is_refreshed_at_most_once_a_minute() {
+    let now = Utc::now();
+
+    assert!(used_at(None).last_use_outdated(now));
+    assert!(!used_at(Some((now - Duration::seconds(30)).to_rfc3339())).last_use_outdated(now));
+    assert!(used_at(Some((now - Duration::seconds(61)).to_rfc3339())).last_use_outdated(now));
+    assert!(used_at(Some("not a date".to_owned())).last_use_outdated(now));
+}
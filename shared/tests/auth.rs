@@ -0,0 +1,32 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::json;
+use shared::auth::TokenClaims;
+
+fn token(claims: serde_json::Value) -> String {
+    format!(
+        "header.{}.signature",
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    )
+}
+
+#[test]
+fn scopes_come_from_the_scope_claim_and_permissions() {
+    let claims = TokenClaims::from_token(&token(json!({
+        "sub": "auth0|owner",
+        "scope": "todos:read todos:write",
+        "permissions": ["todos:admin"],
+    })))
+    .unwrap();
+
+    assert_eq!(claims.sub, "auth0|owner");
+    assert_eq!(
+        claims.scopes(),
+        vec!["todos:read", "todos:write", "todos:admin"]
+    );
+}
+
+#[test]
+fn tokens_without_a_json_payload_have_no_claims() {
+    assert!(TokenClaims::from_token("opaque-token").is_none());
+    assert!(TokenClaims::from_token("header.not-base64!.signature").is_none());
+}
@@ -0,0 +1,315 @@
+use amqp::{
+    errors::AmqpError,
+    publisher::{Payload, Publisher},
+};
+use async_trait::async_trait;
+use lapin::{
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties,
+};
+use opentelemetry::Context;
+use serde_json::Value;
+use shared::{
+    amqp::{
+        decode_event, publish_event, ContentMode, EnvelopeError, EventPublisher, EventsConfigs,
+        PublishError,
+    },
+    codecs::{
+        CodecKind, EventCodecs, CLOUDEVENTS_MSGPACK_CONTENT_TYPE,
+        CLOUDEVENTS_PROTOBUF_CONTENT_TYPE, MSGPACK_CONTENT_TYPE, PROTOBUF_CONTENT_TYPE,
+    },
+    events::{SchemaVersions, JSON_CONTENT_TYPE, TODO_CREATED_EVENT},
+    models::todo::TodoCreatedMessage,
+};
+use std::{collections::HashMap, env, sync::Mutex};
+
+/// Keeps every published message as the properties a consumer would receive it with.
+#[derive(Default)]
+struct CapturingPublisher {
+    published: Mutex<Vec<(Vec<u8>, BasicProperties)>>,
+}
+
+#[async_trait]
+impl EventPublisher for CapturingPublisher {
+    async fn publish(
+        &self,
+        _ctx: &Context,
+        _exchange: &str,
+        _routing_key: &str,
+        payload: &Payload,
+        content_type: &str,
+        headers: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), PublishError> {
+        let mut table = FieldTable::default();
+        for (name, value) in headers.unwrap_or_default() {
+            table.insert(ShortString::from(name), value);
+        }
+
+        let properties = BasicProperties::default()
+            .with_content_type(ShortString::from(content_type))
+            .with_headers(table);
+
+        self.published
+            .lock()
+            .unwrap()
+            .push((payload.payload.clone(), properties));
+
+        Ok(())
+    }
+}
+
+/// A ruskit publisher, sending events through the blanket `EventPublisher`.
+#[derive(Default)]
+struct AmqpPublisher {
+    published: Mutex<Vec<Vec<u8>>>,
+}
+
+#[async_trait]
+impl Publisher for AmqpPublisher {
+    async fn publish(
+        &self,
+        _ctx: &Context,
+        _exchange: &str,
+        _key: &str,
+        msg: &Payload,
+        _params: Option<HashMap<&str, AMQPValue>>,
+    ) -> Result<(), AmqpError> {
+        self.published.lock().unwrap().push(msg.payload.clone());
+
+        Ok(())
+    }
+}
+
+fn message() -> TodoCreatedMessage {
+    TodoCreatedMessage {
+        id: "todo".to_owned(),
+        name: "name".to_owned(),
+        description: "description".to_owned(),
+        created_at: "2023-04-20T12:00:00+00:00".to_owned(),
+        owner_id: None,
+    }
+}
+
+fn binary() -> EventsConfigs {
+    EventsConfigs {
+        content_mode: ContentMode::Binary,
+        schema_versions: SchemaVersions::default(),
+        codecs: EventCodecs::default(),
+    }
+}
+
+async fn publish(cfg: &EventsConfigs) -> (Vec<u8>, BasicProperties) {
+    publish_to(cfg, "cloud-events-test").await
+}
+
+async fn publish_to(cfg: &EventsConfigs, routing_key: &str) -> (Vec<u8>, BasicProperties) {
+    let publisher = CapturingPublisher::default();
+
+    publish_event(
+        &publisher,
+        cfg,
+        &Context::new(),
+        "exchange",
+        routing_key,
+        message(),
+    )
+    .await
+    .unwrap();
+
+    let mut published = publisher.published.into_inner().unwrap();
+    assert_eq!(published.len(), 1);
+    published.remove(0)
+}
+
+fn header<'a>(properties: &'a BasicProperties, name: &str) -> Option<&'a AMQPValue> {
+    properties.headers().as_ref()?.inner().get(name)
+}
+
+fn headers(attributes: &[(&str, &str)]) -> BasicProperties {
+    let mut headers = FieldTable::default();
+    for (name, value) in attributes {
+        headers.insert(
+            ShortString::from(*name),
+            AMQPValue::LongString(LongString::from(*value)),
+        );
+    }
+
+    BasicProperties::default().with_headers(headers)
+}
+
+#[tokio::test]
+async fn binary_events_carry_only_the_data_in_the_body() {
+    let (body, properties) = publish(&binary()).await;
+
+    let data: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(data, serde_json::to_value(message()).unwrap());
+
+    for name in [
+        "cloudEvents:specversion",
+        "cloudEvents:id",
+        "cloudEvents:source",
+        "cloudEvents:type",
+        "cloudEvents:datacontenttype",
+    ] {
+        assert!(header(&properties, name).is_some(), "missing {}", name);
+    }
+    assert_eq!(
+        header(&properties, "cloudEvents:type"),
+        Some(&AMQPValue::LongString(LongString::from(TODO_CREATED_EVENT)))
+    );
+}
+
+#[tokio::test]
+async fn binary_events_round_trip_through_decode_event() {
+    let (body, properties) = publish(&binary()).await;
+
+    let event = decode_event(&body, &properties).unwrap();
+
+    assert_eq!(event.ty, TODO_CREATED_EVENT);
+    assert_eq!(event.datacontenttype.as_deref(), Some(JSON_CONTENT_TYPE));
+    assert_eq!(event.data_as::<TodoCreatedMessage>().unwrap(), message());
+}
+
+#[tokio::test]
+async fn structured_events_carry_no_cloudevents_headers() {
+    let (body, properties) = publish(&EventsConfigs::default()).await;
+
+    assert!(header(&properties, "cloudEvents:specversion").is_none());
+    assert_eq!(
+        decode_event(&body, &properties)
+            .unwrap()
+            .data_as::<TodoCreatedMessage>()
+            .unwrap(),
+        message()
+    );
+}
+
+#[tokio::test]
+async fn events_are_encoded_with_the_codec_of_their_routing_key() {
+    for content_mode in [ContentMode::Structured, ContentMode::Binary] {
+        let cfg = EventsConfigs {
+            content_mode,
+            codecs: EventCodecs::new(CodecKind::MessagePack)
+                .with("cloud-events.protobuf", CodecKind::Protobuf),
+            ..EventsConfigs::default()
+        };
+
+        for (routing_key, content_type, structured_content_type) in [
+            (
+                "cloud-events.protobuf",
+                PROTOBUF_CONTENT_TYPE,
+                CLOUDEVENTS_PROTOBUF_CONTENT_TYPE,
+            ),
+            (
+                "cloud-events.other",
+                MSGPACK_CONTENT_TYPE,
+                CLOUDEVENTS_MSGPACK_CONTENT_TYPE,
+            ),
+        ] {
+            let (body, properties) = publish_to(&cfg, routing_key).await;
+
+            assert_eq!(
+                properties.content_type().as_ref().map(|c| c.as_str()),
+                Some(match content_mode {
+                    ContentMode::Structured => structured_content_type,
+                    ContentMode::Binary => content_type,
+                }),
+                "{:?} {}",
+                content_mode,
+                routing_key
+            );
+
+            let event = decode_event(&body, &properties).unwrap();
+            assert_eq!(event.datacontenttype.as_deref(), Some(content_type));
+            assert_eq!(event.data_as::<TodoCreatedMessage>().unwrap(), message());
+        }
+    }
+}
+
+#[tokio::test]
+async fn amqp_publishers_reject_events_not_encoded_as_json() {
+    let publisher = AmqpPublisher::default();
+
+    for kind in [CodecKind::Json, CodecKind::MessagePack, CodecKind::Protobuf] {
+        let cfg = EventsConfigs {
+            codecs: EventCodecs::new(kind),
+            ..EventsConfigs::default()
+        };
+
+        let result = publish_event(
+            &publisher,
+            &cfg,
+            &Context::new(),
+            "exchange",
+            "cloud-events-test",
+            message(),
+        )
+        .await;
+
+        match kind {
+            CodecKind::Json => assert!(result.is_ok(), "{:?}", result),
+            _ => assert!(
+                matches!(
+                    &result,
+                    Err(PublishError::UnsupportedContentType(c)) if c == kind.codec().structured_content_type()
+                ),
+                "{:?}: {:?}",
+                kind,
+                result
+            ),
+        }
+    }
+
+    assert_eq!(publisher.published.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn binary_deliveries_missing_a_required_header_are_rejected() {
+    let body = serde_json::to_vec(&message()).unwrap();
+
+    let properties = headers(&[
+        ("cloudEvents:specversion", "1.0"),
+        ("cloudEvents:source", "/todos"),
+        ("cloudEvents:type", TODO_CREATED_EVENT),
+    ]);
+
+    assert_eq!(
+        decode_event(&body, &properties),
+        Err(EnvelopeError::MissingAttribute("id"))
+    );
+}
+
+#[test]
+fn binary_deliveries_with_an_invalid_dataversion_are_rejected() {
+    let body = serde_json::to_vec(&message()).unwrap();
+
+    let properties = headers(&[
+        ("cloudEvents:specversion", "1.0"),
+        ("cloudEvents:id", "id"),
+        ("cloudEvents:source", "/todos"),
+        ("cloudEvents:type", TODO_CREATED_EVENT),
+        ("cloudEvents:dataversion", "two"),
+    ]);
+
+    assert_eq!(
+        decode_event(&body, &properties),
+        Err(EnvelopeError::InvalidData(
+            "invalid dataversion: two".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn unknown_content_modes_are_rejected() {
+    assert_eq!("Binary".parse::<ContentMode>(), Ok(ContentMode::Binary));
+    assert!("binray".parse::<ContentMode>().is_err());
+
+    env::set_var("EVENTS_CONTENT_MODE", "binray");
+    assert!(EventsConfigs::from_env().is_err());
+
+    env::set_var("EVENTS_CONTENT_MODE", "");
+    assert_eq!(
+        EventsConfigs::from_env().unwrap().content_mode,
+        ContentMode::Structured
+    );
+}
@@ -0,0 +1,216 @@
+use lapin::{
+    types::{AMQPValue, FieldTable, LongString, ShortString},
+    BasicProperties,
+};
+use serde_json::{json, Value};
+use shared::{
+    amqp::{decode_event, EnvelopeError, EventsConfigs},
+    codecs::{
+        codec_for, CodecError, CodecKind, EventCodecs, MSGPACK_CONTENT_TYPE, PROTOBUF_CONTENT_TYPE,
+    },
+    events::{CloudEvent, JSON_CONTENT_TYPE, TODO_COMPLETED_EVENT, TODO_CREATED_EVENT},
+    models::todo::TodoCreatedMessage,
+};
+use std::env;
+
+const CODECS: [CodecKind; 3] = [CodecKind::Json, CodecKind::MessagePack, CodecKind::Protobuf];
+
+fn envelope() -> CloudEvent<Value> {
+    CloudEvent {
+        specversion: "1.0".to_owned(),
+        id: "b5e1c0a4-5c0f-4d8e-9d55-0d3c2a8c7f10".to_owned(),
+        source: "/todos".to_owned(),
+        ty: TODO_CREATED_EVENT.to_owned(),
+        time: Some("2023-04-20T12:00:00+00:00".to_owned()),
+        subject: Some("todo".to_owned()),
+        datacontenttype: None,
+        traceparent: None,
+        dataversion: Some(2),
+        data: json!({
+            "id": "todo",
+            "name": "name",
+            "description": "description",
+            "created_at": "2023-04-20T12:00:00+00:00",
+            "owner_id": "owner",
+        }),
+    }
+}
+
+fn properties(content_type: &str) -> BasicProperties {
+    BasicProperties::default().with_content_type(ShortString::from(content_type))
+}
+
+fn binary_properties(content_type: &str, dataversion: &str) -> BasicProperties {
+    let mut headers = FieldTable::default();
+    for (name, value) in [
+        ("cloudEvents:specversion", "1.0"),
+        ("cloudEvents:id", "id"),
+        ("cloudEvents:source", "/todos"),
+        ("cloudEvents:type", TODO_CREATED_EVENT),
+        ("cloudEvents:datacontenttype", content_type),
+        ("cloudEvents:dataversion", dataversion),
+    ] {
+        headers.insert(
+            ShortString::from(name),
+            AMQPValue::LongString(LongString::from(value)),
+        );
+    }
+
+    BasicProperties::default().with_headers(headers)
+}
+
+#[test]
+fn every_codec_round_trips_an_envelope() {
+    for kind in CODECS {
+        let codec = kind.codec();
+        let mut event = envelope();
+        event.datacontenttype = Some(codec.content_type().to_owned());
+
+        let encoded = codec.encode_event(&event).unwrap();
+
+        assert_eq!(codec.decode_event(&encoded).unwrap(), event, "{:?}", kind);
+        for content_type in [codec.content_type(), codec.structured_content_type()] {
+            assert_eq!(
+                codec_for(Some(content_type)).map(|c| c.content_type()),
+                Some(codec.content_type())
+            );
+        }
+    }
+}
+
+#[test]
+fn structured_envelopes_are_decoded_with_their_content_type() {
+    for kind in CODECS {
+        let codec = kind.codec();
+        let body = codec.encode_event(&envelope()).unwrap();
+
+        let event = decode_event(&body, &properties(codec.structured_content_type()))
+            .unwrap_or_else(|err| panic!("{:?}: {}", kind, err));
+
+        assert_eq!(event.dataversion, Some(2));
+        assert_eq!(
+            event.data_as::<TodoCreatedMessage>().unwrap().name,
+            "name",
+            "{:?}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn binary_data_is_decoded_with_the_datacontenttype() {
+    let codec = CodecKind::Protobuf.codec();
+    let body = codec
+        .encode_data(TODO_CREATED_EVENT, &envelope().data)
+        .unwrap();
+
+    let event = decode_event(&body, &binary_properties(PROTOBUF_CONTENT_TYPE, "2")).unwrap();
+
+    assert_eq!(event.data, envelope().data);
+}
+
+#[test]
+fn protobuf_data_is_the_message_of_its_event_type() {
+    let codec = CodecKind::Protobuf.codec();
+    let data = json!({ "id": "todo", "completed_at": "2023-04-20T12:00:00+00:00" });
+
+    let body = codec.encode_data(TODO_COMPLETED_EVENT, &data).unwrap();
+
+    // fields travel by tag, not by name
+    assert!(!String::from_utf8_lossy(&body).contains("completed_at"));
+    assert_eq!(
+        codec.decode_data(TODO_COMPLETED_EVENT, &body).unwrap(),
+        data
+    );
+
+    assert!(matches!(
+        codec.encode_data("todos.todo.archived", &data),
+        Err(CodecError::Encode(_))
+    ));
+    // a field the message lacks is not silently dropped
+    assert!(matches!(
+        codec.encode_data(
+            TODO_COMPLETED_EVENT,
+            &json!({ "id": "todo", "completed_at": "now", "completed_by": "owner" })
+        ),
+        Err(CodecError::Encode(_))
+    ));
+}
+
+#[test]
+fn older_protobuf_data_is_upcasted() {
+    let codec = CodecKind::Protobuf.codec();
+    let mut data = envelope().data;
+    data.as_object_mut().unwrap().remove("owner_id");
+    let body = codec.encode_data(TODO_CREATED_EVENT, &data).unwrap();
+
+    let event = decode_event(&body, &binary_properties(PROTOBUF_CONTENT_TYPE, "1")).unwrap();
+
+    assert_eq!(
+        event.data_as::<TodoCreatedMessage>().unwrap().owner_id,
+        None
+    );
+}
+
+#[test]
+fn content_type_parameters_are_ignored() {
+    assert_eq!(
+        codec_for(Some("application/json; charset=utf-8")).map(|c| c.content_type()),
+        Some(JSON_CONTENT_TYPE)
+    );
+    assert_eq!(
+        codec_for(Some("application/x-msgpack")).map(|c| c.content_type()),
+        Some(MSGPACK_CONTENT_TYPE)
+    );
+    assert_eq!(
+        codec_for(None).map(|c| c.content_type()),
+        Some(JSON_CONTENT_TYPE)
+    );
+}
+
+#[test]
+fn unknown_content_types_are_rejected() {
+    let body = serde_json::to_vec(&envelope()).unwrap();
+
+    assert_eq!(
+        decode_event(&body, &properties("application/avro")),
+        Err(EnvelopeError::UnsupportedContentType(
+            "application/avro".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn producer_codecs_are_read_from_env_by_routing_key() {
+    env::set_var("EVENTS_CODEC", "msgpack");
+    env::set_var("TODO_CREATED_EVENTS_CODEC", "protobuf");
+
+    let codecs = EventCodecs::from_env().unwrap();
+    assert_eq!(codecs.for_routing_key("todo.created"), CodecKind::Protobuf);
+    assert_eq!(
+        codecs.for_routing_key("todo.deleted"),
+        CodecKind::MessagePack
+    );
+    assert_eq!(EventsConfigs::from_env().unwrap().codecs, codecs);
+
+    env::set_var("TODO_DELETED_EVENTS_CODEC", "protobuff");
+    assert_eq!(
+        EventCodecs::from_env(),
+        Err("TODO_DELETED_EVENTS_CODEC: unknown codec: protobuff".to_owned())
+    );
+    assert!(EventsConfigs::from_env().is_err());
+
+    for key in [
+        "EVENTS_CODEC",
+        "TODO_CREATED_EVENTS_CODEC",
+        "TODO_DELETED_EVENTS_CODEC",
+    ] {
+        env::remove_var(key);
+    }
+    assert_eq!(
+        EventCodecs::from_env()
+            .unwrap()
+            .for_routing_key("todo.created"),
+        CodecKind::Json
+    );
+}
@@ -0,0 +1,124 @@
+use lapin::BasicProperties;
+use serde_json::{json, Value};
+use shared::{
+    amqp::{decode_event, EnvelopeError},
+    events::{downcast, CloudEvent, Event, SchemaVersions, TODO_CREATED_EVENT},
+    models::todo::{
+        TodoCompletedMessage, TodoCreatedMessage, TodoDeletedMessage, TodoRestoredMessage,
+        TodoUpdatedMessage,
+    },
+};
+use std::{fs, path::PathBuf};
+
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/events");
+
+/// Stored envelopes of `event_type`, one file per schema version named `<type>.v<version>.json`.
+fn fixtures(event_type: &str) -> Vec<(u32, CloudEvent<Value>)> {
+    let prefix = format!("{}.v", event_type);
+
+    let mut fixtures: Vec<(u32, CloudEvent<Value>)> = fs::read_dir(FIXTURES_DIR)
+        .expect("missing fixtures dir")
+        .map(|entry| entry.expect("unreadable fixture").path())
+        .filter_map(|path: PathBuf| {
+            let name = path.file_name()?.to_str()?.to_owned();
+            let version = name
+                .strip_prefix(&prefix)?
+                .strip_suffix(".json")?
+                .parse::<u32>()
+                .ok()?;
+
+            let body = fs::read(&path).expect("unreadable fixture");
+            let event = decode_event(&body, &BasicProperties::default())
+                .unwrap_or_else(|err| panic!("{}: {}", name, err));
+
+            Some((version, event))
+        })
+        .collect();
+
+    fixtures.sort_by_key(|(version, _)| *version);
+    fixtures
+}
+
+/// Reads every historical fixture of `E` as the current struct.
+fn read_every_version<E: Event>() -> Vec<E> {
+    let fixtures = fixtures(E::TYPE);
+
+    let versions: Vec<u32> = fixtures.iter().map(|(version, _)| *version).collect();
+    assert_eq!(
+        versions,
+        (1..=E::VERSION).collect::<Vec<u32>>(),
+        "{} needs one fixture per schema version",
+        E::TYPE
+    );
+
+    fixtures
+        .iter()
+        .map(|(version, event)| {
+            assert_eq!(event.dataversion.unwrap_or(1), *version, "{}", E::TYPE);
+
+            event
+                .data_as::<E>()
+                .unwrap_or_else(|err| panic!("{} v{}: {}", E::TYPE, version, err))
+        })
+        .collect()
+}
+
+#[test]
+fn todo_created_reads_from_every_version() {
+    let messages = read_every_version::<TodoCreatedMessage>();
+
+    assert_eq!(messages[0].name, "first todo");
+    assert_eq!(messages[0].owner_id, None);
+    assert_eq!(messages[1].owner_id.as_deref(), Some("auth0|owner"));
+}
+
+#[test]
+fn other_todo_events_read_from_every_version() {
+    read_every_version::<TodoUpdatedMessage>();
+    read_every_version::<TodoDeletedMessage>();
+    read_every_version::<TodoRestoredMessage>();
+    read_every_version::<TodoCompletedMessage>();
+}
+
+#[test]
+fn todo_created_downcasts_to_v1() {
+    let current = json!({
+        "id": "todo",
+        "name": "name",
+        "description": "description",
+        "created_at": "2023-04-20T12:00:00+00:00",
+        "owner_id": "auth0|owner",
+    });
+
+    let v1 = downcast(TODO_CREATED_EVENT, TodoCreatedMessage::VERSION, 1, current).unwrap();
+
+    assert_eq!(v1.get("owner_id"), None);
+    assert_eq!(v1.get("name"), Some(&json!("name")));
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let (_, mut event) = fixtures(TODO_CREATED_EVENT).pop().unwrap();
+    event.dataversion = Some(TodoCreatedMessage::VERSION + 1);
+
+    assert_eq!(
+        event.data_as::<TodoCreatedMessage>(),
+        Err(EnvelopeError::UnsupportedSchemaVersion(
+            TODO_CREATED_EVENT.to_owned(),
+            TodoCreatedMessage::VERSION + 1
+        ))
+    );
+}
+
+#[test]
+fn producer_version_comes_from_configuration() {
+    let version = |cfg: &str| SchemaVersions::parse(cfg).producer_version::<TodoCreatedMessage>();
+
+    assert_eq!(version(""), TodoCreatedMessage::VERSION);
+    assert_eq!(version("todos.todo.created=1"), 1);
+    assert_eq!(version("todos.todo.deleted=1, todos.todo.created=1"), 1);
+    // versions that can not be produced fall back to the current one
+    assert_eq!(version("todos.todo.created=0"), TodoCreatedMessage::VERSION);
+    assert_eq!(version("todos.todo.created=9"), TodoCreatedMessage::VERSION);
+    assert_eq!(version("todos.todo.created"), TodoCreatedMessage::VERSION);
+}